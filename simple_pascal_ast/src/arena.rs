@@ -0,0 +1,267 @@
+//! Arena-backed AST with stable node identities.
+//!
+//! [`Ast`] stores every node of a tree in one `Vec` and addresses them by
+//! [`NodeId`], so passes can keep per-node data (types, resolved symbols,
+//! coverage counts) in a [`NodeMap`] instead of inside the tree. Ids are
+//! assigned in allocation order: pre-order for `Ast::from_node`, children
+//! before their parent for `Ast::alloc`.
+//!
+//! The interpreter's type inference runs on the arena. The parser, the
+//! interpreter and the back ends still work on the boxed [`Node`], and
+//! `Ast::from_node` and `Ast::to_node` convert between the two.
+//!
+//! Ids are only meaningful for the arena that made them: `get` and the
+//! other lookups return `None` or `ArenaErr::UnknownNode` for any other id,
+//! only indexing panics.
+
+use std::{fmt, ops::Index};
+
+use crate::{
+    node::*,
+    token::{delim::DelimKind, keyword::Keyword, literal::Literal, op::OpKind},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A node whose children are ids into the owning [`Ast`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AstNode {
+    UnaryOp { op: OpKind, node: NodeId },
+    BinOp { lhs: NodeId, op: OpKind, rhs: NodeId },
+    Literal(Literal),
+    Ident(String),
+    Keyword(Keyword),
+    Delim(DelimKind),
+    Compound(Vec<NodeId>),
+    Call { name: String, args: Vec<NodeId> },
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ast {
+    nodes: Vec<AstNode>,
+    parents: Vec<Option<NodeId>>,
+    root: Option<NodeId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArenaErr {
+    /// The id isn't a node of this arena.
+    UnknownNode(NodeId),
+    /// The node already has a parent, or appears twice among the children.
+    HasParent(NodeId),
+}
+
+impl fmt::Display for ArenaErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaErr::UnknownNode(id) => write!(f, "node {} isn't in the arena", id.0),
+            ArenaErr::HasParent(id) => write!(f, "node {} already has a parent", id.0),
+        }
+    }
+}
+
+impl AstNode {
+    pub fn children(&self) -> Vec<NodeId> {
+        match self {
+            AstNode::UnaryOp { node, .. } => vec![*node],
+            AstNode::BinOp { lhs, rhs, .. } => vec![*lhs, *rhs],
+            AstNode::Compound(children) | AstNode::Call { args: children, .. } => children.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Ast {
+    pub fn from_node(node: &Node) -> Self {
+        let mut ast = Self::default();
+        ast.root = Some(ast.lower(node, None));
+        ast
+    }
+
+    /// `None` for an empty arena.
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    pub fn set_root(&mut self, id: NodeId) -> Result<(), ArenaErr> {
+        self.get(id).ok_or(ArenaErr::UnknownNode(id))?;
+        self.root = Some(id);
+        Ok(())
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&AstNode> {
+        self.nodes.get(id.index())
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        *self.parents.get(id.index())?
+    }
+
+    /// Parent, grandparent and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&id| self.parent(id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Every id in allocation order.
+    pub fn ids(&self) -> impl DoubleEndedIterator<Item = NodeId> + ExactSizeIterator {
+        (0..self.nodes.len() as u32).map(NodeId)
+    }
+
+    /// Adds a node whose children are already in the arena and have no
+    /// parent yet, and becomes their parent. A tree built this way ends
+    /// with its root, so the new node becomes the root.
+    pub fn alloc(&mut self, node: AstNode) -> Result<NodeId, ArenaErr> {
+        let id = NodeId(self.nodes.len() as u32);
+
+        let children = node.children();
+        for (idx, &child) in children.iter().enumerate() {
+            match self.parents.get(child.index()) {
+                None => return Err(ArenaErr::UnknownNode(child)),
+                Some(Some(_)) => return Err(ArenaErr::HasParent(child)),
+                Some(None) if children[..idx].contains(&child) => return Err(ArenaErr::HasParent(child)),
+                Some(None) => (),
+            }
+        }
+
+        for child in children {
+            self.parents[child.index()] = Some(id);
+        }
+        self.nodes.push(node);
+        self.parents.push(None);
+        self.root = Some(id);
+        Ok(id)
+    }
+
+    /// Rebuilds the boxed tree rooted at `id`.
+    pub fn to_node(&self, id: NodeId) -> Result<Node, ArenaErr> {
+        Ok(match self.get(id).ok_or(ArenaErr::UnknownNode(id))? {
+            AstNode::UnaryOp { op, node } => UnaryOp::new(op.clone(), self.to_node(*node)?),
+            AstNode::BinOp { lhs, op, rhs } => {
+                BinOp::new(self.to_node(*lhs)?, op.clone(), self.to_node(*rhs)?)
+            }
+            AstNode::Literal(lit) => Node::Literal(lit.clone()),
+            AstNode::Ident(ident) => Node::Ident(ident.clone()),
+            AstNode::Keyword(keyword) => Node::Keyword(keyword.clone()),
+            AstNode::Delim(delim) => Node::Delim(delim.clone()),
+            AstNode::Compound(children) => CompoundNode::from_list(
+                children
+                    .iter()
+                    .map(|child| self.to_node(*child))
+                    .collect::<Result<_, _>>()?,
+            ),
+            AstNode::Call { name, args } => Call::new(
+                name.clone(),
+                args.iter().map(|arg| self.to_node(*arg)).collect::<Result<_, _>>()?,
+            ),
+            AstNode::None => Node::None,
+        })
+    }
+
+    // reserves the slot first so that ids come out in pre-order
+    fn lower(&mut self, node: &Node, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(AstNode::None);
+        self.parents.push(parent);
+
+        self.nodes[id.index()] = match node {
+            Node::UnaryOp(unary_op) => AstNode::UnaryOp {
+                op: unary_op.op.clone(),
+                node: self.lower(&unary_op.node, Some(id)),
+            },
+            Node::BinOp(bin_op) => AstNode::BinOp {
+                lhs: self.lower(&bin_op.lhs, Some(id)),
+                op: bin_op.op.clone(),
+                rhs: self.lower(&bin_op.rhs, Some(id)),
+            },
+            Node::Literal(lit) => AstNode::Literal(lit.clone()),
+            Node::Ident(ident) => AstNode::Ident(ident.clone()),
+            Node::Keyword(keyword) => AstNode::Keyword(keyword.clone()),
+            Node::Delim(delim) => AstNode::Delim(delim.clone()),
+            Node::Compound(compound) => AstNode::Compound(
+                compound
+                    .children
+                    .iter()
+                    .map(|child| self.lower(child, Some(id)))
+                    .collect(),
+            ),
+            Node::Call(call) => AstNode::Call {
+                name: call.name.clone(),
+                args: call.args.iter().map(|arg| self.lower(arg, Some(id))).collect(),
+            },
+            Node::None => AstNode::None,
+        };
+        id
+    }
+}
+
+/// Panics if the id isn't a node of this arena, see `Ast::get`.
+impl Index<NodeId> for Ast {
+    type Output = AstNode;
+
+    fn index(&self, id: NodeId) -> &AstNode {
+        &self.nodes[id.index()]
+    }
+}
+
+/// Dense side table keyed by [`NodeId`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeMap<T> {
+    values: Vec<Option<T>>,
+}
+
+impl<T> Default for NodeMap<T> {
+    fn default() -> Self {
+        Self { values: Vec::new() }
+    }
+}
+
+impl<T> NodeMap<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert(&mut self, id: NodeId, value: T) -> Option<T> {
+        if self.values.len() <= id.index() {
+            self.values.resize_with(id.index() + 1, || None);
+        }
+        self.values[id.index()].replace(value)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.values.get(id.index())?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.values.get_mut(id.index())?.as_mut()
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.values.get_mut(id.index())?.take()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, value)| Some((NodeId(idx as u32), value.as_ref()?)))
+    }
+}
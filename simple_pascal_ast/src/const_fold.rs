@@ -0,0 +1,159 @@
+//! Constant folding and algebraic simplification.
+//!
+//! [`ConstFolder`] is a [`Folder`] that evaluates operators whose operands
+//! are literals, so `10 * 2 / 4` becomes `5.0`, and removes `+ 0`, `- 0`
+//! and `* 1`. Literal types are kept as the interpreter would see them:
+//!
+//! - `+`, `-`, `*` of two INTEGERs is an INTEGER, with a REAL operand a REAL
+//! - `/` and `^` are always REAL
+//! - `DIV`, `MOD` and `%` only fold INTEGERs
+//!
+//! Anything that fails at run time is left alone: division by zero, a
+//! negative ISO `MOD` divisor, results outside the dialect's integer range
+//! or non-finite reals, and `DIV`/`MOD` with a REAL operand, which Pascal
+//! rejects. The identities only use an INTEGER `0` or `1`, which never
+//! change the type of the other operand.
+//! Negated literals become negative literals, e.g. `-(2 + 3)` is `-5`.
+
+use crate::{
+    dialect::Dialect,
+    fold::{self, Folder},
+    node::*,
+    token::{literal::Literal, op::OpKind},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Integer(i64),
+    Real(f64),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstFolder {
+    dialect: Dialect,
+}
+
+impl ConstFolder {
+    pub fn new(dialect: Dialect) -> Self {
+        Self { dialect }
+    }
+
+    fn fold_bin_value(&self, lhs: Value, op: &OpKind, rhs: Value) -> Option<Value> {
+        use Value::*;
+
+        let value = match (op, lhs, rhs) {
+            (OpKind::Plus, Integer(lhs), Integer(rhs)) => Integer(lhs.checked_add(rhs)?),
+            (OpKind::Minus, Integer(lhs), Integer(rhs)) => Integer(lhs.checked_sub(rhs)?),
+            (OpKind::Star, Integer(lhs), Integer(rhs)) => Integer(lhs.checked_mul(rhs)?),
+            (OpKind::Div, Integer(_), Integer(0)) => return None,
+            (OpKind::Div, Integer(lhs), Integer(rhs)) => Integer(lhs.checked_div(rhs)?),
+            (OpKind::Mod | OpKind::Percent, Integer(_), Integer(0)) => return None,
+            (OpKind::Mod | OpKind::Percent, Integer(lhs), Integer(rhs)) => match self.dialect {
+                Dialect::Iso7185 if rhs < 0 => return None,
+                Dialect::Iso7185 => Integer(lhs.checked_rem_euclid(rhs)?),
+                _ => Integer(lhs.checked_rem(rhs)?),
+            },
+            (OpKind::Div | OpKind::Mod | OpKind::Percent, _, _) => return None,
+            (OpKind::Plus, lhs, rhs) => Real(lhs.real() + rhs.real()),
+            (OpKind::Minus, lhs, rhs) => Real(lhs.real() - rhs.real()),
+            (OpKind::Star, lhs, rhs) => Real(lhs.real() * rhs.real()),
+            (OpKind::Slash, _, rhs) if rhs.real() == 0.0 => return None,
+            (OpKind::Slash, lhs, rhs) => Real(lhs.real() / rhs.real()),
+            (OpKind::Caret, lhs, rhs) => Real(lhs.real().powf(rhs.real())),
+            _ => return None,
+        };
+
+        self.checked(value)
+    }
+
+    fn checked(&self, value: Value) -> Option<Value> {
+        match value {
+            Value::Integer(int) if int.unsigned_abs() > self.dialect.max_int() as u64 => None,
+            Value::Real(real) if !real.is_finite() => None,
+            value => Some(value),
+        }
+    }
+}
+
+impl Folder for ConstFolder {
+    fn fold_bin_op(&mut self, bin_op: BinOp) -> Node {
+        if bin_op.op == OpKind::AssignEq {
+            let rhs = self.fold_node(*bin_op.rhs);
+            return BinOp::new(*bin_op.lhs, bin_op.op, rhs);
+        }
+
+        let (lhs, op, rhs) = match fold::fold_bin_op(self, bin_op) {
+            Node::BinOp(bin_op) => (*bin_op.lhs, bin_op.op, *bin_op.rhs),
+            node => return node,
+        };
+
+        if let (Some(lhs), Some(rhs)) = (value(&lhs), value(&rhs)) {
+            if let Some(value) = self.fold_bin_value(lhs, &op, rhs) {
+                return value.into_node();
+            }
+        }
+
+        match (value(&lhs), &op, value(&rhs)) {
+            (_, OpKind::Plus | OpKind::Minus, Some(Value::Integer(0)))
+            | (_, OpKind::Star, Some(Value::Integer(1))) => lhs,
+            (Some(Value::Integer(0)), OpKind::Plus, _)
+            | (Some(Value::Integer(1)), OpKind::Star, _) => rhs,
+            _ => BinOp::new(lhs, op, rhs),
+        }
+    }
+
+    fn fold_unary_op(&mut self, unary_op: UnaryOp) -> Node {
+        let node = self.fold_node(*unary_op.node);
+
+        match (&unary_op.op, value(&node)) {
+            (OpKind::Plus, Some(_)) => node,
+            (OpKind::Minus, Some(Value::Integer(int))) => match int.checked_neg() {
+                Some(int) => Value::Integer(int).into_node(),
+                None => UnaryOp::new(unary_op.op, node),
+            },
+            (OpKind::Minus, Some(Value::Real(real))) => Value::Real(-real).into_node(),
+            // `- -x` is `x`
+            (OpKind::Minus, None) => match node {
+                Node::UnaryOp(inner) if inner.op == OpKind::Minus => *inner.node,
+                node => UnaryOp::new(unary_op.op, node),
+            },
+            _ => UnaryOp::new(unary_op.op, node),
+        }
+    }
+}
+
+impl Value {
+    fn real(self) -> f64 {
+        match self {
+            Value::Integer(int) => int as f64,
+            Value::Real(real) => real,
+        }
+    }
+
+    fn into_node(self) -> Node {
+        Node::Literal(match self {
+            Value::Integer(int) => Literal::Integer(int.to_string()),
+            // `Display` never uses an exponent, which the lexer can't read
+            Value::Real(real) => {
+                let mut text = real.to_string();
+                if !text.contains('.') {
+                    text.push_str(".0");
+                }
+                Literal::Float(text)
+            }
+        })
+    }
+}
+
+fn value(node: &Node) -> Option<Value> {
+    match node {
+        Node::Literal(Literal::Integer(int)) => int.parse().ok().map(Value::Integer),
+        Node::Literal(Literal::Float(real)) => real.parse().ok().map(Value::Real),
+        _ => None,
+    }
+}
+
+/// Folds `node` with the rules of `dialect`.
+pub fn const_fold(node: Node, dialect: Dialect) -> Node {
+    ConstFolder::new(dialect).fold_node(node)
+}
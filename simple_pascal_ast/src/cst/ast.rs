@@ -0,0 +1,290 @@
+//! Typed views over a [`SyntaxNode`] tree.
+//!
+//! Views borrow the tree and never own data; `to_node` lowers them to the
+//! abstract [`Node`] the interpreter works with.
+
+use crate::{
+    dialect::Dialect,
+    node::{BinOp, Call, CompoundNode, Node, NodeList, UnaryOp},
+    token::{
+        literal::{self, decode_string},
+        op::OpKind,
+    },
+};
+
+use super::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+macro_rules! ast_node {
+    ($name:ident, $kind:ident) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name<'a>(&'a SyntaxNode);
+
+        impl<'a> $name<'a> {
+            pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+                if node.kind() == SyntaxKind::$kind {
+                    Some(Self(node))
+                } else {
+                    None
+                }
+            }
+
+            pub fn syntax(&self) -> &'a SyntaxNode {
+                self.0
+            }
+        }
+    };
+}
+
+ast_node!(Program, Program);
+ast_node!(Compound, Compound);
+ast_node!(Assignment, Assignment);
+ast_node!(ExprStmt, ExprStmt);
+ast_node!(EmptyStmt, EmptyStmt);
+ast_node!(BinExpr, BinExpr);
+ast_node!(UnaryExpr, UnaryExpr);
+ast_node!(ParenExpr, ParenExpr);
+ast_node!(CallExpr, CallExpr);
+ast_node!(Literal, Literal);
+ast_node!(NameRef, NameRef);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Statement<'a> {
+    Compound(Compound<'a>),
+    Assignment(Assignment<'a>),
+    Expr(ExprStmt<'a>),
+    Empty(EmptyStmt<'a>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Expr<'a> {
+    Bin(BinExpr<'a>),
+    Unary(UnaryExpr<'a>),
+    Paren(ParenExpr<'a>),
+    Call(CallExpr<'a>),
+    Literal(Literal<'a>),
+    Name(NameRef<'a>),
+}
+
+fn significant_token(node: &SyntaxNode) -> Option<&SyntaxToken> {
+    node.child_tokens().find(|token| !token.kind().is_trivia())
+}
+
+impl<'a> Program<'a> {
+    pub fn compound(&self) -> Option<Compound<'a>> {
+        self.0.child_nodes().find_map(Compound::cast)
+    }
+
+    pub fn to_node(&self) -> Node {
+        self.compound().map_or(Node::None, |compound| compound.to_node())
+    }
+}
+
+impl<'a> Compound<'a> {
+    pub fn statements(&self) -> impl Iterator<Item = Statement<'a>> {
+        self.0.child_nodes().filter_map(Statement::cast)
+    }
+
+    pub fn to_node(&self) -> Node {
+        CompoundNode::from_list(self.statements().map(|stmt| stmt.to_node()).collect::<NodeList>())
+    }
+}
+
+impl<'a> Statement<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        use SyntaxKind as K;
+
+        match node.kind() {
+            K::Compound => Some(Statement::Compound(Compound(node))),
+            K::Assignment => Some(Statement::Assignment(Assignment(node))),
+            K::ExprStmt => Some(Statement::Expr(ExprStmt(node))),
+            K::EmptyStmt => Some(Statement::Empty(EmptyStmt(node))),
+            _ => None,
+        }
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        match self {
+            Statement::Compound(stmt) => stmt.syntax(),
+            Statement::Assignment(stmt) => stmt.syntax(),
+            Statement::Expr(stmt) => stmt.syntax(),
+            Statement::Empty(stmt) => stmt.syntax(),
+        }
+    }
+
+    pub fn to_node(&self) -> Node {
+        match self {
+            Statement::Compound(compound) => compound.to_node(),
+            Statement::Assignment(assignment) => assignment.to_node(),
+            Statement::Expr(stmt) => stmt.expr().map_or(Node::None, |expr| expr.to_node()),
+            Statement::Empty(_) => Node::None,
+        }
+    }
+}
+
+impl<'a> Assignment<'a> {
+    pub fn target(&self) -> Option<NameRef<'a>> {
+        self.0.child_nodes().find_map(NameRef::cast)
+    }
+
+    pub fn value(&self) -> Option<Expr<'a>> {
+        self.0.child_nodes().skip(1).find_map(Expr::cast)
+    }
+
+    pub fn to_node(&self) -> Node {
+        BinOp::new(
+            self.target().map_or(Node::None, |target| target.to_node()),
+            OpKind::AssignEq,
+            self.value().map_or(Node::None, |value| value.to_node()),
+        )
+    }
+}
+
+impl<'a> ExprStmt<'a> {
+    pub fn expr(&self) -> Option<Expr<'a>> {
+        self.0.child_nodes().find_map(Expr::cast)
+    }
+}
+
+impl<'a> Expr<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        use SyntaxKind as K;
+
+        match node.kind() {
+            K::BinExpr => Some(Expr::Bin(BinExpr(node))),
+            K::UnaryExpr => Some(Expr::Unary(UnaryExpr(node))),
+            K::ParenExpr => Some(Expr::Paren(ParenExpr(node))),
+            K::CallExpr => Some(Expr::Call(CallExpr(node))),
+            K::Literal => Some(Expr::Literal(Literal(node))),
+            K::NameRef => Some(Expr::Name(NameRef(node))),
+            _ => None,
+        }
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        match self {
+            Expr::Bin(expr) => expr.syntax(),
+            Expr::Unary(expr) => expr.syntax(),
+            Expr::Paren(expr) => expr.syntax(),
+            Expr::Call(expr) => expr.syntax(),
+            Expr::Literal(expr) => expr.syntax(),
+            Expr::Name(expr) => expr.syntax(),
+        }
+    }
+
+    pub fn to_node(&self) -> Node {
+        match self {
+            Expr::Bin(expr) => expr.to_node(),
+            Expr::Unary(expr) => expr.to_node(),
+            Expr::Paren(expr) => expr.expr().map_or(Node::None, |expr| expr.to_node()),
+            Expr::Call(expr) => expr.to_node(),
+            Expr::Literal(expr) => expr.to_node(),
+            Expr::Name(expr) => expr.to_node(),
+        }
+    }
+}
+
+impl<'a> BinExpr<'a> {
+    pub fn lhs(&self) -> Option<Expr<'a>> {
+        self.0.child_nodes().find_map(Expr::cast)
+    }
+
+    pub fn rhs(&self) -> Option<Expr<'a>> {
+        self.0.child_nodes().skip(1).find_map(Expr::cast)
+    }
+
+    pub fn op_token(&self) -> Option<&'a SyntaxToken> {
+        significant_token(self.0)
+    }
+
+    pub fn op(&self) -> Option<OpKind> {
+        self.op_token()?.kind().to_op()
+    }
+
+    pub fn to_node(&self) -> Node {
+        match (self.lhs(), self.op(), self.rhs()) {
+            (Some(lhs), Some(op), Some(rhs)) => BinOp::new(lhs.to_node(), op, rhs.to_node()),
+            _ => Node::None,
+        }
+    }
+}
+
+impl<'a> UnaryExpr<'a> {
+    pub fn op_token(&self) -> Option<&'a SyntaxToken> {
+        significant_token(self.0)
+    }
+
+    pub fn op(&self) -> Option<OpKind> {
+        self.op_token()?.kind().to_op()
+    }
+
+    pub fn operand(&self) -> Option<Expr<'a>> {
+        self.0.child_nodes().find_map(Expr::cast)
+    }
+
+    pub fn to_node(&self) -> Node {
+        match (self.op(), self.operand()) {
+            (Some(op), Some(operand)) => UnaryOp::new(op, operand.to_node()),
+            _ => Node::None,
+        }
+    }
+}
+
+impl<'a> ParenExpr<'a> {
+    pub fn expr(&self) -> Option<Expr<'a>> {
+        self.0.child_nodes().find_map(Expr::cast)
+    }
+}
+
+impl<'a> CallExpr<'a> {
+    pub fn callee(&self) -> Option<NameRef<'a>> {
+        self.0.child_nodes().find_map(NameRef::cast)
+    }
+
+    // the callee is a `NameRef` too, the arguments follow it
+    pub fn args(&self) -> impl Iterator<Item = Expr<'a>> {
+        self.0.child_nodes().skip(1).filter_map(Expr::cast)
+    }
+
+    pub fn to_node(&self) -> Node {
+        match self.callee().and_then(|callee| callee.name()) {
+            Some(name) => Call::new(name.to_string(), self.args().map(|arg| arg.to_node()).collect()),
+            None => Node::None,
+        }
+    }
+}
+
+impl<'a> Literal<'a> {
+    pub fn token(&self) -> Option<&'a SyntaxToken> {
+        significant_token(self.0)
+    }
+
+    pub fn literal(&self) -> Option<literal::Literal> {
+        let token = self.token()?;
+
+        match token.kind() {
+            SyntaxKind::IntLiteral => Some(literal::Literal::Integer(token.text().to_string())),
+            SyntaxKind::FloatLiteral => Some(literal::Literal::Float(token.text().to_string())),
+            // the dialect was checked while parsing, the most permissive
+            // one decodes whatever made it into the tree
+            SyntaxKind::StringLiteral => {
+                decode_string(token.text(), Dialect::FreePascal).map(literal::Literal::String)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_node(&self) -> Node {
+        self.literal().map_or(Node::None, Node::Literal)
+    }
+}
+
+impl<'a> NameRef<'a> {
+    pub fn name(&self) -> Option<&'a str> {
+        Some(significant_token(self.0)?.text())
+    }
+
+    pub fn to_node(&self) -> Node {
+        self.name()
+            .map_or(Node::None, |name| Node::Ident(name.to_string()))
+    }
+}
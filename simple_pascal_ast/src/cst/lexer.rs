@@ -0,0 +1,183 @@
+use crate::{
+    dialect::Dialect,
+    token::{
+        keyword::{parse_keyword, Keyword},
+        literal::string_len,
+        op::{parse_word_op, OpKind},
+    },
+};
+
+use super::{SyntaxKind, SyntaxToken};
+
+fn is_word(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+// Splits `text` into tokens without dropping anything: concatenating the
+// texts of the returned tokens always yields `text` back.
+pub(super) fn tokenize(text: &str, dialect: Dialect) -> Vec<SyntaxToken> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while let Some(ch) = rest.chars().next() {
+        let (kind, len) = next_token(ch, rest, dialect);
+        tokens.push(SyntaxToken::new(kind, &rest[..len]));
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+fn next_token(ch: char, rest: &str, dialect: Dialect) -> (SyntaxKind, usize) {
+    use SyntaxKind::*;
+
+    if ch.is_whitespace() {
+        return (Whitespace, take_while(rest, char::is_whitespace));
+    }
+    if ch == '{' || rest.starts_with("(*") {
+        return comment(rest, dialect);
+    }
+    if dialect.line_comments() && rest.starts_with("//") {
+        return (Comment, take_while(rest, |ch| ch != '\n'));
+    }
+    if ch == '\'' || ch == '#' && dialect.char_codes() {
+        return (StringLiteral, string_len(rest, dialect));
+    }
+    if rest.starts_with(":=") {
+        return (AssignEq, 2);
+    }
+    if ch.is_ascii_digit() {
+        return number(rest);
+    }
+    if is_word(ch) {
+        let len = take_while(rest, is_word);
+        let word = &rest[..len];
+        let kind = match (parse_keyword(word), parse_word_op(word)) {
+            (Some(Keyword::Begin), _) => BeginKw,
+            (Some(Keyword::End), _) => EndKw,
+            (_, Some(OpKind::Div)) => DivKw,
+            (_, Some(_)) => ModKw,
+            _ if dialect.is_reserved(word) => ReservedWord,
+            _ => Ident,
+        };
+        return (kind, len);
+    }
+
+    let kind = match ch {
+        '+' => Plus,
+        '-' => Minus,
+        '*' => Star,
+        '/' => Slash,
+        '^' => Caret,
+        '%' => Percent,
+        '(' => LParen,
+        ')' => RParen,
+        '.' => Dot,
+        ';' => Semicolon,
+        ',' => Comma,
+        _ => Error,
+    };
+    (kind, ch.len_utf8())
+}
+
+fn take_while(text: &str, pred: impl Fn(char) -> bool) -> usize {
+    text.find(|ch| !pred(ch)).unwrap_or(text.len())
+}
+
+// mirrors `Lexer::skip_comment`
+fn comment(text: &str, dialect: Dialect) -> (SyntaxKind, usize) {
+    let brace = text.starts_with('{');
+    let open_len = if brace { 1 } else { 2 };
+    let body = &text[open_len..];
+
+    let close = match (body.find('}'), body.find("*)")) {
+        (Some(brace_end), Some(paren_end)) if dialect.mixed_comment_delims() => {
+            Some(brace_end.min(paren_end))
+        }
+        (Some(end), _) if brace || dialect.mixed_comment_delims() => Some(end),
+        (_, Some(end)) if !brace || dialect.mixed_comment_delims() => Some(end),
+        _ => None,
+    };
+
+    // an unterminated comment swallows the rest of the input as an error
+    match close {
+        Some(end) => {
+            let close_len = if body[end..].starts_with('}') { 1 } else { 2 };
+            (SyntaxKind::Comment, open_len + end + close_len)
+        }
+        None => (SyntaxKind::Error, text.len()),
+    }
+}
+
+// mirrors `Lexer::number`: a dot right after the digits always starts
+// the fractional part
+fn number(text: &str) -> (SyntaxKind, usize) {
+    let int_len = take_while(text, |ch| ch.is_ascii_digit());
+
+    match text[int_len..].strip_prefix('.') {
+        Some(fraction) => (
+            SyntaxKind::FloatLiteral,
+            int_len + 1 + take_while(fraction, |ch| ch.is_ascii_digit()),
+        ),
+        None => (SyntaxKind::IntLiteral, int_len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless() {
+        let text = "BEGIN { set x }\n\tx := 2.5 (* two *) ^ 3; ?\nEND.  ";
+        let tokens = tokenize(text, Dialect::default());
+
+        assert_eq!(text, tokens.iter().map(|t| t.text()).collect::<String>());
+    }
+
+    #[test]
+    fn kinds() {
+        use SyntaxKind::*;
+
+        let kinds: Vec<_> = tokenize("a:= {c}(1.5)", Dialect::default())
+            .iter()
+            .map(|t| t.kind())
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![Ident, AssignEq, Whitespace, Comment, LParen, FloatLiteral, RParen]
+        );
+    }
+
+    #[test]
+    fn dialect_tokens() {
+        use SyntaxKind::*;
+
+        let kinds = |text, dialect| -> Vec<_> {
+            tokenize(text, dialect)
+                .iter()
+                .map(|t| t.kind())
+                .filter(|kind| !kind.is_trivia())
+                .collect()
+        };
+
+        assert_eq!(
+            kinds("s := 'a'#13'b' mod x // c", Dialect::FreePascal),
+            vec![Ident, AssignEq, StringLiteral, ModKw, Ident]
+        );
+        assert_eq!(
+            kinds("s := 'a'#13 xor", Dialect::Iso7185),
+            vec![Ident, AssignEq, StringLiteral, Error, IntLiteral, Ident]
+        );
+        assert_eq!(kinds("{ a *) b", Dialect::Iso7185), vec![Ident]);
+        assert_eq!(kinds("{ a *) b", Dialect::TurboPascal), vec![Error]);
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let tokens = tokenize("x (* oops", Dialect::default());
+
+        assert_eq!(tokens.last().unwrap().kind(), SyntaxKind::Error);
+        assert_eq!(tokens.last().unwrap().text(), "(* oops");
+    }
+}
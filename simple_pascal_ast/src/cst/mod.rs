@@ -0,0 +1,208 @@
+//! Lossless concrete syntax tree.
+//!
+//! Unlike [`crate::node::Node`], the CST keeps every byte of the source:
+//! whitespace and comments are stored as trivia tokens next to the
+//! significant ones, so `parse(text)?.to_string() == text` always holds.
+//! Typed views over the tree live in [`ast`].
+
+pub mod ast;
+mod lexer;
+mod parser;
+
+use std::fmt;
+
+use crate::token::op::OpKind;
+
+pub use parser::{parse, parse_with, parse_with_line, parse_with_max_depth};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // trivia
+    Whitespace,
+    Comment,
+
+    // tokens
+    Ident,
+    IntLiteral,
+    FloatLiteral,
+    StringLiteral,
+    BeginKw,
+    EndKw,
+    DivKw,
+    ModKw,
+    // reserved by the dialect but not supported by the parser
+    ReservedWord,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Percent,
+    AssignEq,
+    LParen,
+    RParen,
+    Dot,
+    Semicolon,
+    Comma,
+    Error,
+
+    // nodes
+    Program,
+    Compound,
+    Assignment,
+    ExprStmt,
+    EmptyStmt,
+    BinExpr,
+    UnaryExpr,
+    ParenExpr,
+    CallExpr,
+    Literal,
+    NameRef,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+
+    pub fn to_op(self) -> Option<OpKind> {
+        use SyntaxKind::*;
+
+        match self {
+            Plus => Some(OpKind::Plus),
+            Minus => Some(OpKind::Minus),
+            Star => Some(OpKind::Star),
+            Slash => Some(OpKind::Slash),
+            Caret => Some(OpKind::Caret),
+            Percent => Some(OpKind::Percent),
+            DivKw => Some(OpKind::Div),
+            ModKw => Some(OpKind::Mod),
+            AssignEq => Some(OpKind::AssignEq),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    kind: SyntaxKind,
+    children: Vec<SyntaxElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxToken {
+    pub fn new(kind: SyntaxKind, text: impl Into<String>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl SyntaxNode {
+    pub fn new(kind: SyntaxKind, children: Vec<SyntaxElement>) -> Self {
+        Self { kind, children }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Direct child tokens, trivia included.
+    pub fn child_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    /// Every token under this node in source order, trivia included.
+    pub fn descendant_tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.children.iter().map(SyntaxElement::text_len).sum()
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        match self {
+            SyntaxElement::Node(node) => node.text_len(),
+            SyntaxElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            fmt::Display::fmt(child, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(node) => fmt::Display::fmt(node, f),
+            SyntaxElement::Token(token) => fmt::Display::fmt(token, f),
+        }
+    }
+}
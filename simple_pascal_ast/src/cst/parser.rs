@@ -0,0 +1,350 @@
+use crate::{
+    dialect::Dialect,
+    parser::{ParserErr, DEFAULT_MAX_DEPTH, DEFAULT_MAX_OPERATORS},
+    token::{
+        literal::decode_string,
+        op::{Fixity, EXPR_PRECEDENCE},
+    },
+};
+
+use super::{lexer::tokenize, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+
+/// Parses `text` into a lossless syntax tree rooted at a `Program` node.
+///
+/// The grammar is the one accepted by [`crate::parser::Parser::calculator`].
+pub fn parse(text: &str) -> Result<SyntaxNode, ParserErr> {
+    parse_with(text, Dialect::default())
+}
+
+pub fn parse_with(text: &str, dialect: Dialect) -> Result<SyntaxNode, ParserErr> {
+    parse_with_max_depth(text, dialect, DEFAULT_MAX_DEPTH)
+}
+
+/// Like `parse_with`, rejecting blocks and expressions nested deeper than
+/// `max_depth` as `Parser::set_max_depth` does. Chains of operators are
+/// limited to `DEFAULT_MAX_OPERATORS`.
+pub fn parse_with_max_depth(text: &str, dialect: Dialect, max_depth: usize) -> Result<SyntaxNode, ParserErr> {
+    CstParser::new(text, dialect, max_depth).program()
+}
+
+/// Like `parse_with`, failing with the line where the error was found too,
+/// the one `Parser::line` gives after a failed `parse`.
+pub fn parse_with_line(text: &str, dialect: Dialect) -> Result<SyntaxNode, (u32, ParserErr)> {
+    let mut parser = CstParser::new(text, dialect, DEFAULT_MAX_DEPTH);
+    parser.program().map_err(|err| (parser.line(), err))
+}
+
+struct CstParser {
+    tokens: Vec<SyntaxToken>,
+    pos: usize,
+    dialect: Dialect,
+    max_depth: usize,
+    depth: usize,
+    // operators of the chains the current operand is part of
+    operators: usize,
+}
+
+type Children = Vec<SyntaxElement>;
+
+impl CstParser {
+    fn new(text: &str, dialect: Dialect, max_depth: usize) -> Self {
+        CstParser {
+            tokens: tokenize(text, dialect),
+            pos: 0,
+            dialect,
+            max_depth,
+            depth: 0,
+            operators: 0,
+        }
+    }
+
+    // line of the next significant token
+    fn line(&self) -> u32 {
+        let next = self.tokens[self.pos..]
+            .iter()
+            .position(|token| !token.kind().is_trivia())
+            .map_or(self.tokens.len(), |idx| self.pos + idx);
+        let newlines: usize = self.tokens[..next].iter().map(|token| token.text().matches('\n').count()).sum();
+        newlines as u32 + 1
+    }
+
+    fn program(&mut self) -> Result<SyntaxNode, ParserErr> {
+        let mut children = Children::new();
+
+        children.push(SyntaxElement::Node(self.compound()?));
+        self.expect(
+            SyntaxKind::Dot,
+            &mut children,
+            ParserErr::MissingToken(format!(
+                "Program must end up with a dot, got {:?}",
+                self.peek()
+            )),
+        )?;
+        self.trivia(&mut children);
+
+        if self.pos != self.tokens.len() {
+            return Err(ParserErr::InvalidExpr);
+        }
+
+        Ok(SyntaxNode::new(SyntaxKind::Program, children))
+    }
+
+    fn compound(&mut self) -> Result<SyntaxNode, ParserErr> {
+        let mut children = Children::new();
+
+        self.expect(
+            SyntaxKind::BeginKw,
+            &mut children,
+            ParserErr::MissingToken(format!("Expected keyword `BEGIN`, got {:?}", self.peek())),
+        )?;
+
+        children.push(SyntaxElement::Node(self.statement()?));
+        while self.peek() == Some(SyntaxKind::Semicolon) {
+            self.bump(&mut children);
+            children.push(SyntaxElement::Node(self.statement()?));
+        }
+
+        self.expect(
+            SyntaxKind::EndKw,
+            &mut children,
+            ParserErr::MissingToken(format!("Expected keyword `END`, got {:?}", self.peek())),
+        )?;
+
+        Ok(SyntaxNode::new(SyntaxKind::Compound, children))
+    }
+
+    fn statement(&mut self) -> Result<SyntaxNode, ParserErr> {
+        use SyntaxKind::*;
+
+        match self.peek() {
+            Some(BeginKw) => self.nest(Self::compound),
+            Some(EndKw) | Some(Semicolon) | Some(Dot) | None => {
+                Ok(SyntaxNode::new(EmptyStmt, Children::new()))
+            }
+            Some(Ident) if self.peek_nth(1) == Some(AssignEq) => {
+                let mut children = Children::new();
+
+                let mut target = Children::new();
+                self.bump(&mut target);
+                children.push(SyntaxElement::Node(SyntaxNode::new(NameRef, target)));
+
+                self.bump(&mut children);
+                children.push(SyntaxElement::Node(self.expr()?));
+
+                Ok(SyntaxNode::new(Assignment, children))
+            }
+            _ => Ok(SyntaxNode::new(
+                ExprStmt,
+                vec![SyntaxElement::Node(self.expr()?)],
+            )),
+        }
+    }
+
+    fn expr(&mut self) -> Result<SyntaxNode, ParserErr> {
+        self.expr_bp(EXPR_PRECEDENCE)
+    }
+
+    // same precedence climbing as `Parser::expr_bp`
+    fn expr_bp(&mut self, min_precedence: u8) -> Result<SyntaxNode, ParserErr> {
+        self.nest(|parser| parser.climb(min_precedence))
+    }
+
+    // same limit as `Parser::nest`
+    fn nest(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<SyntaxNode, ParserErr>,
+    ) -> Result<SyntaxNode, ParserErr> {
+        if self.depth >= self.max_depth {
+            return Err(ParserErr::NestingTooDeep(self.max_depth));
+        }
+
+        self.depth += 1;
+        let res = parse(self);
+        self.depth -= 1;
+        res
+    }
+
+    fn climb(&mut self, min_precedence: u8) -> Result<SyntaxNode, ParserErr> {
+        let operators = self.operators;
+        let res = self.fold(min_precedence);
+        self.operators = operators;
+        res
+    }
+
+    // chains count against `DEFAULT_MAX_OPERATORS`, like in `Parser::fold`
+    fn fold(&mut self, min_precedence: u8) -> Result<SyntaxNode, ParserErr> {
+        let mut res = self.factor()?;
+
+        while let Some(op) = self.peek().and_then(SyntaxKind::to_op) {
+            let precedence = match op.precedence() {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            if self.operators >= DEFAULT_MAX_OPERATORS {
+                return Err(ParserErr::TooManyOperators(DEFAULT_MAX_OPERATORS));
+            }
+            self.operators += 1;
+            let next_precedence = match op.fixity() {
+                Fixity::Right => precedence,
+                _ => precedence + 1,
+            };
+
+            let mut children = vec![SyntaxElement::Node(res)];
+            self.bump(&mut children);
+            children.push(SyntaxElement::Node(self.expr_bp(next_precedence)?));
+            res = SyntaxNode::new(SyntaxKind::BinExpr, children);
+        }
+        Ok(res)
+    }
+
+    fn factor(&mut self) -> Result<SyntaxNode, ParserErr> {
+        use SyntaxKind::*;
+
+        let mut children = Children::new();
+
+        let kind = match self.peek() {
+            Some(IntLiteral) | Some(FloatLiteral) | Some(StringLiteral) => {
+                self.bump(&mut children);
+                self.check_literal(children.last())?;
+                Literal
+            }
+            Some(ReservedWord) => {
+                self.bump(&mut children);
+                return Err(ParserErr::InvalidToken(format!(
+                    "`{}` is a reserved word in {} and isn't supported yet",
+                    children.last().map(|word| word.to_string()).unwrap_or_default(),
+                    self.dialect
+                )));
+            }
+            Some(Ident) if self.peek_nth(1) == Some(LParen) => {
+                let mut name = Children::new();
+                self.bump(&mut name);
+                children.push(SyntaxElement::Node(SyntaxNode::new(NameRef, name)));
+
+                self.bump(&mut children);
+                if self.peek() != Some(RParen) {
+                    children.push(SyntaxElement::Node(self.expr()?));
+                    while self.peek() == Some(Comma) {
+                        self.bump(&mut children);
+                        children.push(SyntaxElement::Node(self.expr()?));
+                    }
+                }
+                self.expect(
+                    RParen,
+                    &mut children,
+                    ParserErr::TokenMismatch(format!(
+                        "Expected `,` or `)` in the arguments of a call, got {:?}",
+                        self.peek()
+                    )),
+                )?;
+                CallExpr
+            }
+            Some(Ident) => {
+                self.bump(&mut children);
+                NameRef
+            }
+            Some(LParen) => {
+                self.bump(&mut children);
+                children.push(SyntaxElement::Node(self.expr()?));
+                self.expect(
+                    RParen,
+                    &mut children,
+                    ParserErr::TokenMismatch(String::from("Expected close delimiter for Paren")),
+                )?;
+                ParenExpr
+            }
+            Some(Error) => return Err(ParserErr::Undefined),
+            Some(kind) => match kind.to_op().map(|op| (op.prefix_precedence(), op)) {
+                Some((Some(precedence), _)) => {
+                    self.bump(&mut children);
+                    children.push(SyntaxElement::Node(self.expr_bp(precedence)?));
+                    UnaryExpr
+                }
+                Some((None, op)) => {
+                    return Err(ParserErr::TokenMismatch(format!(
+                        "Unary operator supports only `+` and `-`, got {:?}",
+                        op
+                    )))
+                }
+                None => return Err(ParserErr::InvalidExpr),
+            },
+            None => return Err(ParserErr::InvalidExpr),
+        };
+
+        Ok(SyntaxNode::new(kind, children))
+    }
+
+    // same checks as `Parser::factor` and the lexer
+    fn check_literal(&self, lit: Option<&SyntaxElement>) -> Result<(), ParserErr> {
+        let (kind, text) = match lit {
+            Some(SyntaxElement::Token(token)) => (token.kind(), token.text()),
+            _ => return Ok(()),
+        };
+
+        match kind {
+            SyntaxKind::IntLiteral => match text.parse::<i64>() {
+                Ok(value) if value <= self.dialect.max_int() => Ok(()),
+                _ => Err(ParserErr::InvalidToken(format!(
+                    "Integer literal `{}` exceeds the largest {} integer {}",
+                    text,
+                    self.dialect,
+                    self.dialect.max_int()
+                ))),
+            },
+            SyntaxKind::StringLiteral => match decode_string(text, self.dialect) {
+                Some(_) => Ok(()),
+                None => Err(ParserErr::InvalidToken(format!(
+                    "Invalid or unterminated string literal in {}",
+                    self.dialect
+                ))),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn peek(&self) -> Option<SyntaxKind> {
+        self.peek_nth(0)
+    }
+
+    // kind of the n-th significant token ahead
+    fn peek_nth(&self, n: usize) -> Option<SyntaxKind> {
+        self.tokens[self.pos..]
+            .iter()
+            .map(SyntaxToken::kind)
+            .filter(|kind| !kind.is_trivia())
+            .nth(n)
+    }
+
+    fn trivia(&mut self, children: &mut Children) {
+        while let Some(token) = self.tokens.get(self.pos) {
+            if !token.kind().is_trivia() {
+                break;
+            }
+            children.push(SyntaxElement::Token(token.clone()));
+            self.pos += 1;
+        }
+    }
+
+    // moves the pending trivia and the next significant token into `children`
+    fn bump(&mut self, children: &mut Children) {
+        self.trivia(children);
+
+        if let Some(token) = self.tokens.get(self.pos) {
+            children.push(SyntaxElement::Token(token.clone()));
+            self.pos += 1;
+        }
+    }
+
+    fn expect(
+        &mut self,
+        kind: SyntaxKind,
+        children: &mut Children,
+        err: ParserErr,
+    ) -> Result<(), ParserErr> {
+        if self.peek() != Some(kind) {
+            return Err(err);
+        }
+        self.bump(children);
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! Pascal dialects understood by the lexer, the parser and the interpreter.
+//!
+//! | | ISO 7185 | Turbo Pascal | Free Pascal / Delphi |
+//! |---|---|---|---|
+//! | comments | `{ }`, `(* *)`, openers and closers interchangeable | `{ }`, `(* *)` | `{ }`, `(* *)`, `//` |
+//! | strings | `'...'`, non-empty | `'...'`, `#nn` codes, may be empty | as Turbo Pascal |
+//! | largest integer | `maxint` = 2^31 - 1 | `LongInt`, 2^31 - 1 | `Int64`, 2^63 - 1 |
+//! | reserved words | ISO list | + `string`, `xor`, `shl`, `unit`, ... | + `class`, `try`, `raise`, ... |
+//! | remainder | `MOD` | `MOD` | `MOD`, `%` |
+//!
+//! All of them spell integer division as `DIV`, and `+`, `-` and `*` of
+//! two INTEGERs fail when the result leaves the largest integer's range;
+//! REALs aren't limited, even whole ones like `65536.0`.
+//! Reserved words are case-insensitive everywhere.
+
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dialect {
+    #[default]
+    Iso7185,
+    TurboPascal,
+    /// Free Pascal in `objfpc`/`delphi` mode, also used for Delphi sources.
+    FreePascal,
+}
+
+const ISO_RESERVED: &[&str] = &[
+    "AND", "ARRAY", "BEGIN", "CASE", "CONST", "DIV", "DO", "DOWNTO", "ELSE", "END", "FILE",
+    "FOR", "FUNCTION", "GOTO", "IF", "IN", "LABEL", "MOD", "NIL", "NOT", "OF", "OR", "PACKED",
+    "PROCEDURE", "PROGRAM", "RECORD", "REPEAT", "SET", "THEN", "TO", "TYPE", "UNTIL", "VAR",
+    "WHILE", "WITH",
+];
+
+const TURBO_RESERVED: &[&str] = &[
+    "ASM", "CONSTRUCTOR", "DESTRUCTOR", "IMPLEMENTATION", "INLINE", "INTERFACE", "OBJECT",
+    "SHL", "SHR", "STRING", "UNIT", "USES", "XOR",
+];
+
+const FREE_PASCAL_RESERVED: &[&str] = &[
+    "AS", "CLASS", "DISPINTERFACE", "EXCEPT", "EXPORTS", "FINALIZATION", "FINALLY",
+    "INITIALIZATION", "IS", "LIBRARY", "ON", "OPERATOR", "OUT", "PROPERTY", "RAISE",
+    "RESOURCESTRING", "THREADVAR", "TRY",
+];
+
+impl Dialect {
+    /// Whether `word` is reserved in this dialect, regardless of case.
+    pub fn is_reserved(&self, word: &str) -> bool {
+        let word = word.to_ascii_uppercase();
+        let word = word.as_str();
+
+        ISO_RESERVED.contains(&word)
+            || *self != Dialect::Iso7185 && TURBO_RESERVED.contains(&word)
+            || *self == Dialect::FreePascal && FREE_PASCAL_RESERVED.contains(&word)
+    }
+
+    /// `// ...` comments running to the end of the line.
+    pub fn line_comments(&self) -> bool {
+        *self == Dialect::FreePascal
+    }
+
+    /// ISO 7185 treats `{` and `(*` (and `}` and `*)`) as the same
+    /// delimiter, so `{ ... *)` is a complete comment.
+    pub fn mixed_comment_delims(&self) -> bool {
+        *self == Dialect::Iso7185
+    }
+
+    /// `#13`-style character codes inside string literals.
+    pub fn char_codes(&self) -> bool {
+        *self != Dialect::Iso7185
+    }
+
+    pub fn empty_strings(&self) -> bool {
+        *self != Dialect::Iso7185
+    }
+
+    /// `%` as another spelling of `MOD`.
+    pub fn percent_mod(&self) -> bool {
+        *self == Dialect::FreePascal
+    }
+
+    /// Largest value of the widest integer type.
+    pub fn max_int(&self) -> i64 {
+        match self {
+            Dialect::Iso7185 | Dialect::TurboPascal => i32::MAX as i64,
+            Dialect::FreePascal => i64::MAX,
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dialect::Iso7185 => "ISO 7185",
+            Dialect::TurboPascal => "Turbo Pascal",
+            Dialect::FreePascal => "Free Pascal",
+        })
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "iso" | "iso7185" => Ok(Dialect::Iso7185),
+            "tp" | "turbo" | "turbopascal" => Ok(Dialect::TurboPascal),
+            "fpc" | "freepascal" | "delphi" => Ok(Dialect::FreePascal),
+            _ => Err(format!("unknown dialect `{}`, expected iso, turbo or fpc", name)),
+        }
+    }
+}
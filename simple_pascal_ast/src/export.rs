@@ -0,0 +1,149 @@
+//! Textual renderings of a [`Node`] tree for tests, teaching and debugging.
+//!
+//! `to_sexpr` gives a compact one-line form:
+//!
+//! ```text
+//! BEGIN x := -a + 2; END.   =>   (begin (:= x (+ (- a) 2)) ())
+//! ```
+//!
+//! Compound statements are `(begin ...)`, empty statements `()`, calls
+//! `(call f args...)`, operators keep their Pascal spelling and string
+//! literals are quoted as in source.
+//! `to_dot` emits a Graphviz digraph with one vertex per node and `lhs`/`rhs`
+//! labelled edges, e.g. `dump-ast --format dot prog.pas | dot -Tsvg`.
+
+use std::fmt::Write;
+
+use crate::{
+    node::*,
+    token::{delim::DelimKind, keyword::Keyword, literal::Literal},
+};
+
+impl Node {
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        write_sexpr(&mut out, self);
+        out
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = Dot {
+            out: String::from("digraph ast {\n    node [shape=box, fontname=monospace];\n"),
+            next_id: 0,
+        };
+        dot.node(self);
+        dot.out.push_str("}\n");
+        dot.out
+    }
+}
+
+fn write_sexpr(out: &mut String, node: &Node) {
+    match node {
+        Node::UnaryOp(unary_op) => {
+            write!(out, "({} ", unary_op.op.symbol()).unwrap();
+            write_sexpr(out, &unary_op.node);
+            out.push(')');
+        }
+        Node::BinOp(bin_op) => {
+            write!(out, "({} ", bin_op.op.symbol()).unwrap();
+            write_sexpr(out, &bin_op.lhs);
+            out.push(' ');
+            write_sexpr(out, &bin_op.rhs);
+            out.push(')');
+        }
+        Node::Compound(compound) => {
+            out.push_str("(begin");
+            for child in &compound.children {
+                out.push(' ');
+                write_sexpr(out, child);
+            }
+            out.push(')');
+        }
+        Node::Call(call) => {
+            write!(out, "(call {}", call.name).unwrap();
+            for arg in &call.args {
+                out.push(' ');
+                write_sexpr(out, arg);
+            }
+            out.push(')');
+        }
+        Node::None => out.push_str("()"),
+        leaf => out.push_str(&leaf_text(leaf)),
+    }
+}
+
+// source spelling of a node without children
+fn leaf_text(node: &Node) -> String {
+    match node {
+        Node::Literal(Literal::Integer(text) | Literal::Float(text)) => text.clone(),
+        Node::Literal(Literal::String(text)) => format!("'{}'", text.replace('\'', "''")),
+        Node::Ident(ident) => ident.clone(),
+        Node::Keyword(Keyword::Begin) => String::from("BEGIN"),
+        Node::Keyword(Keyword::End) => String::from("END"),
+        Node::Delim(DelimKind::Paren) => String::from("()"),
+        Node::Delim(DelimKind::Dot) => String::from("."),
+        Node::Delim(DelimKind::Semicolon) => String::from(";"),
+        Node::Delim(DelimKind::Comma) => String::from(","),
+        _ => String::new(),
+    }
+}
+
+struct Dot {
+    out: String,
+    next_id: usize,
+}
+
+impl Dot {
+    // writes the vertex of `node` and its subtree, returns the vertex name
+    fn node(&mut self, node: &Node) -> String {
+        let name = format!("n{}", self.next_id);
+        self.next_id += 1;
+
+        let label = match node {
+            Node::UnaryOp(unary_op) => format!("UnaryOp {}", unary_op.op.symbol()),
+            Node::BinOp(bin_op) => format!("BinOp {}", bin_op.op.symbol()),
+            Node::Compound(_) => String::from("Compound"),
+            Node::Call(call) => format!("Call {}", call.name),
+            Node::Literal(_) => format!("Literal {}", leaf_text(node)),
+            Node::Ident(_) => format!("Ident {}", leaf_text(node)),
+            Node::Keyword(_) => format!("Keyword {}", leaf_text(node)),
+            Node::Delim(_) => format!("Delim {}", leaf_text(node)),
+            Node::None => String::from("Empty"),
+        };
+        writeln!(self.out, "    {} [label=\"{}\"];", name, escape(&label)).unwrap();
+
+        match node {
+            Node::UnaryOp(unary_op) => self.edge(&name, &unary_op.node, None),
+            Node::BinOp(bin_op) => {
+                self.edge(&name, &bin_op.lhs, Some("lhs"));
+                self.edge(&name, &bin_op.rhs, Some("rhs"));
+            }
+            Node::Compound(compound) => {
+                for child in &compound.children {
+                    self.edge(&name, child, None);
+                }
+            }
+            Node::Call(call) => {
+                for (idx, arg) in call.args.iter().enumerate() {
+                    self.edge(&name, arg, Some(&format!("arg {}", idx)));
+                }
+            }
+            _ => {}
+        }
+        name
+    }
+
+    fn edge(&mut self, parent: &str, child: &Node, label: Option<&str>) {
+        let child = self.node(child);
+
+        match label {
+            Some(label) => writeln!(self.out, "    {} -> {} [label=\"{}\"];", parent, child, label),
+            None => writeln!(self.out, "    {} -> {};", parent, child),
+        }
+        .unwrap();
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
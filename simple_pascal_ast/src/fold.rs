@@ -0,0 +1,97 @@
+//! Transforming traversal of the [`Node`] tree.
+//!
+//! A [`Folder`] takes the tree by value and rebuilds it bottom-up. Each
+//! method returns a whole `Node`, so a pass can replace a node with one of
+//! a different kind, e.g. a `BinOp` with a `Literal`. The `fold_*`
+//! functions are the defaults that rebuild the node from folded children.
+
+use crate::{
+    node::*,
+    token::{delim::DelimKind, keyword::Keyword, literal::Literal},
+};
+
+pub trait Folder: Sized {
+    fn fold_node(&mut self, node: Node) -> Node {
+        fold_node(self, node)
+    }
+
+    fn fold_bin_op(&mut self, bin_op: BinOp) -> Node {
+        fold_bin_op(self, bin_op)
+    }
+
+    fn fold_unary_op(&mut self, unary_op: UnaryOp) -> Node {
+        fold_unary_op(self, unary_op)
+    }
+
+    fn fold_compound(&mut self, compound: CompoundNode) -> Node {
+        fold_compound(self, compound)
+    }
+
+    fn fold_call(&mut self, call: Call) -> Node {
+        fold_call(self, call)
+    }
+
+    fn fold_literal(&mut self, lit: Literal) -> Node {
+        Node::Literal(lit)
+    }
+
+    fn fold_ident(&mut self, ident: String) -> Node {
+        Node::Ident(ident)
+    }
+
+    fn fold_keyword(&mut self, keyword: Keyword) -> Node {
+        Node::Keyword(keyword)
+    }
+
+    fn fold_delim(&mut self, delim: DelimKind) -> Node {
+        Node::Delim(delim)
+    }
+
+    fn fold_none(&mut self) -> Node {
+        Node::None
+    }
+}
+
+pub fn fold_node<F: Folder>(folder: &mut F, node: Node) -> Node {
+    match node {
+        Node::BinOp(bin_op) => folder.fold_bin_op(bin_op),
+        Node::UnaryOp(unary_op) => folder.fold_unary_op(unary_op),
+        Node::Compound(compound) => folder.fold_compound(compound),
+        Node::Call(call) => folder.fold_call(call),
+        Node::Literal(lit) => folder.fold_literal(lit),
+        Node::Ident(ident) => folder.fold_ident(ident),
+        Node::Keyword(keyword) => folder.fold_keyword(keyword),
+        Node::Delim(delim) => folder.fold_delim(delim),
+        Node::None => folder.fold_none(),
+    }
+}
+
+pub fn fold_bin_op<F: Folder>(folder: &mut F, bin_op: BinOp) -> Node {
+    let lhs = folder.fold_node(*bin_op.lhs);
+    let rhs = folder.fold_node(*bin_op.rhs);
+
+    BinOp::new(lhs, bin_op.op, rhs)
+}
+
+pub fn fold_unary_op<F: Folder>(folder: &mut F, unary_op: UnaryOp) -> Node {
+    let node = folder.fold_node(*unary_op.node);
+
+    UnaryOp::new(unary_op.op, node)
+}
+
+pub fn fold_compound<F: Folder>(folder: &mut F, compound: CompoundNode) -> Node {
+    CompoundNode::from_list(
+        compound
+            .children
+            .into_iter()
+            .map(|child| folder.fold_node(child))
+            .collect(),
+    )
+}
+
+pub fn fold_call<F: Folder>(folder: &mut F, call: Call) -> Node {
+    Call::new(
+        call.name,
+        call.args.into_iter().map(|arg| folder.fold_node(arg)).collect(),
+    )
+}
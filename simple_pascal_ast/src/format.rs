@@ -0,0 +1,388 @@
+//! Canonical source formatter.
+//!
+//! Works on the lossless [`cst`](crate::cst) so comments survive
+//! formatting. Empty statements are dropped, keywords are upper-cased,
+//! nested blocks are indented and expressions that don't fit into
+//! `max_width` are wrapped before their outermost operators.
+
+use std::collections::HashMap;
+
+use crate::{
+    cst::{self, ast::*, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken},
+    dialect::Dialect,
+    parser::ParserErr,
+    token::op::Fixity,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub indent: usize,
+    pub max_width: usize,
+    pub dialect: Dialect,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: 4,
+            max_width: 80,
+            dialect: Dialect::default(),
+        }
+    }
+}
+
+pub fn format(text: &str) -> Result<String, ParserErr> {
+    format_with(text, &FormatOptions::default())
+}
+
+pub fn format_with(text: &str, options: &FormatOptions) -> Result<String, ParserErr> {
+    let tree = cst::parse_with(text, options.dialect)?;
+    Ok(format_tree(&tree, options))
+}
+
+/// Formats a tree produced by [`cst::parse`].
+pub fn format_tree(tree: &SyntaxNode, options: &FormatOptions) -> String {
+    let mut printer = Printer::new(tree, options);
+
+    if let Some(program) = Program::cast(tree) {
+        printer.program(program);
+    }
+    printer.finish()
+}
+
+struct Comment<'a> {
+    text: &'a str,
+    // whether the comment starts on its own line in the source
+    own_line: bool,
+}
+
+// comments are keyed by the significant token they precede
+type TokenKey = *const SyntaxToken;
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    comments: HashMap<TokenKey, Vec<Comment<'a>>>,
+    trailing: Vec<Comment<'a>>,
+    out: String,
+    line: String,
+    // the current line ends with a `//` comment
+    line_closed: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(tree: &'a SyntaxNode, options: &'a FormatOptions) -> Self {
+        let mut comments = HashMap::new();
+        let mut pending = Vec::new();
+        let mut newline = true;
+
+        for token in tree.descendant_tokens() {
+            match token.kind() {
+                SyntaxKind::Whitespace => newline |= token.text().contains('\n'),
+                SyntaxKind::Comment => {
+                    pending.push(Comment {
+                        text: token.text(),
+                        own_line: newline,
+                    });
+                    newline = false;
+                }
+                _ => {
+                    if !pending.is_empty() {
+                        comments.insert(token as TokenKey, std::mem::take(&mut pending));
+                    }
+                    newline = false;
+                }
+            }
+        }
+
+        Self {
+            options,
+            comments,
+            trailing: pending,
+            out: String::new(),
+            line: String::new(),
+            line_closed: false,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.out
+    }
+
+    fn program(&mut self, program: Program<'a>) {
+        if let Some(compound) = program.compound() {
+            self.compound(compound, 0);
+        }
+
+        for token in program.syntax().child_tokens() {
+            if token.kind() == SyntaxKind::Dot {
+                self.line.push('.');
+                self.comments_between(token, 0);
+            }
+        }
+
+        let trailing = std::mem::take(&mut self.trailing);
+        self.place_comments(trailing, 0);
+    }
+
+    fn compound(&mut self, compound: Compound<'a>, depth: usize) {
+        let node = compound.syntax();
+        let last = node
+            .child_nodes()
+            .collect::<Vec<_>>()
+            .iter()
+            .rposition(|stmt| stmt.kind() != SyntaxKind::EmptyStmt);
+        let mut stmt_idx = 0;
+
+        for child in node.children() {
+            match child {
+                SyntaxElement::Token(token) => match token.kind() {
+                    SyntaxKind::BeginKw => {
+                        self.comments_between(token, depth);
+                        self.start_line(depth, "BEGIN");
+                    }
+                    SyntaxKind::EndKw => {
+                        self.comments_between(token, depth + 1);
+                        self.start_line(depth, "END");
+                    }
+                    SyntaxKind::Semicolon => self.comments_between(token, depth + 1),
+                    _ => (),
+                },
+                SyntaxElement::Node(stmt_node) => {
+                    let stmt = match Statement::cast(stmt_node) {
+                        Some(stmt) => stmt,
+                        None => continue,
+                    };
+
+                    if !matches!(stmt, Statement::Empty(_)) {
+                        if let Some(first) = first_token(stmt_node) {
+                            self.comments_between(first, depth + 1);
+                        }
+                        self.statement(stmt, depth + 1);
+
+                        if Some(stmt_idx) != last {
+                            self.line.push(';');
+                        }
+                    }
+                    stmt_idx += 1;
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: Statement<'a>, depth: usize) {
+        match stmt {
+            Statement::Compound(compound) => self.compound(compound, depth),
+            Statement::Assignment(assignment) => {
+                let target = assignment
+                    .target()
+                    .map(|target| self.expr_flat(Expr::Name(target)))
+                    .unwrap_or_default();
+                let assign_eq = assignment
+                    .syntax()
+                    .child_tokens()
+                    .find(|token| token.kind() == SyntaxKind::AssignEq)
+                    .map(|token| self.token_text(token, ":="))
+                    .unwrap_or_default();
+
+                self.start_line(depth, &format!("{} {} ", target, assign_eq));
+                if let Some(value) = assignment.value() {
+                    self.expr(value, depth);
+                }
+            }
+            Statement::Expr(stmt) => {
+                self.start_line(depth, "");
+                if let Some(expr) = stmt.expr() {
+                    self.expr(expr, depth);
+                }
+            }
+            Statement::Empty(_) => (),
+        }
+    }
+
+    // appends `expr` to the current line, wrapping it if it's too long
+    fn expr(&mut self, expr: Expr<'a>, depth: usize) {
+        let flat = self.expr_flat(expr);
+
+        // only left-associative chains are split, `a ^ b ^ c` stays as is
+        let bin = match expr {
+            Expr::Bin(bin)
+                if self.line.len() + flat.len() > self.options.max_width
+                    && bin.op().map(|op| op.fixity()) == Some(Fixity::Left) =>
+            {
+                bin
+            }
+            _ => {
+                self.line.push_str(&flat);
+                return;
+            }
+        };
+
+        // split the left-leaning chain of the outermost operator level
+        let level = precedence(bin);
+        let mut operands = Vec::new();
+        let mut lhs = Expr::Bin(bin);
+        while let Expr::Bin(bin) = lhs {
+            match bin.lhs() {
+                Some(next) if precedence(bin) == level => {
+                    operands.push((bin.op_token(), bin.rhs()));
+                    lhs = next;
+                }
+                _ => break,
+            }
+        }
+
+        self.line.push_str(&self.expr_flat(lhs));
+        for (op, rhs) in operands.into_iter().rev() {
+            let op = op.map(|op| self.op_text(op)).unwrap_or_default();
+            let rhs = rhs.map(|rhs| self.expr_flat(rhs)).unwrap_or_default();
+
+            self.start_line(depth + 1, &format!("{} {}", op, rhs));
+        }
+    }
+
+    fn expr_flat(&self, expr: Expr<'a>) -> String {
+        match expr {
+            Expr::Bin(bin) => {
+                let lhs = bin.lhs().map(|lhs| self.expr_flat(lhs)).unwrap_or_default();
+                let op = bin.op_token().map(|op| self.op_text(op)).unwrap_or_default();
+                let rhs = bin.rhs().map(|rhs| self.expr_flat(rhs)).unwrap_or_default();
+
+                format!("{} {} {}", lhs, op, rhs)
+            }
+            Expr::Unary(unary) => {
+                let op = unary.op_token().map(|op| self.op_text(op)).unwrap_or_default();
+                let operand = unary
+                    .operand()
+                    .map(|operand| self.expr_flat(operand))
+                    .unwrap_or_default();
+
+                format!("{}{}", op, operand)
+            }
+            Expr::Paren(paren) => {
+                let mut text = String::new();
+                for child in paren.syntax().children() {
+                    match child {
+                        SyntaxElement::Token(token) if token.kind() == SyntaxKind::LParen => {
+                            text.push_str(&self.token_text(token, "("))
+                        }
+                        SyntaxElement::Token(token) if token.kind() == SyntaxKind::RParen => {
+                            text.push_str(&self.token_text(token, ")"))
+                        }
+                        SyntaxElement::Node(node) => {
+                            if let Some(expr) = Expr::cast(node) {
+                                text.push_str(&self.expr_flat(expr));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                text
+            }
+            Expr::Call(call) => {
+                let mut text = String::new();
+                for child in call.syntax().children() {
+                    match child {
+                        SyntaxElement::Token(token) if token.kind() == SyntaxKind::LParen => {
+                            text.push_str(&self.token_text(token, "("))
+                        }
+                        SyntaxElement::Token(token) if token.kind() == SyntaxKind::Comma => {
+                            text.push_str(&self.token_text(token, ", "))
+                        }
+                        SyntaxElement::Token(token) if token.kind() == SyntaxKind::RParen => {
+                            text.push_str(&self.token_text(token, ")"))
+                        }
+                        SyntaxElement::Node(node) => {
+                            if let Some(expr) = Expr::cast(node) {
+                                text.push_str(&self.expr_flat(expr));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                text
+            }
+            Expr::Literal(lit) => lit
+                .token()
+                .map(|token| self.token_text(token, token.text()))
+                .unwrap_or_default(),
+            Expr::Name(name) => match first_token(name.syntax()) {
+                Some(token) => self.token_text(token, token.text()),
+                None => String::new(),
+            },
+        }
+    }
+
+    fn op_text(&self, token: &SyntaxToken) -> String {
+        match token.kind().to_op() {
+            Some(op) => self.token_text(token, op.symbol()),
+            None => self.token_text(token, token.text()),
+        }
+    }
+
+    // `text` prefixed with the comments that precede `token` inside an expression
+    fn token_text(&self, token: &SyntaxToken, text: &str) -> String {
+        let mut res = String::new();
+
+        for comment in self.comments.get(&(token as TokenKey)).into_iter().flatten() {
+            res.push_str(&inline_comment(comment.text));
+            res.push(' ');
+        }
+        res.push_str(text);
+        res
+    }
+
+    fn comments_between(&mut self, token: &SyntaxToken, depth: usize) {
+        let comments = self.comments.remove(&(token as TokenKey)).unwrap_or_default();
+        self.place_comments(comments, depth);
+    }
+
+    // comments that shared a line with the previous code stay trailing,
+    // the rest get lines of their own
+    fn place_comments(&mut self, comments: Vec<Comment<'a>>, depth: usize) {
+        for comment in comments {
+            if comment.own_line || self.line.is_empty() || self.line_closed {
+                self.start_line(depth, comment.text);
+                self.flush();
+            } else {
+                self.line.push(' ');
+                self.line.push_str(comment.text);
+                self.line_closed = comment.text.starts_with("//");
+            }
+        }
+    }
+
+    fn start_line(&mut self, depth: usize, text: &str) {
+        self.flush();
+        self.line = " ".repeat(depth * self.options.indent);
+        self.line.push_str(text);
+    }
+
+    fn flush(&mut self) {
+        self.line_closed = false;
+        if !self.line.is_empty() {
+            self.out.push_str(self.line.trim_end());
+            self.out.push('\n');
+            self.line.clear();
+        }
+    }
+}
+
+// a `//` comment in the middle of a line would swallow the code after it
+fn inline_comment(text: &str) -> String {
+    match text.strip_prefix("//") {
+        Some(body) if !body.contains("*)") => format!("(*{} *)", body.trim_end()),
+        Some(body) => format!("{{{} }}", body.trim_end()),
+        None => text.to_string(),
+    }
+}
+
+fn first_token(node: &SyntaxNode) -> Option<&SyntaxToken> {
+    node.descendant_tokens()
+        .into_iter()
+        .find(|token| !token.kind().is_trivia())
+}
+
+fn precedence(bin: BinExpr) -> Option<u8> {
+    bin.op()?.precedence()
+}
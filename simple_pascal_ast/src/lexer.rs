@@ -1,7 +1,14 @@
 use std::{iter::Peekable, str::Chars};
 
-use crate::token::{
-    delim::parse_delim, keyword::parse_keyword, literal::Literal, op::OpKind, Token,
+use crate::{
+    dialect::Dialect,
+    token::{
+        delim::parse_delim,
+        keyword::parse_keyword,
+        literal::{decode_string, Literal},
+        op::{parse_word_op, OpKind},
+        Token,
+    },
 };
 
 fn is_word(ch: char) -> bool {
@@ -11,11 +18,18 @@ fn is_word(ch: char) -> bool {
 #[derive(Debug)]
 pub enum LexerErr {
     UndefinedChar,
+    UnterminatedComment,
+    InvalidString,
+    ReservedWord(String),
 }
 
 #[derive(Debug)]
 pub(crate) struct Lexer<'a> {
     current_char: Peekable<Chars<'a>>,
+    dialect: Dialect,
+    line: u32,
+    // line the last token started on
+    token_line: u32,
 }
 
 impl<'a> Lexer<'a> {
@@ -26,6 +40,22 @@ impl<'a> Lexer<'a> {
                 continue;
             }
 
+            if next_char == '{' || self.at_paren_comment() {
+                self.skip_comment()?;
+                continue;
+            }
+
+            if self.dialect.line_comments() && self.at_line_comment() {
+                self.skip_line_comment();
+                continue;
+            }
+
+            self.token_line = self.line;
+
+            if next_char == '\'' || next_char == '#' && self.dialect.char_codes() {
+                return Ok(Token::Literal(self.string()?));
+            }
+
             if let Some(op) = self.op_kind(next_char) {
                 self.current_char.next();
 
@@ -43,24 +73,42 @@ impl<'a> Lexer<'a> {
             } else if next_char.is_ascii_digit() {
                 return Ok(Token::Literal(self.number()));
             } else if is_word(next_char) {
-                // is literal or keyword, or ident
+                // is keyword, word operator or ident
                 let word = self.word();
                 if let Some(keyword) = parse_keyword(&word) {
                     return Ok(Token::Keyword(keyword));
+                } else if let Some(op) = parse_word_op(&word) {
+                    return Ok(Token::Op(op));
+                } else if self.dialect.is_reserved(&word) {
+                    return Err(LexerErr::ReservedWord(word));
                 } else {
-                    // since we have only Literal::Integer and Literal::Float
-                    // we can just return Token::Ident with no regard
                     return Ok(Token::Ident(word));
                 }
             } else {
                 return Err(LexerErr::UndefinedChar);
             }
         }
+        self.token_line = self.line;
         Ok(Token::Eof)
     }
 
     pub fn set(&mut self, text: &'a str) {
         self.current_char = text.chars().peekable();
+        self.line = 1;
+        self.token_line = 1;
+    }
+
+    /// 1-based line of the last token.
+    pub fn token_line(&self) -> u32 {
+        self.token_line
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
     }
 }
 
@@ -87,7 +135,94 @@ impl Lexer<'_> {
     }
 
     fn skip_whitespace(&mut self) {
-        while let Some(_) = self.current_char.next_if(|ch| ch.is_whitespace()) {}
+        while let Some(ch) = self.current_char.next_if(|ch| ch.is_whitespace()) {
+            self.count_line(ch);
+        }
+    }
+
+    fn count_line(&mut self, ch: char) {
+        if ch == '\n' {
+            self.line += 1;
+        }
+    }
+
+    fn at_paren_comment(&self) -> bool {
+        let mut ahead = self.current_char.clone();
+        ahead.next() == Some('(') && ahead.next() == Some('*')
+    }
+
+    fn at_line_comment(&self) -> bool {
+        let mut ahead = self.current_char.clone();
+        ahead.next() == Some('/') && ahead.next() == Some('/')
+    }
+
+    // skips either `{ ... }` or `(* ... *)`, nesting is not supported
+    fn skip_comment(&mut self) -> Result<(), LexerErr> {
+        let mixed = self.dialect.mixed_comment_delims();
+        let brace = match self.current_char.next() {
+            Some('{') => true,
+            _ => {
+                self.current_char.next();
+                false
+            }
+        };
+
+        let mut prev = '\0';
+        while let Some(ch) = self.current_char.next() {
+            self.count_line(ch);
+            let closed_brace = ch == '}';
+            let closed_paren = prev == '*' && ch == ')';
+
+            if mixed && (closed_brace || closed_paren)
+                || brace && closed_brace
+                || !brace && closed_paren
+            {
+                return Ok(());
+            }
+            prev = ch;
+        }
+        Err(LexerErr::UnterminatedComment)
+    }
+
+    fn skip_line_comment(&mut self) {
+        while self.current_char.next_if(|ch| *ch != '\n').is_some() {}
+    }
+
+    // quoted parts and `#nn` char codes following each other make up a
+    // single literal: `'line'#13#10'next'`
+    fn string(&mut self) -> Result<Literal, LexerErr> {
+        let mut raw = String::new();
+
+        loop {
+            match self.current_char.peek() {
+                Some('\'') => {
+                    raw.extend(self.current_char.next());
+                    loop {
+                        match self.current_char.next() {
+                            Some('\'') => break,
+                            Some(ch) => {
+                                self.count_line(ch);
+                                raw.push(ch)
+                            }
+                            None => return Err(LexerErr::InvalidString),
+                        }
+                    }
+                    raw.push('\'');
+                }
+                Some('#') if self.dialect.char_codes() => {
+                    raw.extend(self.current_char.next());
+                    while let Some(digit) = self.current_char.next_if(|ch| ch.is_ascii_digit()) {
+                        raw.push(digit);
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        match decode_string(&raw, self.dialect) {
+            Some(string) => Ok(Literal::String(string)),
+            None => Err(LexerErr::InvalidString),
+        }
     }
 
     fn op_kind(&mut self, ch: char) -> Option<OpKind> {
@@ -129,6 +264,9 @@ impl<'a> From<&'a str> for Lexer<'a> {
     fn from(text: &'a str) -> Self {
         Self {
             current_char: text.chars().peekable(),
+            dialect: Dialect::default(),
+            line: 1,
+            token_line: 1,
         }
     }
 }
@@ -200,7 +338,7 @@ mod tests {
     fn from() {
         let lexer = Lexer::from("4 + 3");
         let lexer_str =
-            "Lexer { current_char: Peekable { iter: Chars(['4', ' ', '+', ' ', '3']), peeked: None } }";
+            "Lexer { current_char: Peekable { iter: Chars(['4', ' ', '+', ' ', '3']), peeked: None }, dialect: Iso7185, line: 1, token_line: 1 }";
 
         assert_eq!(lexer_str, format!("{:?}", lexer));
     }
@@ -208,13 +346,13 @@ mod tests {
     #[test]
     fn set() {
         let mut lexer = Lexer::from("");
-        let mut lexer_str = "Lexer { current_char: Peekable { iter: Chars([]), peeked: None } }";
+        let mut lexer_str = "Lexer { current_char: Peekable { iter: Chars([]), peeked: None }, dialect: Iso7185, line: 1, token_line: 1 }";
 
         assert_eq!(lexer_str, format!("{:?}", lexer));
 
         lexer.set("4 + 3");
         lexer_str =
-            "Lexer { current_char: Peekable { iter: Chars(['4', ' ', '+', ' ', '3']), peeked: None } }";
+            "Lexer { current_char: Peekable { iter: Chars(['4', ' ', '+', ' ', '3']), peeked: None }, dialect: Iso7185, line: 1, token_line: 1 }";
 
         assert_eq!(lexer_str, format!("{:?}", lexer));
     }
@@ -241,6 +379,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn skip_comments() {
+        let mut lexer = Lexer::from("{ one } 1 (* two *) + (*)*) 2 (3)");
+        let mut tokens = Vec::new();
+
+        loop {
+            let token = lexer.next().unwrap();
+            if token == Token::Eof {
+                break;
+            }
+            tokens.push(token);
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal(Literal::Integer(String::from("1"))),
+                Token::Op(OpKind::Plus),
+                Token::Literal(Literal::Integer(String::from("2"))),
+                Token::OpenDelim(DelimKind::Paren),
+                Token::Literal(Literal::Integer(String::from("3"))),
+                Token::CloseDelim(DelimKind::Paren),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn unterminated_comment() {
+        Lexer::from("{ oops").next().unwrap();
+    }
+
     #[test]
     #[should_panic]
     fn fail_tokenization() {
@@ -1,4 +1,12 @@
+pub mod arena;
+pub mod const_fold;
+pub mod cst;
+pub mod dialect;
+pub mod export;
+pub mod fold;
+pub mod format;
 mod lexer;
 pub mod node;
 pub mod parser;
 pub mod token;
+pub mod visit;
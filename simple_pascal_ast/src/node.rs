@@ -2,7 +2,8 @@ use std::collections::LinkedList;
 
 use crate::token::{literal::Literal, op::OpKind, keyword::Keyword, delim::DelimKind};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Node {
     UnaryOp(UnaryOp),
     BinOp(BinOp),
@@ -11,29 +12,41 @@ pub enum Node {
     Keyword(Keyword),
     Delim(DelimKind),
     Compound(CompoundNode),
+    Call(Call),
     None
 }
 
 pub type NodeList = LinkedList<Node>;
 
-#[derive(Debug, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CompoundNode {
     pub children: NodeList
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnaryOp {
     pub op: OpKind,
     pub node: Box<Node>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinOp {
     pub lhs: Box<Node>,
     pub op: OpKind,
     pub rhs: Box<Node>,
 }
 
+/// Call of a function or procedure, `name(args)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Call {
+    pub name: String,
+    pub args: Vec<Node>,
+}
+
 impl BinOp {
     pub fn new(lhs: Node, op: OpKind, rhs: Node) -> Node {
         Node::BinOp(Self {
@@ -53,6 +66,12 @@ impl UnaryOp {
     }
 }
 
+impl Call {
+    pub fn new(name: String, args: Vec<Node>) -> Node {
+        Node::Call(Self { name, args })
+    }
+}
+
 impl CompoundNode {
     pub fn new() -> Node {
         Node::Compound(Default::default())
@@ -1,11 +1,16 @@
+use std::fmt;
+
 use crate::{
+    arena::Ast,
+    dialect::Dialect,
     token::{
         Token,
-        op::OpKind, 
+        op::{OpKind, Fixity, EXPR_PRECEDENCE},
         delim::DelimKind,
         keyword::Keyword,
+        literal::Literal,
     }, 
-    lexer::Lexer, 
+    lexer::{Lexer, LexerErr}, 
     node::*
 };
 
@@ -15,18 +20,53 @@ pub enum ParserErr {
     TokenMismatch(String),
     MissingToken(String),
     InvalidExpr,
+    InvalidStatement(String),
+    NestingTooDeep(usize),
+    TooManyOperators(usize),
     Undefined,
 } 
 
+impl fmt::Display for ParserErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserErr::InvalidToken(msg)
+            | ParserErr::TokenMismatch(msg)
+            | ParserErr::MissingToken(msg)
+            | ParserErr::InvalidStatement(msg) => f.write_str(msg),
+            ParserErr::InvalidExpr => f.write_str("Invalid expression"),
+            ParserErr::NestingTooDeep(max) => write!(f, "Nested deeper than {} levels", max),
+            ParserErr::TooManyOperators(max) => write!(f, "More than {} operators in a row", max),
+            ParserErr::Undefined => f.write_str("Unexpected character"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Parser<'a> {
     current_token: Token,
-    lexer: Lexer<'a>
+    lexer: Lexer<'a>,
+    // accept bare expressions like `4 - y;` as statements
+    calculator: bool,
+    statement_lines: Vec<u32>,
+    max_depth: usize,
+    depth: usize,
+    max_operators: usize,
+    // operators of the chains the current operand is part of
+    operators: usize,
 }
 
+/// Default limit of `Parser::set_max_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Default limit of `Parser::set_max_operators`.
+pub const DEFAULT_MAX_OPERATORS: usize = 1000;
+
 impl<'a> Parser<'a> {
     pub fn parse(&mut self, text: &'a str) -> Result<Node, ParserErr> {
         self.lexer.set(text);
+        self.statement_lines.clear();
+        self.depth = 0;
+        self.operators = 0;
         self.next_token()?;
 
         let ast = self.program()?;
@@ -37,6 +77,49 @@ impl<'a> Parser<'a> {
 
         Ok(ast)
     }
+
+    /// Splits `text` into tokens, each with the 1-based line it starts on.
+    /// The final `Eof` isn't included.
+    pub fn tokenize(&mut self, text: &'a str) -> Result<Vec<(u32, Token)>, ParserErr> {
+        self.lexer.set(text);
+
+        let mut tokens = Vec::new();
+        loop {
+            self.next_token()?;
+            if self.current_token == Token::Eof {
+                return Ok(tokens);
+            }
+            tokens.push((self.lexer.token_line(), self.current_token.clone()));
+        }
+    }
+
+    /// Parses statements separated by `;` that aren't wrapped in a program
+    /// block, e.g. a line typed into a REPL.
+    pub fn parse_statements(&mut self, text: &'a str) -> Result<Vec<Node>, ParserErr> {
+        self.lexer.set(text);
+        self.statement_lines.clear();
+        self.depth = 0;
+        self.operators = 0;
+
+        let statements = match self.statement_list()? {
+            Node::Compound(compound) => compound.children.into_iter().collect(),
+            node => vec![node],
+        };
+
+        if self.current_token != Token::Eof {
+            return Err(ParserErr::InvalidStatement(format!(
+                "Expected `;` or the end of input, got {}",
+                describe(&self.current_token)
+            )));
+        }
+
+        Ok(statements)
+    }
+
+    /// Like `parse`, but returns the tree as an arena with node ids.
+    pub fn parse_arena(&mut self, text: &'a str) -> Result<Ast, ParserErr> {
+        Ok(Ast::from_node(&self.parse(text)?))
+    }
 }
 
 impl Parser<'_> {
@@ -44,7 +127,76 @@ impl Parser<'_> {
         Default::default()
     }
 
+    /// Lenient "calculator" dialect: besides the standard statements any
+    /// expression may be used as a statement and is evaluated for its value.
+    pub fn calculator() -> Self {
+        Self {
+            calculator: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_calculator(&mut self, calculator: bool) {
+        self.calculator = calculator;
+    }
+
+    pub fn is_calculator(&self) -> bool {
+        self.calculator
+    }
+
+    /// Limits how deep blocks, parentheses, unary operators and the right
+    /// operands of binary ones may nest, so that parsing (and running)
+    /// hostile input can't overflow the stack.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Limits how many binary operators may follow one another, like in
+    /// `1 + 2 + 3`, counting those of every chain an operand is part of.
+    /// A chain nests like `(1 + 2) + 3`, so it's bounded separately from
+    /// `set_max_depth`, with a much larger limit.
+    pub fn set_max_operators(&mut self, max_operators: usize) {
+        self.max_operators = max_operators;
+    }
+
+    pub fn max_operators(&self) -> usize {
+        self.max_operators
+    }
+
+    pub fn with_dialect(dialect: Dialect) -> Self {
+        let mut parser = Self::new();
+        parser.set_dialect(dialect);
+        parser
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.lexer.set_dialect(dialect);
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.lexer.dialect()
+    }
+
+    /// Line of the current token: after `parse` or `tokenize` fails, the
+    /// line where the error was found.
+    pub fn line(&self) -> u32 {
+        self.lexer.token_line()
+    }
+
+    /// Source line of every statement of the last parsed program, the
+    /// program block first and the rest in the order they appear, which is
+    /// a pre-order walk of the tree. Empty statements are on the line of
+    /// the token that follows them.
+    pub fn statement_lines(&self) -> &[u32] {
+        &self.statement_lines
+    }
+
     fn program(&mut self) -> Result<Node, ParserErr> {
+        self.statement_lines.push(self.lexer.token_line());
         let node_list = self.complex_statement()?;
 
         self.next_token()?;
@@ -71,7 +223,7 @@ impl Parser<'_> {
 
         if self.current_token != Token::Keyword(Keyword::End) {
             return Err(ParserErr::MissingToken(
-                format!("Expected keyword `END`, got {:?}", self.current_token)
+                format!("Expected `;` or keyword `END`, got {:?}", self.current_token)
             ));
         }
 
@@ -90,11 +242,12 @@ impl Parser<'_> {
 
     fn statement(&mut self) -> Result<Node, ParserErr> {
         self.next_token()?;
+        self.statement_lines.push(self.lexer.token_line());
 
         match self.current_token.clone() {
             Token::Keyword(keyword) => {
                 if keyword == Keyword::Begin {
-                    let statement = self.complex_statement();
+                    let statement = self.nest(Self::complex_statement);
                     self.next_token()?;
                     statement
                 } else {
@@ -105,22 +258,41 @@ impl Parser<'_> {
             Token::Ident(ident) => {
                 self.next_token()?;
 
-                let op = if let Token::Op(op) = self.current_token.clone() {
-                    self.next_token()?;
-                    op
-                } else {
-                    return Err(ParserErr::InvalidExpr);
-                };
-
-                Ok(BinOp::new(
-                    Node::Ident(ident),
-                    op,
-                    self.expr()?
-                ))
+                match self.current_token.clone() {
+                    Token::Op(OpKind::AssignEq) => {
+                        self.next_token()?;
+                        Ok(BinOp::new(
+                            Node::Ident(ident),
+                            OpKind::AssignEq,
+                            self.expr()?
+                        ))
+                    },
+                    Token::OpenDelim(DelimKind::Paren) => {
+                        let call = self.call(ident)?;
+                        if self.calculator {
+                            self.climb(call, EXPR_PRECEDENCE)
+                        } else {
+                            Ok(call)
+                        }
+                    },
+                    _ if self.calculator => self.climb(Node::Ident(ident), EXPR_PRECEDENCE),
+                    Token::Op(op) => Err(ParserErr::InvalidStatement(format!(
+                        "Expected `:=` after `{}`, got {}: expressions can't be used as statements",
+                        ident, describe(&Token::Op(op))
+                    ))),
+                    token => Err(ParserErr::InvalidStatement(format!(
+                        "Expected `:=` after `{}`, got {}: procedure calls are not supported",
+                        ident, describe(&token)
+                    ))),
+                }
             },
             Token::Delim(_) => {
                 Ok(Node::None)
             },
+            token if !self.calculator => Err(ParserErr::InvalidStatement(format!(
+                "Statement can't start with {}: expected an assignment, `BEGIN` or an empty statement",
+                describe(&token)
+            ))),
             _ => {
                 let mut node = self.expr()?;
 
@@ -139,51 +311,88 @@ impl Parser<'_> {
     fn next_token(&mut self) -> Result<(), ParserErr> {
         match self.lexer.next() {
             Ok(token) => Ok(self.current_token = token),
-            Err(_) => Err(ParserErr::Undefined),
+            Err(LexerErr::UndefinedChar) => Err(ParserErr::Undefined),
+            Err(LexerErr::UnterminatedComment) => Err(ParserErr::MissingToken(
+                String::from("Unterminated comment")
+            )),
+            Err(LexerErr::InvalidString) => Err(ParserErr::InvalidToken(
+                format!("Invalid or unterminated string literal in {}", self.dialect())
+            )),
+            Err(LexerErr::ReservedWord(word)) => Err(ParserErr::InvalidToken(
+                format!("`{}` is a reserved word in {} and isn't supported yet", word, self.dialect())
+            )),
         }
     }
 
     fn expr(&mut self) -> Result<Node, ParserErr> {
-        let mut res = self.term()?;
-
-        if !(res.is_delim() || res.is_keyword()) {
-            while self.is_expr_token() {
-                if let Token::Op(op) = self.current_token.clone() {
-                    self.next_token()?;
+        self.expr_bp(EXPR_PRECEDENCE)
+    }
 
-                    let node = self.term()?;
+    // precedence climbing: parses operators binding at least as tight as
+    // `min_precedence`, both driven by the table behind `OpKind::precedence`
+    fn expr_bp(&mut self, min_precedence: u8) -> Result<Node, ParserErr> {
+        self.nest(|parser| {
+            let res = parser.factor()?;
 
-                    match node {
-                        Node::Keyword(keyword) => return Err(ParserErr::TokenMismatch(
-                            format!("Binary operator doesn't support keywords, got {:?}", keyword)
-                        )),
-                        Node::Delim(_) | Node::None => return Err(ParserErr::InvalidExpr),
-                        _ => res = BinOp::new(res, op, node)
-                    }
-                }
+            if res.is_delim() || res.is_keyword() {
+                return Ok(res);
             }
-        }
-        Ok(res)
+
+            parser.climb(res, min_precedence)
+        })
     }
 
-    fn term(&mut self) -> Result<Node, ParserErr> {
-        let mut res = self.factor()?;
+    // runs `parse` one nesting level deeper
+    fn nest(&mut self, parse: impl FnOnce(&mut Self) -> Result<Node, ParserErr>) -> Result<Node, ParserErr> {
+        if self.depth >= self.max_depth {
+            return Err(ParserErr::NestingTooDeep(self.max_depth));
+        }
 
-        if !(res.is_delim() || res.is_keyword()) {
-            while self.is_term_token() {
-                if let Token::Op(op) = self.current_token.clone() {
-                    self.next_token()?;
+        self.depth += 1;
+        let res = parse(self);
+        self.depth -= 1;
+        res
+    }
 
-                    let node = self.factor()?;
+    // continues an expression whose leftmost operand is already parsed
+    fn climb(&mut self, res: Node, min_precedence: u8) -> Result<Node, ParserErr> {
+        let operators = self.operators;
+        let res = self.fold(res, min_precedence);
+        self.operators = operators;
+        res
+    }
 
-                    match node {
-                        Node::Keyword(keyword) => return Err(ParserErr::TokenMismatch(
-                            format!("Binary operator doesn't support keywords, got {:?}", keyword)
-                        )),
-                        Node::Delim(_) | Node::None => return Err(ParserErr::InvalidExpr),
-                        _ => res = BinOp::new(res, op, node)
-                    }
-                }
+    // `a + b + c` nests like `(a + b) + c`, without recursing, but every
+    // operator folded into the left operand makes the tree one deeper
+    fn fold(&mut self, mut res: Node, min_precedence: u8) -> Result<Node, ParserErr> {
+        while let Token::Op(op) = self.current_token.clone() {
+            let precedence = match op.precedence() {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            if op == OpKind::Percent && !self.dialect().percent_mod() {
+                return Err(ParserErr::InvalidToken(
+                    format!("`%` is not an operator in {}, use `MOD`", self.dialect())
+                ));
+            }
+            if self.operators >= self.max_operators {
+                return Err(ParserErr::TooManyOperators(self.max_operators));
+            }
+            self.operators += 1;
+            self.next_token()?;
+
+            let next_precedence = match op.fixity() {
+                Fixity::Right => precedence,
+                _ => precedence + 1,
+            };
+            let node = self.expr_bp(next_precedence)?;
+
+            match node {
+                Node::Keyword(keyword) => return Err(ParserErr::TokenMismatch(
+                    format!("Binary operator doesn't support keywords, got {:?}", keyword)
+                )),
+                Node::Delim(_) | Node::None => return Err(ParserErr::InvalidExpr),
+                _ => res = BinOp::new(res, op, node)
             }
         }
         Ok(res)
@@ -194,13 +403,26 @@ impl Parser<'_> {
         self.next_token()?;
 
         match token {
+            Token::Literal(Literal::Integer(int)) => {
+                match int.parse::<i64>() {
+                    Ok(value) if value <= self.dialect().max_int() => Ok(Node::Literal(Literal::Integer(int))),
+                    _ => Err(ParserErr::InvalidToken(format!(
+                        "Integer literal `{}` exceeds the largest {} integer {}",
+                        int, self.dialect(), self.dialect().max_int()
+                    )))
+                }
+            },
             Token::Literal(lit) => Ok(Node::Literal(lit)),
-            Token::Ident(ident) => Ok(Node::Ident(ident)),
+            Token::Ident(ident) => match self.current_token {
+                Token::OpenDelim(DelimKind::Paren) => self.call(ident),
+                _ => Ok(Node::Ident(ident)),
+            },
             Token::OpenDelim(open_delim) => self.bin_delim_factor(open_delim),
             Token::Op(op) => self.unary_op_factor(op),
             Token::Delim(delim) => {
                 match delim {
                     DelimKind::Dot | DelimKind::Semicolon => Ok(Node::Delim(delim)),
+                    DelimKind::Comma => Err(ParserErr::InvalidExpr),
                     _ => Err(ParserErr::MissingToken(
                         format!("Missing delimiter for {:?}", delim)
                     ))
@@ -232,10 +454,41 @@ impl Parser<'_> {
         }
     }
 
+    // `name` is read and `(` is the current token, parses the arguments
+    // up to the closing `)`
+    fn call(&mut self, name: String) -> Result<Node, ParserErr> {
+        self.next_token()?;
+        let mut args = Vec::new();
+
+        if self.current_token != Token::CloseDelim(DelimKind::Paren) {
+            loop {
+                match self.expr()? {
+                    Node::Keyword(_) | Node::Delim(_) | Node::None => return Err(ParserErr::InvalidExpr),
+                    arg => args.push(arg),
+                }
+                if self.current_token != Token::Delim(DelimKind::Comma) {
+                    break;
+                }
+                self.next_token()?;
+            }
+        }
+
+        match self.current_token.clone() {
+            Token::CloseDelim(DelimKind::Paren) => {
+                self.next_token()?;
+                Ok(Call::new(name, args))
+            },
+            token => Err(ParserErr::TokenMismatch(format!(
+                "Expected `,` or `)` in the arguments of `{}`, got {}",
+                name, describe(&token)
+            ))),
+        }
+    }
+
     fn unary_op_factor(&mut self, op: OpKind) -> Result<Node, ParserErr> {
-        match op {
-            OpKind::Plus | OpKind::Minus => {
-                let node = self.factor()?;
+        match op.prefix_precedence() {
+            Some(precedence) => {
+                let node = self.expr_bp(precedence)?;
 
                 match node {
                     Node::Keyword(_) | Node::Delim(_) | Node::Compound(_) | Node::None => Err(ParserErr::InvalidExpr),
@@ -248,30 +501,6 @@ impl Parser<'_> {
             ))
         }
     }
-
-    fn is_expr_token(&self) -> bool {
-        match &self.current_token {
-            Token::Op(ref op) => {
-                match op {
-                    &OpKind::Plus | &OpKind::Minus => true,
-                    _ => false
-                }
-            },
-            _ => false
-        }
-    }
-
-    fn is_term_token(&self) -> bool {
-        match &self.current_token {
-            Token::Op(ref op) => {
-                match op {
-                    &OpKind::Slash | &OpKind::Star | &OpKind::Percent | &OpKind::Caret => true,
-                    _ => false
-                }
-            },
-            _ => false
-        }
-    }
 }
 
 impl Default for Parser<'_> {
@@ -279,6 +508,32 @@ impl Default for Parser<'_> {
         Self {
             current_token: Token::Eof,
             lexer: Lexer::from("\0"),
+            calculator: false,
+            statement_lines: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+            max_operators: DEFAULT_MAX_OPERATORS,
+            operators: 0,
         }
     }
 }
+
+// human readable token for diagnostics
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(ident) => format!("identifier `{}`", ident),
+        Token::Keyword(keyword) => format!("keyword `{}`", format!("{:?}", keyword).to_uppercase()),
+        Token::OpenDelim(_) => String::from("`(`"),
+        Token::CloseDelim(_) => String::from("`)`"),
+        Token::Delim(DelimKind::Dot) => String::from("`.`"),
+        Token::Delim(DelimKind::Semicolon) => String::from("`;`"),
+        Token::Delim(DelimKind::Comma) => String::from("`,`"),
+        Token::Delim(delim) => format!("{:?}", delim),
+        Token::Op(op) => format!("operator `{}`", op.symbol()),
+        Token::Literal(Literal::Integer(lit)) | Token::Literal(Literal::Float(lit)) => {
+            format!("literal `{}`", lit)
+        },
+        Token::Literal(Literal::String(string)) => format!("string literal {:?}", string),
+        Token::Eof => String::from("end of input"),
+    }
+}
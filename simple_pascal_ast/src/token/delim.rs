@@ -1,10 +1,12 @@
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DelimKind {
     Paren, // ()
 
     // these kinds don't have close ones
     Dot,
     Semicolon,
+    Comma,
 }
 
 pub fn parse_delim(delim: char) -> Option<DelimKind> {
@@ -14,6 +16,7 @@ pub fn parse_delim(delim: char) -> Option<DelimKind> {
         '(' | ')' => Some(Paren),
         '.' => Some(Dot),
         ';' => Some(Semicolon),
+        ',' => Some(Comma),
         _ => None,
     }
 }
@@ -2,6 +2,7 @@ use lazy_static::lazy_static;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Keyword {
     Begin,
     End,
@@ -15,6 +16,7 @@ lazy_static! {
             .collect();
 }
 
+// keywords are case-insensitive, `begin` and `Begin` are `BEGIN` too
 pub fn parse_keyword(keyword: &str) -> Option<Keyword> {
-    Some(KEYWORDS.get(keyword)?.clone())
+    Some(KEYWORDS.get(keyword.to_ascii_uppercase().as_str())?.clone())
 }
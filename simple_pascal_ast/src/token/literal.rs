@@ -1,5 +1,69 @@
+use crate::dialect::Dialect;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     Integer(String),
     Float(String),
+    // decoded text, quotes and char codes resolved
+    String(String),
+}
+
+// Length of the string literal `text` starts with: quoted parts and, where
+// the dialect allows, `#nn` char codes. An unterminated literal takes the
+// rest of the text.
+pub(crate) fn string_len(text: &str, dialect: Dialect) -> usize {
+    let mut len = 0;
+
+    loop {
+        let rest = &text[len..];
+
+        if let Some(quoted) = rest.strip_prefix('\'') {
+            len += match quoted.find('\'') {
+                Some(end) => end + 2,
+                None => rest.len(),
+            };
+        } else if dialect.char_codes() && rest.starts_with('#') {
+            len += 1 + rest[1..]
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(rest.len() - 1);
+        } else {
+            return len;
+        }
+    }
+}
+
+// Decodes the raw text of a string literal, `''` inside quotes stands
+// for a single quote.
+pub(crate) fn decode_string(raw: &str, dialect: Dialect) -> Option<String> {
+    let mut res = String::new();
+    let mut chars = raw.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => loop {
+                match chars.next()? {
+                    '\'' if chars.peek() == Some(&'\'') => {
+                        chars.next();
+                        res.push('\'');
+                    }
+                    '\'' => break,
+                    ch => res.push(ch),
+                }
+            },
+            '#' if dialect.char_codes() => {
+                let mut code = String::new();
+                while let Some(digit) = chars.next_if(|ch| ch.is_ascii_digit()) {
+                    code.push(digit);
+                }
+                res.push(char::from_u32(code.parse().ok()?)?);
+            }
+            _ => return None,
+        }
+    }
+
+    if res.is_empty() && !dialect.empty_strings() {
+        return None;
+    }
+    Some(res)
 }
@@ -9,6 +9,7 @@ use literal::Literal;
 use op::OpKind;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     Ident(String),
     Keyword(Keyword),
@@ -1,3 +1,5 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Fixity {
     Left,
     Right,
@@ -5,6 +7,7 @@ pub enum Fixity {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpKind {
     Plus,    // +
     Minus,   // -
@@ -12,18 +15,85 @@ pub enum OpKind {
     Slash,   // /
     Caret,   // ^
     Percent, // %
+    Div,     // DIV
+    Mod,     // MOD
 
     AssignEq, // :=
 }
 
+// Binary operators as (operator, precedence, fixity), higher precedence
+// binds tighter. Adding an operator to the expression grammar only needs
+// an entry here (and a token for it in the lexer).
+const BINARY_OPS: &[(OpKind, u8, Fixity)] = &[
+    (OpKind::AssignEq, 0, Fixity::Right),
+    (OpKind::Plus, 1, Fixity::Left),
+    (OpKind::Minus, 1, Fixity::Left),
+    (OpKind::Star, 2, Fixity::Left),
+    (OpKind::Slash, 2, Fixity::Left),
+    (OpKind::Percent, 2, Fixity::Left),
+    (OpKind::Div, 2, Fixity::Left),
+    (OpKind::Mod, 2, Fixity::Left),
+    (OpKind::Caret, 4, Fixity::Right),
+];
+
+// Prefix operators as (operator, precedence): `-2 * 3` is `(-2) * 3`,
+// while `-2 ^ 2` is `-(2 ^ 2)`.
+const PREFIX_OPS: &[(OpKind, u8)] = &[(OpKind::Plus, 3), (OpKind::Minus, 3)];
+
+/// Lowest precedence an operator needs to appear inside an expression,
+/// assignment is a statement and never binds there.
+pub const EXPR_PRECEDENCE: u8 = 1;
+
 impl OpKind {
     pub fn fixity(&self) -> Fixity {
+        match self.binary() {
+            Some((_, fixity)) => fixity,
+            None => Fixity::None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
         use OpKind::*;
 
         match self {
-            AssignEq => Fixity::Right,
-            Plus | Minus | Star | Slash | Caret | Percent => Fixity::Left,
-            _ => Fixity::None,
+            Plus => "+",
+            Minus => "-",
+            Star => "*",
+            Slash => "/",
+            Caret => "^",
+            Percent => "%",
+            Div => "DIV",
+            Mod => "MOD",
+            AssignEq => ":=",
         }
     }
+
+    /// Binding power of the operator in infix position.
+    pub fn precedence(&self) -> Option<u8> {
+        Some(self.binary()?.0)
+    }
+
+    /// Binding power of the operator in prefix position.
+    pub fn prefix_precedence(&self) -> Option<u8> {
+        PREFIX_OPS
+            .iter()
+            .find(|(op, _)| op == self)
+            .map(|&(_, precedence)| precedence)
+    }
+
+    fn binary(&self) -> Option<(u8, Fixity)> {
+        BINARY_OPS
+            .iter()
+            .find(|(op, _, _)| op == self)
+            .map(|&(_, precedence, fixity)| (precedence, fixity))
+    }
+}
+
+// word operators, spelled the same way in every dialect
+pub fn parse_word_op(word: &str) -> Option<OpKind> {
+    match word.to_ascii_uppercase().as_str() {
+        "DIV" => Some(OpKind::Div),
+        "MOD" => Some(OpKind::Mod),
+        _ => None,
+    }
 }
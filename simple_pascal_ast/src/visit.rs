@@ -0,0 +1,155 @@
+//! Traversal of the [`Node`] tree.
+//!
+//! [`Visitor`] borrows the tree, [`MutVisitor`] edits it in place. Every
+//! method defaults to the matching `walk_*` function, which visits the
+//! children, so an implementation only overrides the nodes it cares about
+//! and calls `walk_*` itself to keep descending.
+
+use crate::{
+    node::*,
+    token::{delim::DelimKind, keyword::Keyword, literal::Literal, op::OpKind},
+};
+
+pub trait Visitor<'ast>: Sized {
+    fn visit_node(&mut self, node: &'ast Node) {
+        walk_node(self, node)
+    }
+
+    fn visit_bin_op(&mut self, bin_op: &'ast BinOp) {
+        walk_bin_op(self, bin_op)
+    }
+
+    fn visit_unary_op(&mut self, unary_op: &'ast UnaryOp) {
+        walk_unary_op(self, unary_op)
+    }
+
+    fn visit_compound(&mut self, compound: &'ast CompoundNode) {
+        walk_compound(self, compound)
+    }
+
+    fn visit_call(&mut self, call: &'ast Call) {
+        walk_call(self, call)
+    }
+
+    fn visit_op(&mut self, _op: &'ast OpKind) {}
+
+    fn visit_literal(&mut self, _lit: &'ast Literal) {}
+
+    fn visit_ident(&mut self, _ident: &'ast str) {}
+
+    fn visit_keyword(&mut self, _keyword: &'ast Keyword) {}
+
+    fn visit_delim(&mut self, _delim: &'ast DelimKind) {}
+
+    fn visit_none(&mut self) {}
+}
+
+pub fn walk_node<'ast, V: Visitor<'ast>>(visitor: &mut V, node: &'ast Node) {
+    match node {
+        Node::BinOp(bin_op) => visitor.visit_bin_op(bin_op),
+        Node::UnaryOp(unary_op) => visitor.visit_unary_op(unary_op),
+        Node::Compound(compound) => visitor.visit_compound(compound),
+        Node::Call(call) => visitor.visit_call(call),
+        Node::Literal(lit) => visitor.visit_literal(lit),
+        Node::Ident(ident) => visitor.visit_ident(ident),
+        Node::Keyword(keyword) => visitor.visit_keyword(keyword),
+        Node::Delim(delim) => visitor.visit_delim(delim),
+        Node::None => visitor.visit_none(),
+    }
+}
+
+pub fn walk_bin_op<'ast, V: Visitor<'ast>>(visitor: &mut V, bin_op: &'ast BinOp) {
+    visitor.visit_node(&bin_op.lhs);
+    visitor.visit_op(&bin_op.op);
+    visitor.visit_node(&bin_op.rhs);
+}
+
+pub fn walk_unary_op<'ast, V: Visitor<'ast>>(visitor: &mut V, unary_op: &'ast UnaryOp) {
+    visitor.visit_op(&unary_op.op);
+    visitor.visit_node(&unary_op.node);
+}
+
+pub fn walk_compound<'ast, V: Visitor<'ast>>(visitor: &mut V, compound: &'ast CompoundNode) {
+    for child in &compound.children {
+        visitor.visit_node(child);
+    }
+}
+
+// the callee isn't an identifier in the `visit_ident` sense, which only
+// sees variables
+pub fn walk_call<'ast, V: Visitor<'ast>>(visitor: &mut V, call: &'ast Call) {
+    for arg in &call.args {
+        visitor.visit_node(arg);
+    }
+}
+
+pub trait MutVisitor: Sized {
+    fn visit_node(&mut self, node: &mut Node) {
+        walk_node_mut(self, node)
+    }
+
+    fn visit_bin_op(&mut self, bin_op: &mut BinOp) {
+        walk_bin_op_mut(self, bin_op)
+    }
+
+    fn visit_unary_op(&mut self, unary_op: &mut UnaryOp) {
+        walk_unary_op_mut(self, unary_op)
+    }
+
+    fn visit_compound(&mut self, compound: &mut CompoundNode) {
+        walk_compound_mut(self, compound)
+    }
+
+    fn visit_call(&mut self, call: &mut Call) {
+        walk_call_mut(self, call)
+    }
+
+    fn visit_op(&mut self, _op: &mut OpKind) {}
+
+    fn visit_literal(&mut self, _lit: &mut Literal) {}
+
+    fn visit_ident(&mut self, _ident: &mut String) {}
+
+    fn visit_keyword(&mut self, _keyword: &mut Keyword) {}
+
+    fn visit_delim(&mut self, _delim: &mut DelimKind) {}
+
+    fn visit_none(&mut self) {}
+}
+
+pub fn walk_node_mut<V: MutVisitor>(visitor: &mut V, node: &mut Node) {
+    match node {
+        Node::BinOp(bin_op) => visitor.visit_bin_op(bin_op),
+        Node::UnaryOp(unary_op) => visitor.visit_unary_op(unary_op),
+        Node::Compound(compound) => visitor.visit_compound(compound),
+        Node::Call(call) => visitor.visit_call(call),
+        Node::Literal(lit) => visitor.visit_literal(lit),
+        Node::Ident(ident) => visitor.visit_ident(ident),
+        Node::Keyword(keyword) => visitor.visit_keyword(keyword),
+        Node::Delim(delim) => visitor.visit_delim(delim),
+        Node::None => visitor.visit_none(),
+    }
+}
+
+pub fn walk_bin_op_mut<V: MutVisitor>(visitor: &mut V, bin_op: &mut BinOp) {
+    visitor.visit_node(&mut bin_op.lhs);
+    visitor.visit_op(&mut bin_op.op);
+    visitor.visit_node(&mut bin_op.rhs);
+}
+
+pub fn walk_unary_op_mut<V: MutVisitor>(visitor: &mut V, unary_op: &mut UnaryOp) {
+    visitor.visit_op(&mut unary_op.op);
+    visitor.visit_node(&mut unary_op.node);
+}
+
+pub fn walk_compound_mut<V: MutVisitor>(visitor: &mut V, compound: &mut CompoundNode) {
+    for child in compound.children.iter_mut() {
+        visitor.visit_node(child);
+    }
+}
+
+pub fn walk_call_mut<V: MutVisitor>(visitor: &mut V, call: &mut Call) {
+    for arg in call.args.iter_mut() {
+        visitor.visit_node(arg);
+    }
+}
@@ -0,0 +1,126 @@
+use simple_pascal_ast::{
+    arena::*,
+    node::*,
+    parser::*,
+    token::op::OpKind,
+};
+
+const SOURCE: &str = r"BEGIN
+    y := 2;
+    BEGIN
+        a := 3;
+        c := -a - y
+    END;
+END.";
+
+#[test]
+fn round_trip() {
+    let node = Parser::new().parse(SOURCE).unwrap();
+    let ast = Ast::from_node(&node);
+
+    assert_eq!(ast.to_node(ast.root().unwrap()), Ok(node));
+    assert_eq!(Parser::new().parse_arena(SOURCE).unwrap(), ast);
+}
+
+#[test]
+fn pre_order_ids_and_parents() {
+    let ast = Parser::new().parse_arena(SOURCE).unwrap();
+    let root = ast.root().unwrap();
+
+    assert_eq!(root.index(), 0);
+    assert_eq!(ast.parent(root), None);
+
+    let AstNode::Compound(statements) = &ast[root] else {
+        panic!("expected a compound statement, got {:?}", ast[root]);
+    };
+    assert_eq!(statements.len(), 3);
+
+    for id in ast.ids().skip(1) {
+        let parent = ast.parent(id).unwrap();
+        assert!(parent < id);
+        assert!(ast[parent].children().contains(&id));
+    }
+
+    // `a` in `-a`
+    let a = ast
+        .ids()
+        .rfind(|&id| ast[id] == AstNode::Ident(String::from("a")))
+        .unwrap();
+    let ancestors: Vec<_> = ast.ancestors(a).map(|id| &ast[id]).collect();
+
+    assert!(matches!(ancestors[0], AstNode::UnaryOp { op: OpKind::Minus, .. }));
+    assert!(matches!(ancestors[1], AstNode::BinOp { op: OpKind::Minus, .. }));
+    assert!(matches!(ancestors[2], AstNode::BinOp { op: OpKind::AssignEq, .. }));
+    assert_eq!(ancestors.len(), 5);
+}
+
+#[test]
+fn alloc_bottom_up() {
+    let mut ast = Ast::default();
+    let x = ast.alloc(AstNode::Ident(String::from("x"))).unwrap();
+    let y = ast.alloc(AstNode::Ident(String::from("y"))).unwrap();
+    let assign = ast.alloc(AstNode::BinOp { lhs: x, op: OpKind::AssignEq, rhs: y }).unwrap();
+
+    assert_eq!(ast.len(), 3);
+    assert_eq!(ast.root(), Some(assign));
+    assert_eq!(ast.parent(x), Some(assign));
+    assert_eq!(ast.parent(y), Some(assign));
+    assert_eq!(
+        ast.to_node(assign),
+        Ok(BinOp::new(
+            Node::Ident(String::from("x")),
+            OpKind::AssignEq,
+            Node::Ident(String::from("y"))
+        ))
+    );
+}
+
+#[test]
+fn alloc_checks_children() {
+    let mut ast = Ast::default();
+    let x = ast.alloc(AstNode::Ident(String::from("x"))).unwrap();
+    let foreign = Parser::new().parse_arena(SOURCE).unwrap().ids().last().unwrap();
+
+    assert_eq!(
+        ast.alloc(AstNode::UnaryOp { op: OpKind::Minus, node: foreign }),
+        Err(ArenaErr::UnknownNode(foreign))
+    );
+    assert_eq!(
+        ast.alloc(AstNode::BinOp { lhs: x, op: OpKind::Plus, rhs: x }),
+        Err(ArenaErr::HasParent(x))
+    );
+
+    let neg = ast.alloc(AstNode::UnaryOp { op: OpKind::Minus, node: x }).unwrap();
+    assert_eq!(
+        ast.alloc(AstNode::UnaryOp { op: OpKind::Minus, node: x }),
+        Err(ArenaErr::HasParent(x))
+    );
+    // failed allocations leave the arena as it was
+    assert_eq!(ast.len(), 2);
+    assert_eq!(ast.root(), Some(neg));
+
+    ast.set_root(x).unwrap();
+    assert_eq!(ast.root(), Some(x));
+    assert_eq!(ast.set_root(foreign), Err(ArenaErr::UnknownNode(foreign)));
+
+    // lookups of a foreign id fail instead of panicking
+    assert_eq!(ast.get(foreign), None);
+    assert_eq!(ast.parent(foreign), None);
+    assert_eq!(ast.ancestors(foreign).count(), 0);
+    assert_eq!(ast.to_node(foreign), Err(ArenaErr::UnknownNode(foreign)));
+}
+
+#[test]
+fn side_table() {
+    let ast = Parser::new().parse_arena(SOURCE).unwrap();
+    let mut depth = NodeMap::new();
+
+    for id in ast.ids() {
+        depth.insert(id, ast.ancestors(id).count());
+    }
+
+    assert_eq!(depth.get(ast.root().unwrap()), Some(&0));
+    assert_eq!(depth.iter().map(|(_, depth)| *depth).max(), Some(5));
+    assert_eq!(depth.remove(ast.root().unwrap()), Some(0));
+    assert_eq!(depth.get(ast.root().unwrap()), None);
+}
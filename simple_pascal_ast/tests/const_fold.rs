@@ -0,0 +1,112 @@
+use simple_pascal_ast::{
+    const_fold::const_fold,
+    dialect::Dialect,
+    parser::*,
+};
+
+fn folded(expr: &str, dialect: Dialect) -> String {
+    let ast = Parser::calculator()
+        .parse(&format!("BEGIN x := {} END.", expr))
+        .unwrap();
+
+    const_fold(ast, dialect).to_sexpr()
+}
+
+fn assert_folds(cases: &[(&str, &str)], dialect: Dialect) {
+    for (expr, expected) in cases {
+        assert_eq!(
+            folded(expr, dialect),
+            format!("(begin (:= x {}))", expected),
+            "folding `{}` in {}",
+            expr,
+            dialect
+        );
+    }
+}
+
+#[test]
+fn fold_constants() {
+    assert_folds(
+        &[
+            ("10 * 2 / 4", "5.0"),
+            ("2 + 3 * 4", "14"),
+            ("1.5 + 2", "3.5"),
+            ("7 DIV 2 + 7 MOD 2", "4"),
+            ("2 ^ 3 ^ 2", "512.0"),
+            ("-(2 + 3)", "-5"),
+            ("-2.5 * 2", "-5.0"),
+            ("+4", "4"),
+            ("1 / 3", "0.3333333333333333"),
+            ("y + 2 * 3", "(+ y 6)"),
+            // only whole constant sub-expressions fold
+            ("y + 2 + 3", "(+ (+ y 2) 3)"),
+        ],
+        Dialect::Iso7185,
+    );
+}
+
+#[test]
+fn identities() {
+    assert_folds(
+        &[
+            ("y * 1", "y"),
+            ("1 * y", "y"),
+            ("y + 0", "y"),
+            ("0 + y", "y"),
+            ("y - 0", "y"),
+            ("y * (3 - 2) + (1 - 1)", "y"),
+            ("- -y", "y"),
+            // these would change the type or drop an operand
+            ("y * 1.0", "(* y 1.0)"),
+            ("y + 0.0", "(+ y 0.0)"),
+            ("y / 1", "(/ y 1)"),
+            ("y * 0", "(* y 0)"),
+            ("0 - y", "(- 0 y)"),
+        ],
+        Dialect::Iso7185,
+    );
+}
+
+#[test]
+fn keep_trapping_operations() {
+    assert_folds(
+        &[
+            ("1 / 0", "(/ 1 0)"),
+            ("1 / (2 - 2)", "(/ 1 0)"),
+            ("1 DIV 0", "(DIV 1 0)"),
+            ("1 MOD 0", "(MOD 1 0)"),
+            ("7.0 DIV 2", "(DIV 7.0 2)"),
+            ("-7 MOD -2", "(MOD -7 -2)"),
+            ("2147483647 + 1", "(+ 2147483647 1)"),
+            ("-2147483647 - 1", "(- -2147483647 1)"),
+        ],
+        Dialect::Iso7185,
+    );
+
+    // ISO `MOD` is never negative, the others truncate
+    assert_folds(&[("-7 MOD 2", "1")], Dialect::Iso7185);
+    assert_folds(
+        &[
+            ("-7 MOD 2", "-1"),
+            ("-7 MOD -2", "-1"),
+            ("2147483647 + 1", "2147483648"),
+        ],
+        Dialect::FreePascal,
+    );
+}
+
+#[test]
+fn before_and_after() {
+    let ast = Parser::new()
+        .parse("BEGIN a := 3 * 4; BEGIN b := a * (2 - 1) END; ; c := 'x' END.")
+        .unwrap();
+
+    assert_eq!(
+        ast.to_sexpr(),
+        "(begin (:= a (* 3 4)) (begin (:= b (* a (- 2 1)))) () (:= c 'x'))"
+    );
+    assert_eq!(
+        const_fold(ast, Dialect::default()).to_sexpr(),
+        "(begin (:= a 12) (begin (:= b a)) () (:= c 'x'))"
+    );
+}
@@ -0,0 +1,149 @@
+use simple_pascal_ast::{
+    cst::{self, ast::*, SyntaxKind},
+    parser::*,
+    token::op::OpKind,
+};
+
+fn sources() -> Vec<&'static str> {
+    vec![
+        r"BEGIN
+        END.",
+        r"BEGIN
+        ;-2;
+        END.",
+        r"BEGIN
+            x:= 2 + 3 * (2 + 3);
+            y:= 2 / 2 - 2 + 3 * ((1 + 1) + (1 + 1));
+        END.",
+        r"{ header }
+        BEGIN
+            y := 2; (* keep me *)
+            BEGIN
+                a := 3;
+                a := a;
+                b := 10 + a + 10 * y / 4;
+                c := a - b
+            END;
+            x := 11;
+        END.   { trailer }
+        ",
+        r"BEGIN
+            z := 3;
+            ;y :=5;
+            ;;;
+            BEGIN
+                c := 2;
+                BEGIN
+                    z := c;
+                END;
+                z := y;
+            END;
+            BEGIN
+                a := 4;
+                y := a + z;
+            END;
+            ;4 - y;
+        END.",
+        r"BEGIN
+            x := Max(a, 2 * b) + Tick( );
+            Log(x, -Max(1,(2)));
+            Reset()
+        END.",
+    ]
+}
+
+#[test]
+fn reprint_byte_for_byte() {
+    for source in sources() {
+        let tree = cst::parse(source).unwrap();
+
+        assert_eq!(source, tree.to_string());
+        assert_eq!(source.len(), tree.text_len());
+    }
+}
+
+#[test]
+fn lower_to_ast() {
+    let mut parser = Parser::calculator();
+
+    for source in sources() {
+        let tree = cst::parse(source).unwrap();
+
+        assert_eq!(
+            parser.parse(source).unwrap(),
+            Program::cast(&tree).unwrap().to_node()
+        );
+    }
+}
+
+#[test]
+fn keep_comments() {
+    let source = sources()[3];
+    let tree = cst::parse(source).unwrap();
+
+    let comments: Vec<_> = tree
+        .descendant_tokens()
+        .into_iter()
+        .filter(|token| token.kind() == SyntaxKind::Comment)
+        .map(|token| token.text())
+        .collect();
+
+    assert_eq!(comments, vec!["{ header }", "(* keep me *)", "{ trailer }"]);
+}
+
+#[test]
+fn typed_views() {
+    let tree = cst::parse("BEGIN x := (1 + y) * 2 END.").unwrap();
+    let program = Program::cast(&tree).unwrap();
+
+    let stmt = program.compound().unwrap().statements().next().unwrap();
+    let assignment = match stmt {
+        Statement::Assignment(assignment) => assignment,
+        _ => panic!("expected assignment, got {:?}", stmt),
+    };
+    assert_eq!(assignment.target().unwrap().name(), Some("x"));
+
+    let value = match assignment.value().unwrap() {
+        Expr::Bin(bin) => bin,
+        expr => panic!("expected binary expression, got {:?}", expr),
+    };
+    assert_eq!(value.op(), Some(OpKind::Star));
+    assert_eq!(value.lhs().unwrap().syntax().to_string(), " (1 + y)");
+    assert!(matches!(value.rhs(), Some(Expr::Literal(_))));
+}
+
+#[test]
+#[should_panic]
+fn unterminated_comment() {
+    cst::parse("BEGIN { END.").unwrap();
+}
+
+#[test]
+#[should_panic]
+fn trailing_tokens() {
+    cst::parse("BEGIN END. x").unwrap();
+}
+
+#[test]
+fn right_associative_power() {
+    let tree = cst::parse("BEGIN x := 2 ^ 3 ^ 2 END.").unwrap();
+    let program = Program::cast(&tree).unwrap();
+
+    assert_eq!(
+        Parser::new().parse("BEGIN x := 2 ^ 3 ^ 2 END.").unwrap(),
+        program.to_node()
+    );
+}
+
+#[test]
+fn error_line() {
+    use simple_pascal_ast::{dialect::Dialect, parser::Parser};
+
+    // the same line the parser reports, comments and all
+    for source in ["BEGIN\n  x := (1\nEND.", "BEGIN { a\ncomment }\n  x := 1 +\n\n; END.", "BEGIN\n  x := 1\nEND"] {
+        let mut parser = Parser::calculator();
+        assert!(parser.parse(source).is_err());
+        let (line, _) = cst::parse_with_line(source, Dialect::default()).unwrap_err();
+        assert_eq!(line, parser.line(), "{:?}", source);
+    }
+}
@@ -0,0 +1,52 @@
+use simple_pascal_ast::{
+    dialect::Dialect,
+    node::*,
+    parser::*,
+    token::literal::Literal,
+};
+
+#[test]
+fn sexpr_leaves() {
+    let mut parser = Parser::calculator();
+    parser.set_dialect(Dialect::FreePascal);
+    let ast = parser
+        .parse("BEGIN s := 'don''t'; f := 2.5 MOD -x;; 1 % 2 END.")
+        .unwrap();
+
+    assert_eq!(
+        ast.to_sexpr(),
+        "(begin (:= s 'don''t') (:= f (MOD 2.5 (- x))) () (% 1 2))"
+    );
+    assert_eq!(Node::Literal(Literal::String(String::new())).to_sexpr(), "''");
+}
+
+#[test]
+fn dot() {
+    let ast = Parser::new().parse("BEGIN x := -1 + y; END.").unwrap();
+
+    assert_eq!(
+        ast.to_dot(),
+        r#"digraph ast {
+    node [shape=box, fontname=monospace];
+    n0 [label="Compound"];
+    n1 [label="BinOp :="];
+    n2 [label="Ident x"];
+    n1 -> n2 [label="lhs"];
+    n3 [label="BinOp +"];
+    n4 [label="UnaryOp -"];
+    n5 [label="Literal 1"];
+    n4 -> n5;
+    n3 -> n4 [label="lhs"];
+    n6 [label="Ident y"];
+    n3 -> n6 [label="rhs"];
+    n1 -> n3 [label="rhs"];
+    n0 -> n1;
+    n7 [label="Empty"];
+    n0 -> n7;
+}
+"#
+    );
+
+    let ast = Parser::new().parse(r#"BEGIN s := '"\' END."#).unwrap();
+    assert!(ast.to_dot().contains(r#"[label="Literal '\"\\'"];"#));
+}
@@ -0,0 +1,158 @@
+use simple_pascal_ast::{
+    format::{format, format_with, FormatOptions},
+    node::Node,
+    parser::*,
+};
+
+#[test]
+fn canonical_layout() {
+    let source = r"BEGIN
+  y:=2;
+     BEGIN a:=3;a := a;;
+  b:=10+a+10*y/4; c:=a-(b) END;
+;x :=  -11;END.";
+
+    assert_eq!(
+        format(source).unwrap(),
+        r"BEGIN
+    y := 2;
+    BEGIN
+        a := 3;
+        a := a;
+        b := 10 + a + 10 * y / 4;
+        c := a - (b)
+    END;
+    x := -11
+END.
+"
+    );
+}
+
+#[test]
+fn empty_program() {
+    assert_eq!(format("BEGIN END.").unwrap(), "BEGIN\nEND.\n");
+}
+
+#[test]
+fn calls() {
+    assert_eq!(
+        format("BEGIN Log( x ,Max(1,2) );y:=Tick( ) END.").unwrap(),
+        "BEGIN\n    Log(x, Max(1, 2));\n    y := Tick()\nEND.\n"
+    );
+}
+
+#[test]
+fn keep_comments() {
+    let source = r"{ header }
+BEGIN
+    y := 2; (* trailing *)
+    { before block }
+    BEGIN
+        a := 1 + { inline } 2
+    END { after block }
+END. { eof }";
+
+    assert_eq!(
+        format(source).unwrap(),
+        r"{ header }
+BEGIN
+    y := 2; (* trailing *)
+    { before block }
+    BEGIN
+        a := 1 + { inline } 2
+    END { after block }
+END. { eof }
+"
+    );
+}
+
+#[test]
+fn wrap_long_expressions() {
+    let options = FormatOptions {
+        max_width: 30,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        format_with(
+            "BEGIN total := first + second * third - (fourth + fifth) END.",
+            &options
+        )
+        .unwrap(),
+        r"BEGIN
+    total := first
+        + second * third
+        - (fourth + fifth)
+END.
+"
+    );
+}
+
+#[test]
+fn idempotent() {
+    let sources = [
+        "BEGIN ;-2; END.",
+        "BEGIN z := 3; ;y :=5; ;;; BEGIN c := 2; BEGIN z := c; END; z := y; END; ;4 - y; END.",
+        "{a}BEGIN{b}x{c}:={d}1{e};{f}END{g}.{h}",
+    ];
+
+    for source in sources {
+        let formatted = format(source).unwrap();
+        assert_eq!(formatted, format(&formatted).unwrap());
+    }
+}
+
+#[test]
+fn preserve_semantics() {
+    fn strip_empty(node: Node) -> Node {
+        match node {
+            Node::Compound(mut compound) => {
+                compound.children = std::mem::take(&mut compound.children)
+                    .into_iter()
+                    .filter(|child| *child != Node::None)
+                    .map(strip_empty)
+                    .collect();
+                Node::Compound(compound)
+            }
+            node => node,
+        }
+    }
+
+    let source = r"BEGIN
+        z := 3;
+        ;y :=5;
+        BEGIN
+            c := 2 * (z - 1);
+        END;
+        ;4 - y;
+    END.";
+    let mut parser = Parser::calculator();
+
+    assert_eq!(
+        strip_empty(parser.parse(source).unwrap()),
+        strip_empty(parser.parse(&format(source).unwrap()).unwrap())
+    );
+}
+
+#[test]
+fn dialect_tokens() {
+    use simple_pascal_ast::dialect::Dialect;
+
+    let options = FormatOptions {
+        dialect: Dialect::FreePascal,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        format_with(
+            "begin // start\nx := 7 mod { two } 2 // tail\n; s := 'a'#9 + // op\n'b' end. // eof",
+            &options
+        )
+        .unwrap(),
+        r"BEGIN // start
+    x := 7 MOD { two } 2; // tail
+    s := 'a'#9 + (* op *) 'b'
+END. // eof
+"
+    );
+}
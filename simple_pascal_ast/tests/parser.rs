@@ -1,10 +1,10 @@
 use simple_pascal_ast::{
     node::*,
-    token::{op::OpKind, literal::Literal},
+    token::{delim::DelimKind, keyword::Keyword, literal::Literal, op::OpKind, Token},
     parser::*
 };
 
-fn exprs_and_trees() -> (Vec<&'static str>, Vec<Node>) {
+fn exprs_and_trees() -> (Vec<&'static str>, Vec<&'static str>) {
     (vec![
         r"BEGIN
         END.",
@@ -44,213 +44,33 @@ fn exprs_and_trees() -> (Vec<&'static str>, Vec<Node>) {
         END."
     ],
     vec![
-        CompoundNode::from_list(
-            NodeList::from([Node::None])
-        ),
-        CompoundNode::from_list(
-            NodeList::from([
-                Node::None,
-                UnaryOp::new(
-                    OpKind::Minus,
-                    Node::Literal(Literal::Integer(String::from("2")))
-                ),
-                Node::None
-            ])
-        ),
-        CompoundNode::from_list(
-            NodeList::from([
-                BinOp::new(
-                    Node::Ident(String::from("x")),
-                    OpKind::AssignEq,
-                    BinOp::new(
-                        Node::Literal(Literal::Integer(String::from("2"))),
-                        OpKind::Plus,
-                        BinOp::new(
-                            Node::Literal(Literal::Integer(String::from("3"))),
-                            OpKind::Star,
-                            BinOp::new(
-                                Node::Literal(Literal::Integer(String::from("2"))),
-                                OpKind::Plus,
-                                Node::Literal(Literal::Integer(String::from("3"))),
-                            )
-                        )
-                    )
-                ),
-                BinOp::new(
-                    Node::Ident(String::from("y")),
-                    OpKind::AssignEq,
-                    BinOp::new(
-                        BinOp::new(
-                            BinOp::new(
-                                Node::Literal(Literal::Integer(String::from("2"))),
-                                OpKind::Slash,
-                                Node::Literal(Literal::Integer(String::from("2"))),
-                            ),
-                            OpKind::Minus,
-                            Node::Literal(Literal::Integer(String::from("2"))),
-                        ),
-                        OpKind::Plus,
-                        BinOp::new(
-                            Node::Literal(Literal::Integer(String::from("3"))),
-                            OpKind::Star,
-                            BinOp::new(
-                                BinOp::new(
-                                    Node::Literal(Literal::Integer(String::from("1"))),
-                                    OpKind::Plus,
-                                    Node::Literal(Literal::Integer(String::from("1"))),
-                                ),
-                                OpKind::Plus,
-                                BinOp::new(
-                                    Node::Literal(Literal::Integer(String::from("1"))),
-                                    OpKind::Plus,
-                                    Node::Literal(Literal::Integer(String::from("1"))),
-                                )
-                            )
-                        )
-                    )
-                ),
-                Node::None,
-            ])
-        ),
-        CompoundNode::from_list(
-            NodeList::from([
-                BinOp::new(
-                    Node::Ident(String::from("y")),
-                    OpKind::AssignEq,
-                    Node::Literal(Literal::Integer(String::from("2")))
-                ),
-                CompoundNode::from_list(
-                    NodeList::from([
-                        BinOp::new(
-                            Node::Ident(String::from("a")),
-                            OpKind::AssignEq,
-                            Node::Literal(Literal::Integer(String::from("3")))
-                        ),
-                        BinOp::new(
-                            Node::Ident(String::from("a")),
-                            OpKind::AssignEq,
-                            Node::Ident(String::from("a")),
-                        ),
-                        BinOp::new(
-                            Node::Ident(String::from("b")),
-                            OpKind::AssignEq,
-                            BinOp::new(
-                                BinOp::new(
-                                    Node::Literal(Literal::Integer(String::from("10"))),
-                                    OpKind::Plus,
-                                    Node::Ident(String::from("a")),
-                                ),
-                                OpKind::Plus,
-                                BinOp::new(
-                                    BinOp::new(
-                                        Node::Literal(Literal::Integer(String::from("10"))),
-                                        OpKind::Star,
-                                        Node::Ident(String::from("y")),
-                                    ),
-                                    OpKind::Slash,
-                                    Node::Literal(Literal::Integer(String::from("4"))),
-                                )
-                            )
-                        ),
-                        BinOp::new(
-                            Node::Ident(String::from("c")),
-                            OpKind::AssignEq,
-                            BinOp::new(
-                                Node::Ident(String::from("a")),
-                                OpKind::Minus,
-                                Node::Ident(String::from("b")),
-                            )
-                        )
-                    ])
-                ),
-                BinOp::new(
-                    Node::Ident(String::from("x")),
-                    OpKind::AssignEq,
-                    Node::Literal(Literal::Integer(String::from("11")))
-                ),
-                Node::None
-            ])
-        ),
-        CompoundNode::from_list(
-            NodeList::from([
-                BinOp::new(
-                    Node::Ident(String::from("z")),
-                    OpKind::AssignEq,
-                    Node::Literal(Literal::Integer(String::from("3")))
-                ),
-                Node::None,
-                BinOp::new(
-                    Node::Ident(String::from("y")),
-                    OpKind::AssignEq,
-                    Node::Literal(Literal::Integer(String::from("5")))
-                ),
-                Node::None,
-                Node::None,
-                Node::None,
-                CompoundNode::from_list(
-                    NodeList::from([
-                        BinOp::new(
-                            Node::Ident(String::from("c")),
-                            OpKind::AssignEq,
-                            Node::Literal(Literal::Integer(String::from("2")))
-                        ),
-                        CompoundNode::from_list(
-                            NodeList::from([
-                                BinOp::new(
-                                    Node::Ident(String::from("z")),
-                                    OpKind::AssignEq,
-                                    Node::Ident(String::from("c"))
-                                ),
-                                Node::None,
-                            ])
-                        ),
-                        BinOp::new(
-                            Node::Ident(String::from("z")),
-                            OpKind::AssignEq,
-                            Node::Ident(String::from("y"))
-                        ),
-                        Node::None
-                    ])
-                ),
-                CompoundNode::from_list(
-                    NodeList::from([
-                        BinOp::new(
-                            Node::Ident(String::from("a")),
-                            OpKind::AssignEq,
-                            Node::Literal(Literal::Integer(String::from("4")))
-                        ),
-                        BinOp::new(
-                            Node::Ident(String::from("y")),
-                            OpKind::AssignEq,
-                            BinOp::new(
-                                Node::Ident(String::from("a")),
-                                OpKind::Plus,
-                                Node::Ident(String::from("z"))
-                            ),
-                        ),
-                        Node::None
-                    ])
-                ),
-                Node::None,
-                BinOp::new(
-                    Node::Literal(Literal::Integer(String::from("4"))),
-                    OpKind::Minus,
-                    Node::Ident(String::from("y"))
-                ),
-                Node::None
-            ])
-        )
+        "(begin ())",
+        "(begin () (- 2) ())",
+        "(begin \
+            (:= x (+ 2 (* 3 (+ 2 3)))) \
+            (:= y (+ (- (/ 2 2) 2) (* 3 (+ (+ 1 1) (+ 1 1))))) \
+            ())",
+        "(begin \
+            (:= y 2) \
+            (begin (:= a 3) (:= a a) (:= b (+ (+ 10 a) (/ (* 10 y) 4))) (:= c (- a b))) \
+            (:= x 11) \
+            ())",
+        "(begin \
+            (:= z 3) () (:= y 5) () () () \
+            (begin (:= c 2) (begin (:= z c) ()) (:= z y) ()) \
+            (begin (:= a 4) (:= y (+ a z)) ()) \
+            () (- 4 y) ())",
     ])
 }
 
 #[test]
 fn parse() {
-    let mut parser = Parser::new();
+    let mut parser = Parser::calculator();
 
     let (exprs, trees) = exprs_and_trees();
 
     for (i, expr) in exprs.iter().enumerate() {
-        assert_eq!(trees[i], parser.parse(expr).unwrap());
+        assert_eq!(trees[i], parser.parse(expr).unwrap().to_sexpr());
     }
 }
 
@@ -295,4 +115,310 @@ fn invalid_assignment() {
     BEGIN
         2 := 2;
     END.").unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn precedence_and_associativity() {
+    fn sexpr(expr: &str) -> String {
+        Parser::new()
+            .parse(&format!("BEGIN x := {} END.", expr))
+            .unwrap()
+            .to_sexpr()
+    }
+
+    // `^` is right-associative
+    assert_eq!(sexpr("2 ^ 3 ^ 2"), "(begin (:= x (^ 2 (^ 3 2))))");
+
+    // and binds tighter than `*`
+    assert_eq!(sexpr("2 * 3 ^ 2"), "(begin (:= x (* 2 (^ 3 2))))");
+
+    // `-` and `/` stay left-associative
+    assert_eq!(
+        sexpr("8 - 4 - 2 / 2 / 2"),
+        "(begin (:= x (- (- 8 4) (/ (/ 2 2) 2))))"
+    );
+
+    // unary minus binds looser than `^` but tighter than `*`
+    assert_eq!(sexpr("-2 ^ 2 * 3"), "(begin (:= x (* (- (^ 2 2)) 3)))");
+}
+
+#[test]
+fn strict_statements() {
+    let mut parser = Parser::new();
+
+    assert_eq!(
+        parser
+            .parse("BEGIN ; x := 1;; BEGIN END; y := x END.")
+            .unwrap()
+            .to_sexpr(),
+        "(begin () (:= x 1) () (begin ()) (:= y x))"
+    );
+}
+
+#[test]
+fn reject_expression_statements() {
+    let mut parser = Parser::new();
+
+    let cases = [
+        (
+            "BEGIN -2; END.",
+            "Statement can't start with operator `-`: expected an assignment, `BEGIN` or an empty statement",
+        ),
+        (
+            "BEGIN 4 - y END.",
+            "Statement can't start with literal `4`: expected an assignment, `BEGIN` or an empty statement",
+        ),
+        (
+            "BEGIN (x) END.",
+            "Statement can't start with `(`: expected an assignment, `BEGIN` or an empty statement",
+        ),
+        (
+            "BEGIN y * 2; END.",
+            "Expected `:=` after `y`, got operator `*`: expressions can't be used as statements",
+        ),
+        (
+            "BEGIN y; END.",
+            "Expected `:=` after `y`, got `;`: procedure calls are not supported",
+        ),
+    ];
+
+    for (source, message) in cases {
+        assert_eq!(
+            parser.parse(source),
+            Err(ParserErr::InvalidStatement(String::from(message)))
+        );
+    }
+}
+
+#[test]
+fn calculator_expression_statements() {
+    let mut parser = Parser::new();
+    parser.set_calculator(true);
+
+    // the leading identifier is the first operand of the expression
+    assert_eq!(
+        parser.parse("BEGIN y * 2 + 1 END.").unwrap().to_sexpr(),
+        "(begin (+ (* y 2) 1))"
+    );
+}
+
+#[test]
+fn dialects() {
+    use simple_pascal_ast::dialect::Dialect;
+
+    let source = r"begin
+        x := 7 mod 2 DIV 1; { ISO comment *)
+        s := 'it''s';
+    End.";
+
+    assert_eq!(
+        Parser::with_dialect(Dialect::Iso7185)
+            .parse(source)
+            .unwrap()
+            .to_sexpr(),
+        "(begin (:= x (DIV (MOD 7 2) 1)) (:= s 'it''s') ())"
+    );
+
+    // `{` only closes with `}` outside of ISO Pascal
+    assert!(Parser::with_dialect(Dialect::TurboPascal).parse(source).is_err());
+
+    // string literals are decoded, so the tab is printed as is
+    let mut parser = Parser::with_dialect(Dialect::FreePascal);
+    assert_eq!(
+        parser
+            .parse("BEGIN s := 'a'#9'b'; // tab\n e := '' END.")
+            .unwrap(),
+        CompoundNode::from_list(NodeList::from([
+            BinOp::new(
+                Node::Ident(String::from("s")),
+                OpKind::AssignEq,
+                Node::Literal(Literal::String(String::from("a\tb")))
+            ),
+            BinOp::new(
+                Node::Ident(String::from("e")),
+                OpKind::AssignEq,
+                Node::Literal(Literal::String(String::new()))
+            ),
+        ]))
+    );
+
+    // empty strings, char codes and line comments are extensions
+    let mut parser = Parser::with_dialect(Dialect::Iso7185);
+    assert!(parser.parse("BEGIN e := '' END.").is_err());
+    assert!(parser.parse("BEGIN e := #9 END.").is_err());
+    assert!(parser.parse("BEGIN x := 1 // no\n END.").is_err());
+}
+
+#[test]
+fn dialect_reserved_words_and_integers() {
+    use simple_pascal_ast::dialect::Dialect;
+
+    let xor = "BEGIN xor := 1 END.";
+    assert!(Parser::with_dialect(Dialect::Iso7185).parse(xor).is_ok());
+    assert_eq!(
+        Parser::with_dialect(Dialect::TurboPascal).parse(xor),
+        Err(ParserErr::InvalidToken(String::from(
+            "`xor` is a reserved word in Turbo Pascal and isn't supported yet"
+        )))
+    );
+
+    let big = "BEGIN x := 4294967296 END.";
+    assert!(Parser::with_dialect(Dialect::TurboPascal).parse(big).is_err());
+    assert!(Parser::with_dialect(Dialect::FreePascal).parse(big).is_ok());
+
+    assert_eq!(
+        Parser::new().parse("BEGIN x := 5 % 2 END."),
+        Err(ParserErr::InvalidToken(String::from(
+            "`%` is not an operator in ISO 7185, use `MOD`"
+        )))
+    );
+    assert!(Parser::calculator().parse("BEGIN x := 5 % 2 END.").is_err());
+    assert!(Parser::with_dialect(Dialect::TurboPascal).parse("BEGIN x := 5 % 2 END.").is_err());
+    assert!(Parser::with_dialect(Dialect::FreePascal).parse("BEGIN x := 5 % 2 END.").is_ok());
+}
+
+#[test]
+fn statement_lines() {
+    let mut parser = Parser::new();
+    let ast = parser.parse(r"
+BEGIN
+    y := 2;
+    BEGIN
+        a := 3; b := a
+    END;
+
+    x := 11
+END.").unwrap();
+
+    assert_eq!(ast.to_sexpr(), "(begin (:= y 2) (begin (:= a 3) (:= b a)) (:= x 11))");
+    assert_eq!(parser.statement_lines(), [2, 3, 4, 5, 5, 8]);
+
+    parser.parse("BEGIN END.").unwrap();
+    assert_eq!(parser.statement_lines(), [1, 1]);
+}
+
+#[test]
+fn tokenize_and_error_lines() {
+    let mut parser = Parser::new();
+    assert_eq!(
+        parser.tokenize("BEGIN\n  x := 1 { one }\nEND.").unwrap(),
+        [
+            (1, Token::Keyword(Keyword::Begin)),
+            (2, Token::Ident(String::from("x"))),
+            (2, Token::Op(OpKind::AssignEq)),
+            (2, Token::Literal(Literal::Integer(String::from("1")))),
+            (3, Token::Keyword(Keyword::End)),
+            (3, Token::Delim(DelimKind::Dot)),
+        ]
+    );
+
+    let err = parser.parse("BEGIN\n  x := (1 + 2;\nEND.").unwrap_err();
+    assert_eq!(parser.line(), 2);
+    assert_eq!(err.to_string(), "Expected close delimiter for Paren");
+    assert_eq!(ParserErr::InvalidExpr.to_string(), "Invalid expression");
+}
+
+#[test]
+fn parse_statements() {
+    let mut parser = Parser::calculator();
+    let sexprs = |nodes: Vec<Node>| nodes.iter().map(Node::to_sexpr).collect::<Vec<_>>();
+
+    assert_eq!(sexprs(parser.parse_statements("x := 1; x + 2").unwrap()), ["(:= x 1)", "(+ x 2)"]);
+    assert_eq!(
+        sexprs(parser.parse_statements("BEGIN y := 2 END;").unwrap()),
+        ["(begin (:= y 2))", "()"]
+    );
+    assert!(parser.parse_statements("x := 1 END").is_err());
+    assert!(Parser::new().parse_statements("x + 2").is_err());
+}
+
+#[test]
+fn calls() {
+    let ast = Parser::new()
+        .parse("BEGIN x := Max(a, 2 * b) + Tick(); Log(x, -Max(1, (2))) END.")
+        .unwrap();
+
+    assert_eq!(
+        ast.to_sexpr(),
+        "(begin (:= x (+ (call Max a (* 2 b)) (call Tick))) (call Log x (- (call Max 1 2))))"
+    );
+    assert_eq!(
+        Parser::calculator().parse("BEGIN Tick() * 2 END.").unwrap().to_sexpr(),
+        "(begin (* (call Tick) 2))"
+    );
+
+    let mut parser = Parser::new();
+    assert_eq!(
+        parser.parse("BEGIN Log(x y) END."),
+        Err(ParserErr::TokenMismatch(String::from(
+            "Expected `,` or `)` in the arguments of `Log`, got identifier `y`"
+        )))
+    );
+    assert!(parser.parse("BEGIN Log(x,) END.").is_err());
+    assert!(parser.parse("BEGIN Log(, x) END.").is_err());
+    // a call is a statement, an expression built from it isn't
+    assert!(parser.parse("BEGIN Tick() + 1 END.").is_err());
+}
+
+#[test]
+fn nesting_depth() {
+    use simple_pascal_ast::dialect::Dialect;
+
+    let parens = |depth: usize| format!("BEGIN x := {}1{} END.", "(".repeat(depth), ")".repeat(depth));
+    let blocks = |depth: usize| format!("{}{}.", "BEGIN ".repeat(depth), " END".repeat(depth));
+    let (parens_8, parens_10, parens_100, parens_300) = (parens(8), parens(10), parens(100), parens(300));
+    let (blocks_100, blocks_300) = (blocks(100), blocks(300));
+    let minuses = format!("BEGIN x := {}1 END.", "-".repeat(300));
+
+    let mut parser = Parser::new();
+    assert_eq!(parser.max_depth(), DEFAULT_MAX_DEPTH);
+
+    assert!(parser.parse(&parens_100).is_ok());
+    assert!(parser.parse(&blocks_100).is_ok());
+    assert_eq!(parser.parse(&parens_300), Err(ParserErr::NestingTooDeep(DEFAULT_MAX_DEPTH)));
+    assert_eq!(parser.parse(&blocks_300), Err(ParserErr::NestingTooDeep(DEFAULT_MAX_DEPTH)));
+    assert_eq!(
+        parser.parse(&minuses).unwrap_err().to_string(),
+        "Nested deeper than 256 levels"
+    );
+
+    parser.set_max_depth(10);
+    assert!(parser.parse(&parens_8).is_ok());
+    assert!(parser.parse(&parens_10).is_err());
+    // a failed parse doesn't count against the next one
+    assert!(parser.parse(&parens_8).is_ok());
+
+    assert_eq!(
+        simple_pascal_ast::cst::parse(&parens_300).unwrap_err(),
+        ParserErr::NestingTooDeep(DEFAULT_MAX_DEPTH)
+    );
+    assert!(simple_pascal_ast::cst::parse_with_max_depth(&parens_8, Dialect::default(), 10).is_ok());
+    assert_eq!(
+        simple_pascal_ast::cst::parse_with_max_depth(&parens_10, Dialect::default(), 10).unwrap_err(),
+        ParserErr::NestingTooDeep(10)
+    );
+
+    // chains of operators have their own, much larger limit
+    let sum = |terms: usize| format!("BEGIN x := 1{} END.", " + 1".repeat(terms - 1));
+    let (sum_300, sum_1000, sum_100k) = (sum(300), sum(1000), sum(100_000));
+    let mut parser = Parser::new();
+    assert_eq!(parser.max_operators(), DEFAULT_MAX_OPERATORS);
+    assert!(parser.parse(&sum_300).is_ok());
+    assert!(parser.parse(&sum_1000).is_ok());
+    assert!(simple_pascal_ast::cst::parse(&sum_1000).is_ok());
+    assert_eq!(parser.parse(&sum_100k), Err(ParserErr::TooManyOperators(DEFAULT_MAX_OPERATORS)));
+    assert_eq!(
+        simple_pascal_ast::cst::parse(&sum_100k).unwrap_err(),
+        ParserErr::TooManyOperators(DEFAULT_MAX_OPERATORS)
+    );
+    // every chain an operand is in counts
+    let nested = format!("BEGIN x := 1{} + (1{}) END.", " + 1".repeat(600), " + 1".repeat(600));
+    assert!(parser.parse(&nested).is_err());
+    let siblings = format!("BEGIN x := (1{}) * (1{}) END.", " + 1".repeat(600), " + 1".repeat(600));
+    assert!(parser.parse(&siblings).is_ok());
+
+    let (sum_11, sum_12) = (sum(11), sum(12));
+    parser.set_max_operators(10);
+    assert!(parser.parse(&sum_11).is_ok());
+    assert_eq!(parser.parse(&sum_12), Err(ParserErr::TooManyOperators(10)));
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "serde")]
+
+use simple_pascal_ast::{
+    arena::Ast,
+    node::*,
+    parser::*,
+    token::{literal::Literal, op::OpKind, Token},
+};
+
+const SOURCE: &str = r"BEGIN
+    y := 2;
+    BEGIN
+        a := -3.5;
+        b := 10 + a DIV y ^ 2
+    END;
+END.";
+
+#[test]
+fn node_json() {
+    let ast = Parser::new().parse("BEGIN x := -1 END.").unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&ast).unwrap(),
+        serde_json::json!({
+            "Compound": {
+                "children": [{
+                    "BinOp": {
+                        "lhs": { "Ident": "x" },
+                        "op": "AssignEq",
+                        "rhs": {
+                            "UnaryOp": {
+                                "op": "Minus",
+                                "node": { "Literal": { "Integer": "1" } }
+                            }
+                        }
+                    }
+                }]
+            }
+        })
+    );
+}
+
+#[test]
+fn round_trip() {
+    let ast = Parser::new().parse(SOURCE).unwrap();
+    let json = serde_json::to_string(&ast).unwrap();
+
+    assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), ast);
+
+    let arena = Ast::from_node(&ast);
+    let json = serde_json::to_string(&arena).unwrap();
+
+    assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), arena);
+}
+
+#[test]
+fn token_json() {
+    let tokens = vec![
+        Token::Ident(String::from("a")),
+        Token::Op(OpKind::Div),
+        Token::Literal(Literal::String(String::from("it's"))),
+        Token::Eof,
+    ];
+    let json = serde_json::to_string(&tokens).unwrap();
+
+    assert_eq!(
+        json,
+        r#"[{"Ident":"a"},{"Op":"Div"},{"Literal":{"String":"it's"}},"Eof"]"#
+    );
+    assert_eq!(serde_json::from_str::<Vec<Token>>(&json).unwrap(), tokens);
+}
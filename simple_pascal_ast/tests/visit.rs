@@ -0,0 +1,129 @@
+use simple_pascal_ast::{
+    fold::{self, Folder},
+    node::*,
+    parser::*,
+    token::{literal::Literal, op::OpKind},
+    visit::{self, MutVisitor, Visitor},
+};
+
+const SOURCE: &str = r"BEGIN
+    y := 2;
+    BEGIN
+        a := 3;
+        b := 10 + a + 10 * y / 4;
+        c := -a - b
+    END;
+    x := 11;
+END.";
+
+#[derive(Default)]
+struct Stats<'ast> {
+    idents: Vec<&'ast str>,
+    ops: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'ast> Visitor<'ast> for Stats<'ast> {
+    fn visit_ident(&mut self, ident: &'ast str) {
+        self.idents.push(ident);
+    }
+
+    fn visit_op(&mut self, _op: &'ast OpKind) {
+        self.ops += 1;
+    }
+
+    fn visit_compound(&mut self, compound: &'ast CompoundNode) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        visit::walk_compound(self, compound);
+        self.depth -= 1;
+    }
+}
+
+#[test]
+fn visitor() {
+    let ast = Parser::new().parse(SOURCE).unwrap();
+
+    let mut stats = Stats::default();
+    stats.visit_node(&ast);
+
+    assert_eq!(stats.idents, vec!["y", "a", "b", "a", "y", "c", "a", "b", "x"]);
+    assert_eq!(stats.ops, 11);
+    assert_eq!(stats.max_depth, 2);
+}
+
+struct Rename;
+
+impl MutVisitor for Rename {
+    fn visit_ident(&mut self, ident: &mut String) {
+        ident.make_ascii_uppercase();
+    }
+
+    // only rename inside assignments' right-hand sides
+    fn visit_bin_op(&mut self, bin_op: &mut BinOp) {
+        if bin_op.op == OpKind::AssignEq {
+            self.visit_node(&mut bin_op.rhs);
+        } else {
+            visit::walk_bin_op_mut(self, bin_op);
+        }
+    }
+}
+
+#[test]
+fn mut_visitor() {
+    let mut ast = Parser::new().parse("BEGIN a := b + (c * a) END.").unwrap();
+
+    Rename.visit_node(&mut ast);
+
+    assert_eq!(
+        ast,
+        Parser::new().parse("BEGIN a := B + (C * A) END.").unwrap()
+    );
+}
+
+// drops empty statements and replaces `y` by its value
+struct Simplify;
+
+impl Folder for Simplify {
+    fn fold_compound(&mut self, compound: CompoundNode) -> Node {
+        match fold::fold_compound(self, compound) {
+            Node::Compound(mut compound) => {
+                compound.children = std::mem::take(&mut compound.children)
+                    .into_iter()
+                    .filter(|child| *child != Node::None)
+                    .collect();
+                Node::Compound(compound)
+            }
+            node => node,
+        }
+    }
+
+    fn fold_ident(&mut self, ident: String) -> Node {
+        match ident.as_str() {
+            "y" => Node::Literal(Literal::Integer(String::from("2"))),
+            _ => Node::Ident(ident),
+        }
+    }
+}
+
+#[test]
+fn folder() {
+    let ast = Parser::new().parse("BEGIN ; x := y * z;; BEGIN END END.").unwrap();
+
+    assert_eq!(
+        Simplify.fold_node(ast),
+        CompoundNode::from_list(NodeList::from([
+            BinOp::new(
+                Node::Ident(String::from("x")),
+                OpKind::AssignEq,
+                BinOp::new(
+                    Node::Literal(Literal::Integer(String::from("2"))),
+                    OpKind::Star,
+                    Node::Ident(String::from("z"))
+                )
+            ),
+            CompoundNode::new(),
+        ]))
+    );
+}
@@ -0,0 +1,83 @@
+//! Arithmetic shared by the tree-walking interpreter and the VM, so both
+//! agree on every result and every error.
+
+use simple_pascal_ast::{
+    dialect::Dialect,
+    token::{literal::Literal, op::OpKind},
+};
+
+use crate::{interpreter::InterpreterErr, native::ValueType};
+
+// `ty` is the static type of the result, see `crate::types`
+pub(crate) fn bin_op(
+    dialect: Dialect,
+    op: &OpKind,
+    ty: Option<ValueType>,
+    lhs: f64,
+    rhs: f64,
+) -> Result<f64, InterpreterErr> {
+    match op {
+        OpKind::Caret => Ok(lhs.powf(rhs)),
+        OpKind::Minus => int_result(dialect, ty, lhs - rhs),
+        OpKind::Plus => int_result(dialect, ty, lhs + rhs),
+        OpKind::Slash => Ok(lhs / rhs),
+        OpKind::Star => int_result(dialect, ty, lhs * rhs),
+        OpKind::Div => {
+            let (lhs, rhs) = int_operands(dialect, lhs, rhs)?;
+            Ok((lhs / rhs).trunc())
+        },
+        OpKind::Mod | OpKind::Percent => {
+            let (lhs, rhs) = int_operands(dialect, lhs, rhs)?;
+
+            match dialect {
+                // ISO 7185 requires a positive divisor and a non-negative result
+                Dialect::Iso7185 if rhs < 0.0 => Err(InterpreterErr::InvalidBinOp),
+                Dialect::Iso7185 => Ok(lhs.rem_euclid(rhs)),
+                _ => Ok(lhs % rhs),
+            }
+        },
+        _ => Err(InterpreterErr::InvalidBinOp)
+    }
+}
+
+// results that are statically INTEGERs must stay in the dialect's range,
+// REALs aren't limited
+fn int_result(dialect: Dialect, ty: Option<ValueType>, res: f64) -> Result<f64, InterpreterErr> {
+    if ty == Some(ValueType::Integer) && res.abs() > dialect.max_int() as f64 {
+        return Err(InterpreterErr::IntegerOverflow);
+    }
+    Ok(res)
+}
+
+// `DIV` and `MOD` are only defined for integers of the dialect's range
+fn int_operands(dialect: Dialect, lhs: f64, rhs: f64) -> Result<(f64, f64), InterpreterErr> {
+    let max_int = dialect.max_int() as f64;
+
+    for value in [lhs, rhs] {
+        if value.fract() != 0.0 {
+            return Err(InterpreterErr::InvalidBinOp);
+        }
+        if value.abs() > max_int {
+            return Err(InterpreterErr::IntegerOverflow);
+        }
+    }
+
+    if rhs == 0.0 {
+        return Err(InterpreterErr::DivisionByZero);
+    }
+    Ok((lhs, rhs))
+}
+
+pub(crate) fn literal(lit: &Literal) -> Result<f64, InterpreterErr> {
+    let res: Result<f64, _> = match lit {
+        Literal::Float(float) => float.parse(),
+        Literal::Integer(int) => int.parse(),
+        // there are no string values yet
+        Literal::String(_) => return Err(InterpreterErr::InvalidLiteral),
+    };
+
+    match res {
+        Ok(float) => Ok(float),
+        Err(_) => Err(InterpreterErr::InvalidLiteral)
+    }
+}
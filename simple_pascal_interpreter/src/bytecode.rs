@@ -0,0 +1,401 @@
+//! Compiler from [`Node`] to bytecode for the [`Vm`](crate::vm::Vm).
+//!
+//! Variables are resolved to slot indices at compile time. Programs have no
+//! branches yet, so the compiler sees assignments in the same order as the
+//! tree-walker and knows exactly which scope each name lives in: the first
+//! assignment to a name not visible from the current block gets a new slot
+//! there, later ones reuse it. Reads of names that are never assigned and
+//! other run-time errors compile to a `Trap`, raised when it's reached.
+//! Calls are rejected up front: host functions are only registered with
+//! the tree-walking `Interpreter`, compiled programs have none to call.
+//! The standard procedures of [`crate::standard`] compile to their own
+//! ops, with the strings `Write` prints kept in `Program::string`.
+//! `+`, `-` and `*` of statically INTEGER operands, as typed by
+//! `crate::types`, compile to the range checked `IntAdd`, `IntSub` and
+//! `IntMul`.
+
+use std::{collections::HashMap, fmt};
+
+use simple_pascal_ast::{dialect::Dialect, node::*, token::op::OpKind};
+
+use crate::{
+    arith,
+    interpreter::InterpreterErr,
+    native::ValueType,
+    standard::{string_arg, Standard},
+    types::Types,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// Pushes a constant.
+    Const(f64),
+    /// Pushes the value of a slot.
+    Load(u32),
+    /// Pops a value into a slot.
+    Store(u32),
+    /// Duplicates the top of the stack.
+    Dup,
+    Pop,
+    Neg,
+    /// `+` with a REAL or an untyped operand.
+    Add,
+    Sub,
+    Mul,
+    /// `+` of two INTEGERs, fails with `IntegerOverflow` past the
+    /// dialect's range.
+    IntAdd,
+    IntSub,
+    IntMul,
+    /// `/`, always a real division.
+    Slash,
+    Pow,
+    /// `DIV`
+    IntDiv,
+    /// `MOD` and `%`
+    Mod,
+    /// Records the variables of a finished block, see `Program::scope`.
+    ExitScope(u32),
+    /// Pops a number and prints it, see `standard::format_number`.
+    Write,
+    /// Prints `Program::string(index)`.
+    WriteStr(u32),
+    /// Ends the line written.
+    WriteLn,
+    /// Starts reading the next line of input, fails with `InvalidInput`
+    /// at the end of it.
+    ReadLine,
+    /// Pushes the next number of the line read, fails with `InvalidInput`
+    /// when there's none.
+    Read,
+    /// Stops the program with an error.
+    Trap(InterpreterErr),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    pub(crate) code: Vec<Op>,
+    // names and slots of the variables of every block, by scope index
+    pub(crate) scopes: Vec<Vec<(String, u32)>>,
+    pub(crate) slots: u32,
+    // (code offset, source line) where each statement's code starts
+    pub(crate) lines: Vec<(u32, u32)>,
+    pub(crate) dialect: Dialect,
+    // what `WriteStr` prints, by index
+    pub(crate) strings: Vec<String>,
+}
+
+impl Program {
+    pub fn code(&self) -> &[Op] {
+        &self.code
+    }
+
+    /// Variables assigned in the block that `ExitScope(scope)` closes.
+    pub fn scope(&self, scope: u32) -> &[(String, u32)] {
+        &self.scopes[scope as usize]
+    }
+
+    /// The string `WriteStr(index)` prints.
+    pub fn string(&self, index: u32) -> &str {
+        &self.strings[index as usize]
+    }
+
+    /// Number of variable slots the program needs.
+    pub fn slots(&self) -> u32 {
+        self.slots
+    }
+
+    /// Dialect whose rules the program runs with, saved in `.pcode` files.
+    /// The compiler doesn't depend on it, so it's ISO 7185 until set.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    /// Source line of the statement the op at `offset` belongs to, if the
+    /// program was compiled with lines.
+    pub fn line_at(&self, offset: usize) -> Option<u32> {
+        let idx = self.lines.partition_point(|&(start, _)| start as usize <= offset);
+        Some(self.lines.get(idx.checked_sub(1)?)?.1)
+    }
+}
+
+/// A tree that can't be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileErr {
+    /// A call of a host function, on the statement's line if it's known.
+    Call { function: String, line: Option<u32> },
+}
+
+impl CompileErr {
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            CompileErr::Call { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for CompileErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileErr::Call { function, .. } => write!(
+                f,
+                "`{}` can't be called, compiled programs have no host functions",
+                function
+            ),
+        }
+    }
+}
+
+pub fn compile(ast: &Node) -> Result<Program, CompileErr> {
+    compile_with_lines(ast, &[])
+}
+
+/// Like `compile`, recording the source line of every statement, as given
+/// by `Parser::statement_lines` for the same tree.
+pub fn compile_with_lines(ast: &Node, lines: &[u32]) -> Result<Program, CompileErr> {
+    let mut compiler = Compiler {
+        statement_lines: lines,
+        types: Types::infer(ast, HashMap::new()),
+        ..Default::default()
+    };
+    compiler.stmt(ast);
+
+    match compiler.err {
+        Some(err) => Err(err),
+        None => Ok(compiler.program),
+    }
+}
+
+#[derive(Default)]
+struct Compiler<'a> {
+    program: Program,
+    // scope index and variables of the open blocks, innermost last
+    scopes: Vec<(u32, HashMap<String, u32>)>,
+    statement_lines: &'a [u32],
+    statements: usize,
+    types: Types,
+    // line of the statement being compiled
+    line: Option<u32>,
+    // the first call found
+    err: Option<CompileErr>,
+}
+
+impl Compiler<'_> {
+    fn emit(&mut self, op: Op) {
+        self.program.code.push(op);
+    }
+
+    // statements are compiled in the same pre-order the parser numbers them
+    fn mark_line(&mut self) {
+        self.line = self.statement_lines.get(self.statements).copied();
+        if let Some(line) = self.line {
+            let offset = self.program.code.len() as u32;
+
+            // a statement without code, e.g. an empty one, shares its
+            // offset with the next one
+            match self.program.lines.last_mut() {
+                Some(last) if last.0 == offset => last.1 = line,
+                _ => self.program.lines.push((offset, line)),
+            }
+        }
+        self.statements += 1;
+    }
+
+    // compiles a node whose value isn't used
+    fn stmt(&mut self, node: &Node) {
+        self.mark_line();
+
+        match node {
+            Node::Compound(compound) => self.compound(compound),
+            Node::BinOp(bin_op) if bin_op.op == OpKind::AssignEq => {
+                self.assign(&bin_op.lhs, &bin_op.rhs, false);
+            },
+            Node::None => (),
+            Node::Call(call) if Standard::get(&call.name).is_some() => self.standard(call),
+            node => {
+                self.expr(node);
+                self.emit(Op::Pop);
+            }
+        }
+    }
+
+    // compiles a node that leaves its value on the stack, returns its
+    // static type
+    fn expr(&mut self, node: &Node) -> Option<ValueType> {
+        let children = match node {
+            Node::BinOp(bin_op) if bin_op.op == OpKind::AssignEq => {
+                vec![None, self.assign(&bin_op.lhs, &bin_op.rhs, true)]
+            },
+            Node::BinOp(bin_op) => {
+                let lhs = self.expr(&bin_op.lhs);
+                let rhs = self.expr(&bin_op.rhs);
+                let int = self.types.node(node, &[lhs, rhs]) == Some(ValueType::Integer);
+                self.emit(match bin_op.op {
+                    OpKind::Plus if int => Op::IntAdd,
+                    OpKind::Minus if int => Op::IntSub,
+                    OpKind::Star if int => Op::IntMul,
+                    OpKind::Plus => Op::Add,
+                    OpKind::Minus => Op::Sub,
+                    OpKind::Star => Op::Mul,
+                    OpKind::Slash => Op::Slash,
+                    OpKind::Caret => Op::Pow,
+                    OpKind::Div => Op::IntDiv,
+                    OpKind::Mod | OpKind::Percent => Op::Mod,
+                    OpKind::AssignEq => unreachable!(),
+                });
+                vec![lhs, rhs]
+            },
+            Node::UnaryOp(unary_op) => match unary_op.op {
+                OpKind::Minus => {
+                    let ty = self.expr(&unary_op.node);
+                    self.emit(Op::Neg);
+                    vec![ty]
+                },
+                OpKind::Plus => vec![self.expr(&unary_op.node)],
+                _ => {
+                    self.emit(Op::Trap(InterpreterErr::InvalidUnaryOp));
+                    vec![None]
+                },
+            },
+            Node::Literal(lit) => {
+                match arith::literal(lit) {
+                    Ok(value) => self.emit(Op::Const(value)),
+                    Err(err) => self.emit(Op::Trap(err)),
+                }
+                Vec::new()
+            },
+            Node::Ident(ident) => {
+                match self.resolve(ident) {
+                    Some(slot) => self.emit(Op::Load(slot)),
+                    None => self.emit(Op::Trap(InterpreterErr::UndefinedIdent)),
+                }
+                Vec::new()
+            },
+            Node::Compound(compound) => {
+                self.compound(compound);
+                self.emit(Op::Const(0.0));
+                Vec::new()
+            },
+            Node::None => {
+                self.emit(Op::Const(0.0));
+                Vec::new()
+            },
+            // standard procedures have no value
+            Node::Call(call) if Standard::get(&call.name).is_some() => {
+                self.emit(Op::Trap(InterpreterErr::TypeMismatch));
+                Vec::new()
+            },
+            Node::Call(call) => {
+                if self.err.is_none() {
+                    self.err = Some(CompileErr::Call {
+                        function: call.name.clone(),
+                        line: self.line,
+                    });
+                }
+                self.emit(Op::Trap(InterpreterErr::UndefinedFunction));
+                Vec::new()
+            },
+            Node::Keyword(_) | Node::Delim(_) => {
+                self.emit(Op::Trap(InterpreterErr::UndefinedErr));
+                Vec::new()
+            },
+        };
+
+        self.types.node(node, &children)
+    }
+
+    fn compound(&mut self, compound: &CompoundNode) {
+        let scope = self.program.scopes.len() as u32;
+        self.program.scopes.push(Vec::new());
+        self.scopes.push((scope, HashMap::new()));
+
+        for child in &compound.children {
+            self.stmt(child);
+        }
+
+        self.scopes.pop();
+        self.emit(Op::ExitScope(scope));
+    }
+
+    // `keep_value` leaves the assigned value on the stack, returns its
+    // static type
+    fn assign(&mut self, var: &Node, expr: &Node, keep_value: bool) -> Option<ValueType> {
+        let ident = match var {
+            Node::Ident(ident) => ident,
+            _ => {
+                self.emit(Op::Trap(InterpreterErr::InvalidAssignment));
+                return None;
+            },
+        };
+
+        let ty = self.expr(expr);
+        if keep_value {
+            self.emit(Op::Dup);
+        }
+        self.store(ident);
+        ty
+    }
+
+    // a call of a standard procedure as a statement
+    fn standard(&mut self, call: &Call) {
+        if Standard::get(&call.name) == Some(Standard::ReadLn) {
+            // like the interpreter, check every variable before reading
+            if !call.args.iter().all(|arg| matches!(arg, Node::Ident(_))) {
+                self.emit(Op::Trap(InterpreterErr::InvalidAssignment));
+                return;
+            }
+            self.emit(Op::ReadLine);
+            for arg in &call.args {
+                if let Node::Ident(ident) = arg {
+                    self.emit(Op::Read);
+                    self.store(ident);
+                }
+            }
+            return;
+        }
+
+        for arg in &call.args {
+            match string_arg(arg) {
+                Some(text) => {
+                    self.emit(Op::WriteStr(self.program.strings.len() as u32));
+                    self.program.strings.push(text.to_string());
+                },
+                None => {
+                    self.expr(arg);
+                    self.emit(Op::Write);
+                },
+            }
+        }
+        if Standard::get(&call.name) == Some(Standard::WriteLn) {
+            self.emit(Op::WriteLn);
+        }
+    }
+
+    // pops the value into `ident`
+    fn store(&mut self, ident: &str) {
+        if let Some(slot) = self.resolve(ident) {
+            self.emit(Op::Store(slot));
+            return;
+        }
+
+        // the first assignment defines the variable in the current block
+        let slot = self.program.slots;
+        match self.scopes.last_mut() {
+            Some((scope, vars)) => {
+                vars.insert(ident.to_string(), slot);
+                self.program.scopes[*scope as usize].push((ident.to_string(), slot));
+                self.program.slots += 1;
+                self.emit(Op::Store(slot));
+            },
+            None => self.emit(Op::Trap(InterpreterErr::UndefinedErr)),
+        }
+    }
+
+    fn resolve(&self, ident: &str) -> Option<u32> {
+        self.scopes.iter().rev().find_map(|(_, vars)| vars.get(ident).copied())
+    }
+}
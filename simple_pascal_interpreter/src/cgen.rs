@@ -0,0 +1,393 @@
+//! C back end: turns programs into a single portable C99 file.
+//!
+//! The tree goes through [`bytecode::compile`] first, so variables are the
+//! same resolved slots the VM uses, and the stack code is rebuilt into C
+//! expressions. Every variable is a `double`, like in the interpreter.
+//! When a block ends the program prints its variables as one line of
+//! `name=value` pairs (`%.17g`, so values read back exactly), in the order
+//! `Interpreter::interpret` returns the blocks. Run-time errors print
+//! `runtime error: <InterpreterErr>` to stderr and exit with status 1.
+//!
+//! The embedded runtime implements `DIV`, `MOD` and `+`, `-` and `*` of
+//! INTEGERs with the dialect's integer range checks, REAL arithmetic is
+//! plain C. It also has the standard procedures of [`crate::standard`]:
+//! `Write` and `WriteLn` print to stdout, numbers with the same digits
+//! as [`format_number`] and strings as C literals, and `ReadLn` reads
+//! lines of up to 4095 bytes from stdin, dropping the rest of longer ones.
+//! Build with `cc -std=c99 prog.c -lm`.
+//!
+//! Strings are only ever printed, so a string literal anywhere else traps
+//! with `InvalidLiteral` like it does in the interpreter, and there are no
+//! declarations to translate until the language has them.
+//!
+//! [`format_number`]: crate::standard::format_number
+//! [`bytecode::compile`]: crate::bytecode::compile
+
+use std::fmt::Write;
+
+use simple_pascal_ast::{dialect::Dialect, node::Node};
+
+use crate::bytecode::{compile, CompileErr, Op, Program};
+
+const RUNTIME: &str = r#"#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* programs don't call every helper */
+#if defined(__GNUC__)
+#define SPI_UNUSED __attribute__((unused))
+#else
+#define SPI_UNUSED
+#endif
+
+SPI_UNUSED static void spi_trap(const char *err) {
+    fflush(stdout);
+    fprintf(stderr, "runtime error: %s\n", err);
+    exit(1);
+}
+
+/* `+`, `-` and `*` of two INTEGERs must stay in the dialect's range */
+SPI_UNUSED static double spi_int_result(double res) {
+    if (fabs(res) > SPI_MAX_INT) {
+        spi_trap("IntegerOverflow");
+    }
+    return res;
+}
+
+SPI_UNUSED static double spi_add(double lhs, double rhs) {
+    return spi_int_result(lhs + rhs);
+}
+
+SPI_UNUSED static double spi_sub(double lhs, double rhs) {
+    return spi_int_result(lhs - rhs);
+}
+
+SPI_UNUSED static double spi_mul(double lhs, double rhs) {
+    return spi_int_result(lhs * rhs);
+}
+
+/* `DIV` and `MOD` are only defined for integers of the dialect's range */
+SPI_UNUSED static void spi_int_operands(double lhs, double rhs) {
+    const double values[2] = { lhs, rhs };
+    int i;
+
+    for (i = 0; i < 2; i++) {
+        if (values[i] - trunc(values[i]) != 0.0) {
+            spi_trap("InvalidBinOp");
+        }
+        if (fabs(values[i]) > SPI_MAX_INT) {
+            spi_trap("IntegerOverflow");
+        }
+    }
+    if (rhs == 0.0) {
+        spi_trap("DivisionByZero");
+    }
+}
+
+SPI_UNUSED static double spi_div(double lhs, double rhs) {
+    spi_int_operands(lhs, rhs);
+    return trunc(lhs / rhs);
+}
+
+SPI_UNUSED static double spi_mod(double lhs, double rhs) {
+    double res;
+
+    spi_int_operands(lhs, rhs);
+#if SPI_ISO_MOD
+    /* ISO 7185 requires a positive divisor and a non-negative result */
+    if (rhs < 0.0) {
+        spi_trap("InvalidBinOp");
+    }
+    res = fmod(lhs, rhs);
+    return res < 0.0 ? res + fabs(rhs) : res;
+#else
+    res = fmod(lhs, rhs);
+    return res;
+#endif
+}
+
+/* the fewest significant digits that read back exactly, never with an
+   exponent, like `standard::format_number` */
+SPI_UNUSED static void spi_write(double value) {
+    char digits[32];
+    int precision, exponent, count, i;
+
+    if (isnan(value)) {
+        fputs("NaN", stdout);
+        return;
+    }
+    if (signbit(value)) {
+        putchar('-');
+        value = -value;
+    }
+    if (isinf(value)) {
+        fputs("inf", stdout);
+        return;
+    }
+    if (value == 0.0) {
+        putchar('0');
+        return;
+    }
+
+    for (precision = 0;; precision++) {
+        snprintf(digits, sizeof digits, "%.*e", precision, value);
+        if (precision == 16 || strtod(digits, NULL) == value) {
+            break;
+        }
+    }
+    /* `d.ddde+xx` to the digits alone and the exponent */
+    exponent = atoi(strchr(digits, 'e') + 1);
+    if (precision > 0) {
+        memmove(digits + 1, digits + 2, precision);
+    }
+    count = precision + 1;
+    digits[count] = '\0';
+
+    if (exponent < 0) {
+        fputs("0.", stdout);
+        for (i = -1; i > exponent; i--) {
+            putchar('0');
+        }
+        fputs(digits, stdout);
+    } else if (exponent + 1 >= count) {
+        fputs(digits, stdout);
+        for (i = count; i <= exponent; i++) {
+            putchar('0');
+        }
+    } else {
+        fwrite(digits, 1, exponent + 1, stdout);
+        putchar('.');
+        fputs(digits + exponent + 1, stdout);
+    }
+}
+
+SPI_UNUSED static void spi_write_str(const char *text) {
+    fputs(text, stdout);
+}
+
+SPI_UNUSED static void spi_write_ln(void) {
+    putchar('\n');
+}
+
+/* the line `ReadLn` reads, and what's left of it */
+SPI_UNUSED static char spi_line[4096];
+SPI_UNUSED static char *spi_rest = spi_line;
+
+SPI_UNUSED static void spi_read_line(void) {
+    size_t len;
+    int c;
+
+    /* a prompt shows before the program waits */
+    fflush(stdout);
+    if (fgets(spi_line, sizeof spi_line, stdin) == NULL) {
+        spi_trap("InvalidInput");
+    }
+    len = strlen(spi_line);
+    if (len > 0 && spi_line[len - 1] != '\n') {
+        while ((c = getchar()) != '\n' && c != EOF) {
+        }
+    }
+    spi_rest = spi_line;
+}
+
+/* decimal numbers only, like `standard::parse_number` */
+SPI_UNUSED static double spi_read(void) {
+    const char *space = " \t\n\v\f\r";
+    char *token, *end;
+    size_t len;
+    double value;
+
+    token = spi_rest + strspn(spi_rest, space);
+    len = strcspn(token, space);
+    if (len == 0 || strspn(token, "0123456789+-.eE") < len) {
+        spi_trap("InvalidInput");
+    }
+    value = strtod(token, &end);
+    if (end != token + len) {
+        spi_trap("InvalidInput");
+    }
+    spi_rest = token + len;
+    return value;
+}
+"#;
+
+/// Compiles `ast` and translates it to C.
+pub fn transpile(ast: &Node, dialect: Dialect) -> Result<String, CompileErr> {
+    Ok(to_c(&compile(ast)?, dialect))
+}
+
+pub fn to_c(program: &Program, dialect: Dialect) -> String {
+    let mut gen = Gen::new(program);
+
+    for op in program.code() {
+        gen.op(program, op);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "/* Pascal program compiled to C99, {} rules */", dialect).unwrap();
+    writeln!(out, "#define SPI_MAX_INT {}.0", dialect.max_int()).unwrap();
+    writeln!(out, "#define SPI_ISO_MOD {}", (dialect == Dialect::Iso7185) as u8).unwrap();
+    out.push_str(RUNTIME);
+    out.push_str("\nint main(void) {\n");
+    for var in &gen.vars {
+        writeln!(out, "    double {} = 0.0;", var).unwrap();
+    }
+    out.push_str(&gen.body);
+    out.push_str("    return 0;\n}\n");
+    out
+}
+
+struct Gen {
+    // C name of every slot
+    vars: Vec<String>,
+    body: String,
+    // C expressions of the values on the VM stack
+    stack: Vec<String>,
+    temps: usize,
+}
+
+impl Gen {
+    fn new(program: &Program) -> Self {
+        let mut vars: Vec<String> = (0..program.slots()).map(|slot| format!("v{}", slot)).collect();
+        for scope in &program.scopes {
+            for (name, slot) in scope {
+                vars[*slot as usize] = format!("v{}_{}", slot, name);
+            }
+        }
+
+        Self {
+            vars,
+            body: String::new(),
+            stack: Vec::new(),
+            temps: 0,
+        }
+    }
+
+    fn op(&mut self, program: &Program, op: &Op) {
+        match *op {
+            Op::Const(value) => self.stack.push(c_double(value)),
+            Op::Load(slot) => self.stack.push(self.vars[slot as usize].clone()),
+            Op::Store(slot) => {
+                let value = self.pop();
+                // values below may read the variable before it changes
+                self.materialize_stack();
+                writeln!(self.body, "    {} = {};", self.vars[slot as usize], value).unwrap();
+            },
+            Op::Dup => {
+                let value = self.pop();
+                let temp = self.temp(&value);
+                self.stack.extend([temp.clone(), temp]);
+            },
+            Op::Pop => {
+                let value = self.pop();
+                writeln!(self.body, "    (void)({});", value).unwrap();
+            },
+            Op::Neg => {
+                let value = self.pop();
+                self.stack.push(format!("(-{})", value));
+            },
+            Op::Add => self.bin_op(|lhs, rhs| format!("({} + {})", lhs, rhs)),
+            Op::Sub => self.bin_op(|lhs, rhs| format!("({} - {})", lhs, rhs)),
+            Op::Mul => self.bin_op(|lhs, rhs| format!("({} * {})", lhs, rhs)),
+            Op::Slash => self.bin_op(|lhs, rhs| format!("({} / {})", lhs, rhs)),
+            Op::Pow => self.bin_op(|lhs, rhs| format!("pow({}, {})", lhs, rhs)),
+            // calls that may trap become statements, so they run in the
+            // VM's order whatever order C evaluates operands in
+            Op::IntAdd | Op::IntSub | Op::IntMul | Op::IntDiv | Op::Mod => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                let func = match op {
+                    Op::IntAdd => "spi_add",
+                    Op::IntSub => "spi_sub",
+                    Op::IntMul => "spi_mul",
+                    Op::IntDiv => "spi_div",
+                    _ => "spi_mod",
+                };
+                let temp = self.temp(&format!("{}({}, {})", func, lhs, rhs));
+                self.stack.push(temp);
+            },
+            Op::ExitScope(scope) => {
+                let vars = program.scope(scope);
+                let format: Vec<String> = vars.iter().map(|(name, _)| format!("{}=%.17g", name)).collect();
+
+                write!(self.body, "    printf(\"{}\\n\"", format.join(" ")).unwrap();
+                for (_, slot) in vars {
+                    write!(self.body, ", {}", self.vars[*slot as usize]).unwrap();
+                }
+                self.body.push_str(");\n");
+            },
+            Op::Write => {
+                let value = self.pop();
+                writeln!(self.body, "    spi_write({});", value).unwrap();
+            },
+            Op::WriteStr(index) => {
+                writeln!(self.body, "    spi_write_str({});", c_string(program.string(index))).unwrap();
+            },
+            Op::WriteLn => self.body.push_str("    spi_write_ln();\n"),
+            Op::ReadLine => self.body.push_str("    spi_read_line();\n"),
+            Op::Read => {
+                let temp = self.temp("spi_read()");
+                self.stack.push(temp);
+            },
+            Op::Trap(err) => {
+                writeln!(self.body, "    spi_trap(\"{:?}\");", err).unwrap();
+                self.stack.push(String::from("0.0"));
+            },
+        }
+    }
+
+    fn pop(&mut self) -> String {
+        // malformed code is still turned into C that compiles
+        self.stack.pop().unwrap_or_else(|| String::from("0.0"))
+    }
+
+    fn bin_op<F: Fn(String, String) -> String>(&mut self, expr: F) {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        self.stack.push(expr(lhs, rhs));
+    }
+
+    fn temp(&mut self, value: &str) -> String {
+        let temp = format!("t{}", self.temps);
+        self.temps += 1;
+        writeln!(self.body, "    const double {} = {};", temp, value).unwrap();
+        temp
+    }
+
+    fn materialize_stack(&mut self) {
+        for idx in 0..self.stack.len() {
+            let value = self.stack[idx].clone();
+            self.stack[idx] = self.temp(&value);
+        }
+    }
+}
+
+// octal escapes for everything but printable ASCII, `?` too so no
+// trigraph forms
+fn c_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' | b'\\' | b'?' => write!(out, "\\{}", byte as char).unwrap(),
+            b' '..=b'~' => out.push(byte as char),
+            byte => write!(out, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn c_double(value: f64) -> String {
+    if value.is_nan() {
+        String::from("NAN")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "INFINITY" } else { "(-INFINITY)" })
+    } else if value.is_sign_negative() {
+        // `- -5.0` must not turn into `--5.0`
+        format!("({:?})", value)
+    } else {
+        // `Debug` keeps a `.0` or an exponent, so C reads a double back
+        format!("{:?}", value)
+    }
+}
@@ -0,0 +1,96 @@
+//! Debugging runs of an `Interpreter`.
+//!
+//! `Interpreter::debug` starts a run that stops before its first statement.
+//! From there `Interpreter::step` continues it until the next [`Stop`]:
+//! a line with a [`Breakpoint`] whose condition holds, the next statement
+//! the [`Step`] asks for, or the end of the program. While a run is stopped
+//! `Interpreter::scopes` shows the variables of its open blocks and
+//! `Interpreter::watch` evaluates expressions among them.
+//!
+//! Lines come from `Parser::statement_lines`, so a run stops before
+//! statements, never inside expressions. Empty statements are skipped.
+
+use simple_pascal_ast::node::Node;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub line: u32,
+    /// Stops only when this expression isn't 0, or when it fails, e.g. on
+    /// a variable that isn't defined yet.
+    pub condition: Option<Node>,
+}
+
+/// How far `Interpreter::step` runs, besides stopping at breakpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Step {
+    /// Only to the next breakpoint.
+    #[default]
+    Continue,
+    /// To the next statement, in nested blocks too.
+    Into,
+    /// To the next statement that isn't nested deeper than this one.
+    Over,
+    /// To the next statement after the current block.
+    Out,
+}
+
+impl Step {
+    // whether a step from a statement in a block nested `from` deep stops
+    // at one nested `to` deep
+    fn stops(self, from: usize, to: usize) -> bool {
+        match self {
+            Step::Continue => false,
+            Step::Into => true,
+            Step::Over => to <= from,
+            Step::Out => to < from,
+        }
+    }
+}
+
+/// Where a debugged run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// Before the statement on this line, at a breakpoint.
+    Breakpoint(u32),
+    /// Before the statement on this line, at the end of a step.
+    Step(u32),
+    /// The program ran to its end.
+    Finished,
+}
+
+impl Stop {
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            Stop::Breakpoint(line) | Stop::Step(line) => Some(*line),
+            Stop::Finished => None,
+        }
+    }
+}
+
+// the state of a debugged run between stops
+#[derive(Debug, Default)]
+pub(crate) struct Stepping {
+    // `Parser::statement_lines` of the program
+    pub lines: Vec<u32>,
+    pub step: Step,
+    // how deep the statement the run stopped at is nested
+    pub level: usize,
+    pub stop: Option<Stop>,
+    // don't stop at the statement the run stopped at once more, until it
+    // has started
+    pub skip: bool,
+}
+
+impl Stepping {
+    pub fn new(lines: &[u32]) -> Self {
+        Self {
+            lines: lines.to_vec(),
+            step: Step::Into,
+            ..Default::default()
+        }
+    }
+
+    pub fn stops(&self, level: usize) -> bool {
+        self.step.stops(self.level, level)
+    }
+}
@@ -0,0 +1,104 @@
+//! Readable listings of compiled [`Program`]s.
+//!
+//! ```text
+//! ; 2 slots, 1 scope
+//!       ; 2 | y := 2;
+//! 0000  CONST      2
+//! 0001  STORE      0  ; y
+//! ```
+//!
+//! Code of a new source line starts with the line number, and the line
+//! itself when `source` is given. Slot and scope operands are followed by
+//! variable names, string operands by the string.
+
+use std::fmt::Write;
+
+use crate::bytecode::{Op, Program};
+
+pub fn disassemble(program: &Program, source: Option<&str>) -> String {
+    let source_lines: Vec<&str> = source.map(|source| source.lines().collect()).unwrap_or_default();
+
+    let mut names = vec![""; program.slots() as usize];
+    for scope in &program.scopes {
+        for (name, slot) in scope {
+            names[*slot as usize] = name;
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "; {} slot{}, {} scope{}",
+        program.slots(),
+        if program.slots() == 1 { "" } else { "s" },
+        program.scopes.len(),
+        if program.scopes.len() == 1 { "" } else { "s" },
+    )
+    .unwrap();
+
+    let mut lines = program.lines.iter().peekable();
+    let mut last_line = 0;
+    for (offset, op) in program.code().iter().enumerate() {
+        while let Some(&(_, line)) = lines.next_if(|(start, _)| *start as usize <= offset) {
+            // several statements on one line share the comment
+            if line == last_line {
+                continue;
+            }
+            last_line = line;
+
+            match (line as usize).checked_sub(1).and_then(|idx| source_lines.get(idx)) {
+                Some(text) => writeln!(out, "      ; {} | {}", line, text.trim()),
+                None => writeln!(out, "      ; line {}", line),
+            }
+            .unwrap();
+        }
+
+        let (name, operand, comment) = match *op {
+            Op::Const(value) => ("CONST", value.to_string(), String::new()),
+            Op::Load(slot) => ("LOAD", slot.to_string(), names[slot as usize].to_string()),
+            Op::Store(slot) => ("STORE", slot.to_string(), names[slot as usize].to_string()),
+            Op::Dup => ("DUP", String::new(), String::new()),
+            Op::Pop => ("POP", String::new(), String::new()),
+            Op::Neg => ("NEG", String::new(), String::new()),
+            Op::Add => ("ADD", String::new(), String::new()),
+            Op::Sub => ("SUB", String::new(), String::new()),
+            Op::Mul => ("MUL", String::new(), String::new()),
+            Op::IntAdd => ("IADD", String::new(), String::new()),
+            Op::IntSub => ("ISUB", String::new(), String::new()),
+            Op::IntMul => ("IMUL", String::new(), String::new()),
+            Op::Slash => ("SLASH", String::new(), String::new()),
+            Op::Pow => ("POW", String::new(), String::new()),
+            Op::IntDiv => ("DIV", String::new(), String::new()),
+            Op::Mod => ("MOD", String::new(), String::new()),
+            Op::ExitScope(scope) => (
+                "EXIT_SCOPE",
+                scope.to_string(),
+                program
+                    .scope(scope)
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Op::Write => ("WRITE", String::new(), String::new()),
+            Op::WriteStr(index) => (
+                "WRITE_STR",
+                index.to_string(),
+                format!("'{}'", program.string(index).replace('\'', "''")),
+            ),
+            Op::WriteLn => ("WRITE_LN", String::new(), String::new()),
+            Op::ReadLine => ("READ_LINE", String::new(), String::new()),
+            Op::Read => ("READ", String::new(), String::new()),
+            Op::Trap(err) => ("TRAP", format!("{:?}", err), String::new()),
+        };
+
+        let line = format!("{:04}  {:<10} {}", offset, name, operand);
+        if comment.is_empty() {
+            writeln!(out, "{}", line.trim_end())
+        } else {
+            writeln!(out, "{}  ; {}", line, comment)
+        }
+        .unwrap();
+    }
+    out
+}
@@ -3,30 +3,132 @@ use std::{
         LinkedList, 
         HashMap
     }, 
+    fmt,
+    time::{Duration, Instant},
 };
 
 use simple_pascal_ast::{
+    dialect::Dialect,
     node::*, 
     token::{
         literal::Literal, 
-        keyword::Keyword, op::{OpKind, Fixity}
+        op::OpKind
     }
 };
 
-#[derive(Debug)]
+use crate::{
+    arith,
+    debug::{Breakpoint, Step, Stepping, Stop},
+    limits::{CancelToken, Cancellation, Fuel, Limits},
+    native::{CallErr, Checker, FromValue, IntoValue, Native, NativeErr, NativeFn, Signature, ValueType},
+    snapshot::{SnapshotErr, State},
+    standard::{string_arg, Io, Standard},
+    types::Types,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InterpreterErr {
     InvalidLiteral,
     InvalidUnaryOp,
     InvalidBinOp,
     UndefinedIdent,
     InvalidAssignment,
+    DivisionByZero,
+    IntegerOverflow,
     UndefinedErr,
+    UndefinedFunction,
+    ArityMismatch,
+    TypeMismatch,
+    HostError,
+    InvalidInput,
+    BudgetExhausted,
+    Cancelled,
+    DepthExceeded,
+    MemoryExceeded,
+    InvalidResume,
+    Paused,
+}
+
+impl fmt::Display for InterpreterErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InterpreterErr::InvalidLiteral => "invalid literal",
+            InterpreterErr::InvalidUnaryOp => "invalid unary operator",
+            InterpreterErr::InvalidBinOp => "invalid operands of a binary operator",
+            InterpreterErr::UndefinedIdent => "use of an undefined variable",
+            InterpreterErr::InvalidAssignment => "only variables can be assigned to",
+            InterpreterErr::DivisionByZero => "division by zero",
+            InterpreterErr::IntegerOverflow => "integer overflow",
+            InterpreterErr::UndefinedErr => "internal error",
+            InterpreterErr::UndefinedFunction => "call of an undefined function",
+            InterpreterErr::ArityMismatch => "wrong number of arguments",
+            InterpreterErr::TypeMismatch => "value of the wrong type",
+            InterpreterErr::HostError => "host function failed",
+            InterpreterErr::InvalidInput => "invalid or missing input",
+            InterpreterErr::BudgetExhausted => "step budget exhausted",
+            InterpreterErr::Cancelled => "cancelled",
+            InterpreterErr::DepthExceeded => "nested too deeply",
+            InterpreterErr::MemoryExceeded => "out of variable memory",
+            InterpreterErr::InvalidResume => "no suspended run of this program",
+            InterpreterErr::Paused => "paused by the debugger",
+        })
+    }
+}
+
+/// Outcome of `Interpreter::execute`.
+///
+/// Values are all `f64`s; programs have no declarations, so `types` has
+/// the static types inferred from their assignments instead, see
+/// `crate::types`.
+#[derive(Debug, Clone)]
+pub struct ExecutionResult {
+    /// Variables of the program block when the program stopped, not the
+    /// global frame of `Interpreter::globals`.
+    pub program_vars: HashMap<String, f64>,
+    /// Static type of each of `program_vars`, `None` for those without
+    /// one, e.g. a variable assigned both INTEGERs and REALs.
+    pub types: HashMap<String, Option<ValueType>>,
+    /// The error that stopped the program, if any.
+    pub status: Result<(), InterpreterErr>,
+    pub elapsed: Duration,
+    /// Variables of every finished block in exit order, only collected
+    /// with `Interpreter::set_trace`.
+    pub trace: Option<LinkedList<HashMap<String, f64>>>,
+    /// What `Write` and `WriteLn` printed, see `Interpreter::set_output`.
+    pub output: String,
+}
+
+impl ExecutionResult {
+    pub fn is_ok(&self) -> bool {
+        self.status.is_ok()
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct Interpreter {
     vars: LinkedList<HashMap<String, f64>>,
     hist_vars: LinkedList<HashMap<String, f64>>,
+    dialect: Dialect,
+    trace: bool,
+    natives: HashMap<String, Native>,
+    native_err: Option<NativeErr>,
+    // the program block's variables after the last run
+    program_vars: HashMap<String, f64>,
+    fuel: Fuel,
+    cancellation: Cancellation,
+    limits: Limits,
+    // bytes of variables of the current run
+    memory: usize,
+    // the run that ran out of fuel or time
+    suspended: Option<Machine>,
+    breakpoints: Vec<Breakpoint>,
+    // the debugged run, see `debug`
+    stepping: Option<Stepping>,
+    // `Parser::statement_lines` of the programs run next
+    statement_lines: Vec<u32>,
+    // line of the statement running
+    line: Option<u32>,
+    io: Io,
 }
 
 impl Interpreter {
@@ -34,82 +136,654 @@ impl Interpreter {
         Default::default()
     }
 
-    pub fn interpret(&mut self, ast: Node) -> Result<LinkedList<HashMap<String, f64>>, InterpreterErr> {
-        self.visit(ast)?;
+    pub fn with_dialect(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            ..Default::default()
+        }
+    }
 
-        let mut vars = LinkedList::<HashMap<String, f64>>::default();
-        std::mem::swap(&mut vars, &mut self.hist_vars);
-        Ok(vars)
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
     }
 
-    fn visit(&mut self, node: Node) -> Result<f64, InterpreterErr> {
-        match node {
-            Node::BinOp(bin_op) => self.visit_bin_op(bin_op),
-            Node::UnaryOp(unary_op) => self.visit_unary_op(unary_op),
-            Node::Literal(lit) => self.visit_literal(lit),
-            Node::Ident(ident) => self.visit_ident(&ident),
-            Node::Keyword(keyword) => self.visit_keyword(keyword),
-            Node::Compound(compound) => {
-                self.vars.push_front(HashMap::new());
-                let mut res = 0.0;
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
 
-                for node in compound.children {
-                    res = self.visit(node)?;
-                }
+    /// Makes `execute` collect the variables of every finished block.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.trace
+    }
+
+    /// Limits runs to `steps` visited statements and expressions in total,
+    /// or lifts the limit with `None` (the default). What a run doesn't use
+    /// is left for the next one.
+    pub fn set_fuel(&mut self, steps: Option<u64>) {
+        self.fuel.remaining = steps;
+    }
+
+    /// Steps left, `None` without a limit.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel.remaining
+    }
+
+    /// Adds `steps` to a limited budget.
+    pub fn add_fuel(&mut self, steps: u64) {
+        if let Some(remaining) = &mut self.fuel.remaining {
+            *remaining = remaining.saturating_add(steps);
+        }
+    }
+
+    /// Called when a run spends the last step; the program continues where
+    /// it stopped with the steps `refuel` returns, or fails with
+    /// `BudgetExhausted` if it returns 0.
+    pub fn set_refuel<F: FnMut() -> u64 + Send + 'static>(&mut self, refuel: F) {
+        self.fuel.refuel = Some(Box::new(refuel));
+    }
+
+    /// Token to stop the run in progress, e.g. from another thread. Runs
+    /// fail with `Cancelled` until the token is reset.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancellation.token.clone()
+    }
 
-                let vars = self.vars.pop_front();
-                self.log_vars(vars);
+    /// Cancels every run that takes longer than `timeout`.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.cancellation.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.cancellation.timeout
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Makes `func` callable from Pascal code as `name`, replacing any
+    /// function registered under that name before. See [`crate::native`]
+    /// for the Rust types that can be passed and returned. The standard
+    /// procedures of [`crate::standard`] can't be replaced.
+    pub fn register_fn<Args, F: NativeFn<Args>>(&mut self, name: &str, func: F) {
+        self.natives.insert(name.to_string(), Native::new(func));
+    }
+
+    /// Also sends what `Write` and `WriteLn` print to `output` while the
+    /// program runs, e.g. to show it right away. `ExecutionResult::output`
+    /// has it either way.
+    pub fn set_output<F: FnMut(&str) + Send + 'static>(&mut self, output: F) {
+        self.io.write = Some(Box::new(output));
+    }
+
+    /// Where `ReadLn` gets lines from, `None` is the end of the input.
+    /// Without one every `ReadLn` fails with `InvalidInput`.
+    pub fn set_input<F: FnMut() -> Option<String> + Send + 'static>(&mut self, input: F) {
+        self.io.read = Some(Box::new(input));
+    }
+
+    pub fn signature(&self, name: &str) -> Option<&Signature> {
+        self.natives.get(name).map(|native| &native.signature)
+    }
+
+    /// Source lines of the statements of the programs `check`, the run
+    /// methods and `eval` get next, their `Parser::statement_lines`, so
+    /// that a [`NativeErr`] tells which line the failed call is on.
+    /// `debug` takes the lines of its program instead.
+    pub fn set_statement_lines(&mut self, lines: &[u32]) {
+        self.statement_lines = lines.to_vec();
+    }
+
+    /// Checks every call in `ast` against the registered functions. The
+    /// run methods do it before running anything.
+    pub fn check(&mut self, ast: &Node) -> Result<(), InterpreterErr> {
+        self.check_calls(ast, true)
+    }
+
+    /// Details of the last error raised by a call, or by `check`: the
+    /// function, a message, e.g. the one the host function returned, and
+    /// the line of the call.
+    pub fn native_error(&self) -> Option<&NativeErr> {
+        self.native_err.as_ref()
+    }
+
+    /// Line of the statement running, and after a run the one it stopped
+    /// at, e.g. the statement that failed. `None` before the first
+    /// statement or without `set_statement_lines`.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Runs `ast` like `interpret` and reports the program's variables,
+    /// whether it failed and how long it took.
+    pub fn execute(&mut self, ast: &Node) -> ExecutionResult {
+        let start = Instant::now();
+        let status = self.run(ast, None);
+        self.report(ast, start, status)
+    }
+
+    /// Runs `ast` without consuming it, so a parsed program can be run
+    /// any number of times. Every run starts with only the global
+    /// variables, see `set_global`. Returns the variables of every
+    /// finished block in exit order.
+    pub fn interpret(&mut self, ast: &Node) -> Result<LinkedList<HashMap<String, f64>>, InterpreterErr> {
+        self.run(ast, None)?;
+        Ok(std::mem::take(&mut self.hist_vars))
+    }
+
+    /// Whether the last run stopped with `BudgetExhausted` or `Cancelled`
+    /// and can be continued with `resume` or saved with `snapshot`.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
 
-                Ok(res)
+    /// Continues the suspended run of `ast` where it stopped, with the
+    /// variables and the finished blocks it had. Fails with
+    /// `InvalidResume` if no run of this very program is suspended. The
+    /// run may be suspended again, e.g. when the fuel runs out once more.
+    pub fn resume(&mut self, ast: &Node) -> ExecutionResult {
+        let start = Instant::now();
+        self.stepping = None;
+        let status = self.resume_run(ast);
+        self.report(ast, start, status)
+    }
+
+    /// Stops debugged runs before the statements on `line`, or only those
+    /// where `condition` holds, see [`Breakpoint`]. Replaces the line's
+    /// breakpoint, if any.
+    pub fn set_breakpoint(&mut self, line: u32, condition: Option<Node>) {
+        self.clear_breakpoint(line);
+        self.breakpoints.push(Breakpoint { line, condition });
+    }
+
+    /// Returns whether `line` had a breakpoint.
+    pub fn clear_breakpoint(&mut self, line: u32) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.line != line);
+        self.breakpoints.len() != len
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Starts a run of `ast` like `execute` that stops before its first
+    /// statement, see [`crate::debug`]. `lines` are the
+    /// `Parser::statement_lines` of `ast`.
+    pub fn debug(&mut self, ast: &Node, lines: &[u32]) -> Result<Stop, InterpreterErr> {
+        let res = self.run(ast, Some(Stepping::new(lines)));
+        self.stopped(res)
+    }
+
+    /// Continues the stopped run of `ast` until the next breakpoint or the
+    /// statement `step` asks for. Fails with `InvalidResume` if no run of
+    /// `ast` is stopped.
+    pub fn step(&mut self, ast: &Node, step: Step) -> Result<Stop, InterpreterErr> {
+        match &mut self.stepping {
+            Some(stepping) if self.suspended.is_some() => {
+                stepping.step = step;
+                stepping.skip = stepping.stop.take().is_some();
             },
-            Node::None => Ok(0.0),
-            _ => Err(InterpreterErr::UndefinedErr)
+            _ => return Err(InterpreterErr::InvalidResume),
         }
+
+        let res = self.resume_run(ast);
+        self.stopped(res)
     }
 
-    fn visit_bin_op(&mut self, bin_op: BinOp) -> Result<f64, InterpreterErr> {
-        match bin_op.op.fixity() {
-            Fixity::Right => {
-                match bin_op.op {
-                    OpKind::AssignEq => self.assign_var(*bin_op.lhs, *bin_op.rhs),
-                    _ => Err(InterpreterErr::InvalidBinOp)
-                }
+    /// Where the debugged run stopped, `None` once it ended.
+    pub fn stop(&self) -> Option<Stop> {
+        self.stepping.as_ref().and_then(|stepping| stepping.stop)
+    }
+
+    /// Variables of every open scope, the innermost block first and the
+    /// global frame last.
+    pub fn scopes(&self) -> impl Iterator<Item = &HashMap<String, f64>> {
+        self.vars.iter()
+    }
+
+    /// Evaluates `expr` among the variables of the open scopes, e.g. of a
+    /// stopped run, without spending its fuel. Assignments change them.
+    pub fn watch(&mut self, expr: &Node) -> Result<f64, InterpreterErr> {
+        self.check_calls(expr, false)?;
+
+        let tree = Tree::new(expr, &[], &self.types(expr));
+        let mut machine = Machine::new(&tree);
+        let fuel = std::mem::take(&mut self.fuel);
+        let (scopes, blocks) = (self.vars.len(), self.hist_vars.len());
+
+        let line = self.line.take();
+        let res = self.exec(&tree, &mut machine).and_then(|_| machine.pop());
+        self.fuel = fuel;
+        self.line = line;
+        // the run must not see the blocks of `expr`
+        while self.vars.len() > scopes {
+            self.vars.pop_front();
+        }
+        while self.hist_vars.len() > blocks {
+            self.hist_vars.pop_back();
+        }
+        res
+    }
+
+    /// Saves the suspended run, if any, see [`crate::snapshot`] for the
+    /// format. Host functions aren't part of it.
+    pub fn snapshot(&self) -> Option<Vec<u8>> {
+        let machine = self.suspended.as_ref()?;
+
+        let state = State {
+            dialect: self.dialect,
+            memory: self.memory,
+            vars: self.vars.clone(),
+            hist_vars: self.hist_vars.clone(),
+            machine: machine.clone(),
+        };
+        Some(state.to_bytes())
+    }
+
+    /// Replaces the variables and the dialect with those of a `snapshot`,
+    /// possibly taken by another process, and suspends its run. `resume`
+    /// then continues it once the same host functions are registered.
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), SnapshotErr> {
+        let state = State::from_bytes(bytes)?;
+
+        self.dialect = state.dialect;
+        self.memory = state.memory;
+        self.vars = state.vars;
+        self.hist_vars = state.hist_vars;
+        self.program_vars = self.vars.iter().rev().nth(1).cloned().unwrap_or_default();
+        self.suspended = Some(state.machine);
+        self.stepping = None;
+        Ok(())
+    }
+
+    /// Runs a single statement or expression in the global frame, e.g. for
+    /// a REPL, and returns its value. Assignments outside of blocks define
+    /// global variables, which stay alive until `reset`. Discards a
+    /// suspended run.
+    pub fn eval(&mut self, node: &Node) -> Result<f64, InterpreterErr> {
+        self.suspended = None;
+        self.stepping = None;
+        self.enter_global_frame();
+        self.start_run();
+
+        let tree = self.tree(node);
+        let mut machine = Machine::new(&tree);
+        let res = self
+            .check(node)
+            .and_then(|_| self.exec(&tree, &mut machine))
+            .and_then(|_| machine.pop());
+        self.hist_vars.clear();
+        res
+    }
+
+    /// Sets a global variable. Programs see it like one of their own and
+    /// assignments to it change it in place.
+    pub fn set_global<T: IntoValue>(&mut self, name: &str, value: T) {
+        // the global frame is always the last one, even with a run suspended
+        if self.vars.is_empty() {
+            self.vars.push_front(HashMap::new());
+        }
+        if let Some(globals) = self.vars.back_mut() {
+            globals.insert(name.to_string(), value.into_value());
+        }
+    }
+
+    /// Reads a global variable or, if there is none, a variable of the
+    /// program block of the last `interpret` or `execute`, converted to `T`.
+    pub fn get_global<T: FromValue>(&self, name: &str) -> Result<T, InterpreterErr> {
+        let value = self
+            .vars
+            .back()
+            .and_then(|globals| globals.get(name))
+            .or_else(|| self.program_vars.get(name))
+            .ok_or(InterpreterErr::UndefinedIdent)?;
+
+        T::from_value(*value)
+    }
+
+    /// Variables of the global frame, set by `set_global` or `eval`.
+    pub fn globals(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.vars
+            .back()
+            .into_iter()
+            .flat_map(|vars| vars.iter().map(|(name, value)| (name.as_str(), *value)))
+    }
+
+    pub fn reset(&mut self) {
+        self.vars.clear();
+        self.hist_vars.clear();
+        self.program_vars.clear();
+        self.suspended = None;
+        self.stepping = None;
+    }
+
+    fn report(&mut self, ast: &Node, start: Instant, status: Result<(), InterpreterErr>) -> ExecutionResult {
+        // a suspended run still needs the blocks it finished
+        let trace = match self.suspended {
+            Some(_) => self.hist_vars.clone(),
+            None => std::mem::take(&mut self.hist_vars),
+        };
+
+        let types = self.types(ast);
+        ExecutionResult {
+            types: self.program_vars.keys().map(|name| (name.clone(), types.var(name))).collect(),
+            program_vars: self.program_vars.clone(),
+            status,
+            elapsed: start.elapsed(),
+            trace: self.trace.then_some(trace),
+            output: std::mem::take(&mut self.io.output),
+        }
+    }
+
+    fn stopped(&mut self, res: Result<(), InterpreterErr>) -> Result<Stop, InterpreterErr> {
+        let stop = match res {
+            Ok(()) => Stop::Finished,
+            Err(InterpreterErr::Paused) => match self.stop() {
+                Some(stop) => return Ok(stop),
+                None => return Err(InterpreterErr::UndefinedErr),
             },
-            Fixity::Left => {
-                let lhs = self.visit(*bin_op.lhs)?;
-                let rhs = self.visit(*bin_op.rhs)?;
-
-                match bin_op.op {
-                    OpKind::Caret => Ok(lhs.powf(rhs)),
-                    OpKind::Minus => Ok(lhs - rhs),
-                    OpKind::Plus => Ok(lhs + rhs),
-                    OpKind::Slash => Ok(lhs / rhs),
-                    OpKind::Star => Ok(lhs * rhs),
-                    _ => Err(InterpreterErr::InvalidBinOp)
-                }
+            Err(err) => return Err(err),
+        };
+        self.stepping = None;
+        Ok(stop)
+    }
+
+    // runs a whole program on top of the global frame and keeps its
+    // block's variables in `program_vars`
+    fn run(&mut self, ast: &Node, stepping: Option<Stepping>) -> Result<(), InterpreterErr> {
+        self.suspended = None;
+        self.stepping = stepping;
+        self.enter_global_frame();
+        self.hist_vars.clear();
+        self.start_run();
+
+        let tree = self.tree(ast);
+        let mut machine = Machine::new(&tree);
+        let res = self.check(ast).and_then(|_| self.exec(&tree, &mut machine));
+        self.finish(res, machine)
+    }
+
+    fn resume_run(&mut self, ast: &Node) -> Result<(), InterpreterErr> {
+        let tree = self.tree(ast);
+        let mut machine = match self.suspended.take() {
+            Some(machine) if machine.fingerprint == tree.fingerprint() => machine,
+            suspended => {
+                self.suspended = suspended;
+                return Err(InterpreterErr::InvalidResume);
             },
-            Fixity::None => Err(InterpreterErr::InvalidBinOp)
+        };
+        self.cancellation.start();
+
+        // nothing ran yet, so the run stays suspended
+        if let Err(err) = self.check(ast) {
+            self.suspended = Some(machine);
+            return Err(err);
         }
+        let res = self.exec(&tree, &mut machine);
+        self.finish(res, machine)
     }
 
-    fn visit_unary_op(&mut self, unary_op: UnaryOp) -> Result<f64, InterpreterErr> {
-        match unary_op.op {
-            OpKind::Minus => Ok(-self.visit(*unary_op.node)?),
-            OpKind::Plus => self.visit(*unary_op.node),
-            _ => Err(InterpreterErr::InvalidUnaryOp)
+    fn tree<'a>(&self, ast: &'a Node) -> Tree<'a> {
+        Tree::new(ast, self.lines(), &self.types(ast))
+    }
+
+    fn types(&self, ast: &Node) -> Types {
+        let calls = self
+            .natives
+            .iter()
+            .filter_map(|(name, native)| Some((name.clone(), native.signature.ret?)))
+            .collect();
+        Types::infer(ast, calls)
+    }
+
+    // statement lines of the program running, those of a debugged run
+    // come with it
+    fn lines(&self) -> &[u32] {
+        match &self.stepping {
+            Some(stepping) => &stepping.lines,
+            None => &self.statement_lines,
         }
     }
 
-    fn visit_literal(&self, lit: Literal) -> Result<f64, InterpreterErr> {
-        let res: Result<f64, _> = match lit {
-            Literal::Float(float) => float.parse(),
-            Literal::Integer(int) => int.parse()
-        };
+    // `numbered` when `ast` is the program the lines are for, not e.g. a
+    // watched expression
+    fn check_calls(&mut self, ast: &Node, numbered: bool) -> Result<(), InterpreterErr> {
+        let lines = if numbered { self.lines() } else { &[] };
+        let mut checker = Checker::new(&self.natives, lines);
+        checker.statement(ast);
+
+        self.native_err = checker.err;
+        match &self.native_err {
+            Some(err) => Err(err.err),
+            None => Ok(()),
+        }
+    }
+
+    // keeps a run that ran out of fuel or time, or that the debugger
+    // paused, for `resume`
+    fn finish(&mut self, res: Result<(), InterpreterErr>, machine: Machine) -> Result<(), InterpreterErr> {
+        self.program_vars = match res {
+            // the program block is the last one to finish
+            Ok(()) => self.hist_vars.back().cloned(),
+            // or the outermost one still open
+            Err(_) => self.vars.iter().rev().nth(1).cloned(),
+        }
+        .unwrap_or_default();
 
         match res {
-            Ok(float) => Ok(float),
-            Err(_) => Err(InterpreterErr::InvalidLiteral)
+            Err(InterpreterErr::BudgetExhausted | InterpreterErr::Cancelled | InterpreterErr::Paused) => {
+                self.suspended = Some(machine);
+            },
+            // `globals` must not see the scopes of a failed run
+            _ => self.enter_global_frame(),
         }
+        res
+    }
+
+    fn start_run(&mut self) {
+        self.io.start();
+        self.cancellation.start();
+        self.memory = 0;
+        self.line = None;
+    }
+
+    // drops the scopes a failed run may leave behind, keeping or creating
+    // the global frame
+    fn enter_global_frame(&mut self) {
+        while self.vars.len() > 1 {
+            self.vars.pop_front();
+        }
+        if self.vars.is_empty() {
+            self.vars.push_front(HashMap::new());
+        }
+    }
+
+    // runs tasks until none are left. Fuel and cancellation are checked
+    // before a task changes anything, so a run stopped by them continues
+    // with the same task
+    fn exec(&mut self, tree: &Tree, machine: &mut Machine) -> Result<(), InterpreterErr> {
+        while let Some(&task) = machine.tasks.last() {
+            match task {
+                Task::Eval(id) => {
+                    if let Some(line) = tree.line(id) {
+                        self.pause_at(line)?;
+                    }
+                    self.fuel.consume()?;
+                    // past the statement the debugged run stopped at
+                    if let Some(stepping) = &mut self.stepping {
+                        stepping.skip = false;
+                    }
+                    if let Some(line) = tree.line(id) {
+                        self.line = Some(line);
+                    }
+                    if self.limits.max_depth.is_some_and(|max_depth| tree.depth(id) > max_depth) {
+                        return Err(InterpreterErr::DepthExceeded);
+                    }
+                    machine.tasks.pop();
+                    self.eval_node(tree, id, machine)?;
+                },
+                Task::Block { block, next } => {
+                    let Node::Compound(compound) = tree.node(block)? else {
+                        return Err(InterpreterErr::UndefinedErr);
+                    };
+
+                    if (next as usize) < compound.children.len() {
+                        self.cancellation.check()?;
+                        // only the last statement's value is the block's
+                        if next > 0 {
+                            machine.pop()?;
+                        }
+                        machine.tasks.pop();
+                        machine.tasks.push(Task::Block { block, next: next + 1 });
+                        machine.tasks.push(Task::Eval(tree.child(block, next as usize)));
+                    } else {
+                        let res = if next > 0 { machine.pop()? } else { 0.0 };
+                        machine.tasks.pop();
+
+                        let vars = self.vars.pop_front();
+                        self.log_vars(vars);
+                        machine.values.push(res);
+                    }
+                },
+                Task::Apply(id) => {
+                    if let Node::Call(_) = tree.node(id)? {
+                        self.cancellation.check()?;
+                    }
+                    machine.tasks.pop();
+                    self.apply(tree, id, machine)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    // pauses a debugged run before the statement on `line` when the step
+    // ends there or a breakpoint says so
+    fn pause_at(&mut self, line: u32) -> Result<(), InterpreterErr> {
+        let level = self.vars.len().saturating_sub(1);
+        let stepped = match &self.stepping {
+            Some(stepping) if stepping.skip => return Ok(()),
+            Some(stepping) => stepping.stops(level),
+            None => return Ok(()),
+        };
+
+        let stop = match self.breakpoints.iter().find(|breakpoint| breakpoint.line == line) {
+            Some(Breakpoint { condition: None, .. }) => Stop::Breakpoint(line),
+            Some(Breakpoint { condition: Some(condition), .. }) => {
+                let condition = condition.clone();
+                // one that fails stops too
+                match self.watch(&condition) {
+                    Ok(0.0) if stepped => Stop::Step(line),
+                    Ok(0.0) => return Ok(()),
+                    _ => Stop::Breakpoint(line),
+                }
+            },
+            None if stepped => Stop::Step(line),
+            None => return Ok(()),
+        };
+
+        if let Some(stepping) = &mut self.stepping {
+            stepping.level = level;
+            stepping.stop = Some(stop);
+        }
+        Err(InterpreterErr::Paused)
+    }
+
+    // pushes the value of a leaf or the tasks that compute it
+    fn eval_node(&mut self, tree: &Tree, id: u32, machine: &mut Machine) -> Result<(), InterpreterErr> {
+        let value = match tree.node(id)? {
+            Node::Literal(lit) => self.visit_literal(lit)?,
+            Node::Ident(ident) => self.visit_ident(ident)?,
+            Node::None => 0.0,
+            Node::Compound(_) => {
+                self.vars.push_front(HashMap::new());
+                machine.tasks.push(Task::Block { block: id, next: 0 });
+                return Ok(());
+            },
+            Node::BinOp(bin_op) if bin_op.op == OpKind::AssignEq => {
+                if !matches!(*bin_op.lhs, Node::Ident(_)) {
+                    return Err(InterpreterErr::InvalidAssignment);
+                }
+                machine.tasks.push(Task::Apply(id));
+                machine.tasks.push(Task::Eval(tree.child(id, 1)));
+                return Ok(());
+            },
+            Node::BinOp(_) => {
+                machine.tasks.push(Task::Apply(id));
+                machine.tasks.push(Task::Eval(tree.child(id, 1)));
+                machine.tasks.push(Task::Eval(tree.child(id, 0)));
+                return Ok(());
+            },
+            Node::UnaryOp(unary_op) => {
+                match unary_op.op {
+                    OpKind::Minus => machine.tasks.push(Task::Apply(id)),
+                    OpKind::Plus => (),
+                    _ => return Err(InterpreterErr::InvalidUnaryOp),
+                }
+                machine.tasks.push(Task::Eval(tree.child(id, 0)));
+                return Ok(());
+            },
+            Node::Call(call) => {
+                machine.tasks.push(Task::Apply(id));
+                // `ReadLn` assigns to its arguments and `Write` prints
+                // strings as they are
+                let standard = Standard::get(&call.name);
+                for (idx, arg) in call.args.iter().enumerate().rev() {
+                    match standard {
+                        Some(Standard::ReadLn) => (),
+                        Some(_) if string_arg(arg).is_some() => (),
+                        _ => machine.tasks.push(Task::Eval(tree.child(id, idx))),
+                    }
+                }
+                return Ok(());
+            },
+            // keywords and delimiters only get here in trees that weren't
+            // parsed, e.g. built by hand or deserialized
+            Node::Keyword(_) | Node::Delim(_) => return Err(InterpreterErr::UndefinedErr),
+        };
+
+        machine.values.push(value);
+        Ok(())
+    }
+
+    // combines the values of a node's children
+    fn apply(&mut self, tree: &Tree, id: u32, machine: &mut Machine) -> Result<(), InterpreterErr> {
+        let value = match tree.node(id)? {
+            Node::BinOp(bin_op) if bin_op.op == OpKind::AssignEq => {
+                let value = machine.pop()?;
+                self.assign_var(&bin_op.lhs, value)?
+            },
+            Node::BinOp(bin_op) => {
+                let rhs = machine.pop()?;
+                let lhs = machine.pop()?;
+                arith::bin_op(self.dialect, &bin_op.op, tree.ty(id), lhs, rhs)?
+            },
+            Node::UnaryOp(unary_op) if unary_op.op == OpKind::Minus => -machine.pop()?,
+            Node::Call(call) => match Standard::get(&call.name) {
+                Some(standard) => self.visit_standard(standard, call, machine)?,
+                None => {
+                    let args = machine.pop_n(call.args.len())?;
+                    self.visit_call(call, &args)?
+                },
+            },
+            _ => return Err(InterpreterErr::UndefinedErr),
+        };
+
+        machine.values.push(value);
+        Ok(())
+    }
+
+    fn visit_literal(&self, lit: &Literal) -> Result<f64, InterpreterErr> {
+        arith::literal(lit)
     }
 
     fn visit_ident(&mut self, ident: &str) -> Result<f64, InterpreterErr> {
@@ -119,27 +793,78 @@ impl Interpreter {
         }
     }
 
-    fn visit_keyword(&mut self, _keyword: Keyword) -> Result<f64, InterpreterErr> {
-        todo!("NO KEYWORDS YET")
+    // procedures are 0 like other statements
+    fn visit_call(&mut self, call: &Call, args: &[f64]) -> Result<f64, InterpreterErr> {
+        let res = match self.natives.get(&call.name) {
+            Some(native) if native.signature.params.len() == args.len() => (native.func)(args),
+            Some(_) => return Err(InterpreterErr::ArityMismatch),
+            None => return Err(InterpreterErr::UndefinedFunction),
+        };
+
+        let (err, message) = match res {
+            Ok(value) => return Ok(value.unwrap_or(0.0)),
+            Err(CallErr::Arg(idx, err)) => (
+                err,
+                format!("argument {} of `{}` can't be {}", idx + 1, call.name, args[idx]),
+            ),
+            Err(CallErr::Host(message)) => (InterpreterErr::HostError, message),
+        };
+        self.native_err = Some(NativeErr {
+            function: call.name.clone(),
+            err,
+            message,
+            line: self.line,
+        });
+        Err(err)
+    }
+
+    // statements like any other, so 0
+    fn visit_standard(&mut self, standard: Standard, call: &Call, machine: &mut Machine) -> Result<f64, InterpreterErr> {
+        match standard {
+            Standard::ReadLn => {
+                self.io.read_line()?;
+                for arg in &call.args {
+                    let value = self.io.read()?;
+                    self.assign_var(arg, value)?;
+                }
+            },
+            Standard::Write | Standard::WriteLn => {
+                let numbers = call.args.iter().filter(|arg| string_arg(arg).is_none()).count();
+                let mut values = machine.pop_n(numbers)?.into_iter();
+                for arg in &call.args {
+                    match string_arg(arg) {
+                        Some(text) => self.io.write(text),
+                        None => self.io.write_number(values.next().ok_or(InterpreterErr::UndefinedErr)?),
+                    }
+                }
+                if standard == Standard::WriteLn {
+                    self.io.write("\n");
+                }
+            },
+        }
+        Ok(0.0)
     }
 
-    fn assign_var(&mut self, var: Node, expr: Node) -> Result<f64, InterpreterErr> {
+    fn assign_var(&mut self, var: &Node, expr_res: f64) -> Result<f64, InterpreterErr> {
         let ident = match var {
             Node::Ident(ident) => ident,
             _ => return Err(InterpreterErr::InvalidAssignment)
         };
 
-        let expr_res = self.visit(expr)?;
-
         match self.find_ident(&ident) {
             Some(var) => {
                 *var = expr_res;
                 Ok(expr_res)
             },
             None => {
+                self.memory += ident.len() + std::mem::size_of::<f64>();
+                if self.limits.max_memory.is_some_and(|max_memory| self.memory > max_memory) {
+                    return Err(InterpreterErr::MemoryExceeded);
+                }
+
                 match self.vars.front_mut() {
                     Some(map) => {
-                        map.insert(ident, expr_res);
+                        map.insert(ident.clone(), expr_res);
                         Ok(expr_res)
                     },
                     None => Err(InterpreterErr::UndefinedErr)
@@ -163,4 +888,179 @@ impl Interpreter {
             None => ()
         };
     }
-}
\ No newline at end of file
+}
+
+/// A step of a run. Runs keep their tasks on a stack instead of recursing,
+/// so that one can stop between any two steps and continue later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Task {
+    /// Computes a node's value, or pushes the tasks that do.
+    Eval(u32),
+    /// Combines the values of a node's children into its own.
+    Apply(u32),
+    /// Runs the statements of a block from `next` on.
+    Block { block: u32, next: u32 },
+}
+
+/// Where a run is: the tasks left and the values computed so far, for the
+/// program with this fingerprint.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Machine {
+    pub fingerprint: u64,
+    pub tasks: Vec<Task>,
+    pub values: Vec<f64>,
+}
+
+impl Machine {
+    fn new(tree: &Tree) -> Self {
+        Self {
+            fingerprint: tree.fingerprint(),
+            tasks: vec![Task::Eval(0)],
+            values: Vec::new(),
+        }
+    }
+
+    fn pop(&mut self) -> Result<f64, InterpreterErr> {
+        self.values.pop().ok_or(InterpreterErr::UndefinedErr)
+    }
+
+    fn pop_n(&mut self, n: usize) -> Result<Vec<f64>, InterpreterErr> {
+        match self.values.len().checked_sub(n) {
+            Some(at) => Ok(self.values.split_off(at)),
+            None => Err(InterpreterErr::UndefinedErr),
+        }
+    }
+}
+
+// The nodes of a tree numbered breadth-first, so a node's children have
+// consecutive ids. Tasks refer to nodes by these ids, which are the same
+// in every process that parses the same program.
+struct Tree<'a> {
+    nodes: Vec<&'a Node>,
+    first_child: Vec<u32>,
+    depth: Vec<usize>,
+    // source lines of statements, if the interpreter has them
+    lines: Vec<Option<u32>>,
+    // static types of the nodes
+    types: Vec<Option<ValueType>>,
+}
+
+impl<'a> Tree<'a> {
+    // `lines` are `Parser::statement_lines`, or empty
+    fn new(root: &'a Node, lines: &[u32], types: &Types) -> Self {
+        let mut tree = Tree {
+            nodes: vec![root],
+            first_child: Vec::new(),
+            depth: vec![0],
+            lines: Vec::new(),
+            types: Vec::new(),
+        };
+
+        // numbering children as they're found needs no recursion
+        let mut id = 0;
+        while id < tree.nodes.len() {
+            tree.first_child.push(tree.nodes.len() as u32);
+            // levels as the parser counts them: the left operand of a
+            // binary operator and the statements of a block, other than
+            // blocks, are as deep as their parent
+            let depth = tree.depth[id];
+            match tree.nodes[id] {
+                Node::BinOp(bin_op) => {
+                    tree.nodes.extend([&*bin_op.lhs, &*bin_op.rhs]);
+                    tree.depth.extend([depth, depth + 1]);
+                },
+                Node::UnaryOp(unary_op) => {
+                    tree.nodes.push(&unary_op.node);
+                    tree.depth.push(depth + 1);
+                },
+                Node::Compound(compound) => {
+                    for child in &compound.children {
+                        tree.nodes.push(child);
+                        tree.depth.push(depth + matches!(child, Node::Compound(_)) as usize);
+                    }
+                },
+                Node::Call(call) => {
+                    tree.nodes.extend(&call.args);
+                    tree.depth.resize(tree.nodes.len(), depth + 1);
+                },
+                _ => (),
+            }
+            id += 1;
+        }
+
+        // children have higher ids than their parents
+        tree.types = vec![None; tree.nodes.len()];
+        for id in (0..tree.nodes.len()).rev() {
+            let first = tree.first_child[id] as usize;
+            let last = tree.first_child.get(id + 1).map_or(tree.nodes.len(), |&next| next as usize);
+            tree.types[id] = types.node(tree.nodes[id], &tree.types[first..last]);
+        }
+
+        if !lines.is_empty() {
+            tree.number_statements(lines);
+        }
+        tree
+    }
+
+    // statements are numbered in the pre-order the parser found them in
+    fn number_statements(&mut self, lines: &[u32]) {
+        self.lines = vec![None; self.nodes.len()];
+
+        let mut lines = lines.iter();
+        let mut statements = vec![0];
+        while let Some(id) = statements.pop() {
+            let line = lines.next().copied();
+            match self.nodes[id as usize] {
+                Node::None => (),
+                Node::Compound(compound) => {
+                    self.lines[id as usize] = line;
+                    for idx in (0..compound.children.len()).rev() {
+                        statements.push(self.child(id, idx));
+                    }
+                },
+                _ => self.lines[id as usize] = line,
+            }
+        }
+    }
+
+    fn ty(&self, id: u32) -> Option<ValueType> {
+        self.types.get(id as usize).copied().flatten()
+    }
+
+    fn line(&self, id: u32) -> Option<u32> {
+        self.lines.get(id as usize).copied().flatten()
+    }
+
+    fn node(&self, id: u32) -> Result<&'a Node, InterpreterErr> {
+        self.nodes.get(id as usize).copied().ok_or(InterpreterErr::UndefinedErr)
+    }
+
+    // only called with the id of a node `node` found
+    fn child(&self, id: u32, idx: usize) -> u32 {
+        self.first_child[id as usize] + idx as u32
+    }
+
+    fn depth(&self, id: u32) -> usize {
+        self.depth.get(id as usize).copied().unwrap_or_default()
+    }
+
+    // FNV-1a of every node without its children, tells programs apart
+    // that a snapshot's tasks don't fit
+    fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for node in &self.nodes {
+            let key = match node {
+                Node::BinOp(bin_op) => format!("BinOp {:?}", bin_op.op),
+                Node::UnaryOp(unary_op) => format!("UnaryOp {:?}", unary_op.op),
+                Node::Compound(compound) => format!("Compound {}", compound.children.len()),
+                Node::Call(call) => format!("Call {} {}", call.name, call.args.len()),
+                node => format!("{:?}", node),
+            };
+            for byte in key.bytes().chain([0]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        hash
+    }
+}
@@ -1,3 +1,17 @@
+mod arith;
+pub mod bytecode;
+pub mod cgen;
+pub mod debug;
+pub mod disasm;
 pub mod interpreter;
+pub mod limits;
+pub mod native;
+pub mod pcode;
+pub mod snapshot;
+pub mod standard;
+mod types;
+pub mod vm;
+pub mod wasm;
 
-pub use interpreter::Interpreter;
\ No newline at end of file
+pub use interpreter::{ExecutionResult, Interpreter};
+pub use vm::Vm;
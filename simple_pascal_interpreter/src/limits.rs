@@ -0,0 +1,129 @@
+//! Resource limits for running untrusted programs.
+//!
+//! [`Fuel`] is the step budget of an `Interpreter`: every visited statement
+//! and expression costs one step, and a run that needs more than is left
+//! stops with `BudgetExhausted` until `Interpreter::resume` continues it.
+//! A [`CancelToken`] stops a run from another thread, and a timeout stops
+//! it once it passes, both with `Cancelled`; they're checked before every
+//! statement and call. [`Limits`] bound how deep a run nests
+//! and how much variable storage it creates.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use simple_pascal_ast::parser::DEFAULT_MAX_DEPTH;
+
+use crate::interpreter::InterpreterErr;
+
+/// Bounds on what a single run may use, `None` is no bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// How deep blocks, expressions and calls may nest while running,
+    /// past it the run fails with `DepthExceeded`. Levels are counted like
+    /// `Parser::set_max_depth` counts them, so chains like `1 + 2 + 3`
+    /// don't add any, and the default is the parser's: every program the
+    /// parser accepts runs, and the limit catches trees that were built by
+    /// hand, deserialized or parsed with a higher limit.
+    pub max_depth: Option<usize>,
+    /// Bytes of variables a run may create, names and values, past it the
+    /// run fails with `MemoryExceeded`.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_memory: None,
+        }
+    }
+}
+
+type Refuel = dyn FnMut() -> u64 + Send;
+
+#[derive(Default)]
+pub(crate) struct Fuel {
+    // `None` is no limit
+    pub remaining: Option<u64>,
+    pub refuel: Option<Box<Refuel>>,
+}
+
+impl Fuel {
+    // takes one step, asking `refuel` for more once the budget is spent
+    pub fn consume(&mut self) -> Result<(), InterpreterErr> {
+        let Some(remaining) = &mut self.remaining else {
+            return Ok(());
+        };
+
+        if *remaining == 0 {
+            *remaining = self.refuel.as_mut().map_or(0, |refuel| refuel());
+            if *remaining == 0 {
+                return Err(InterpreterErr::BudgetExhausted);
+            }
+        }
+        *remaining -= 1;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Fuel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fuel")
+            .field("remaining", &self.remaining)
+            .field("refuel", &self.refuel.is_some())
+            .finish()
+    }
+}
+
+/// Shared flag that makes runs fail with `Cancelled` at their next
+/// statement or call. Once cancelled it stays so, also for later runs and
+/// `Interpreter::resume`, until `reset`, so a `cancel` that comes before
+/// the run starts isn't lost.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Cancellation {
+    pub token: CancelToken,
+    pub timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    pub fn start(&mut self) {
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    pub fn check(&self) -> Result<(), InterpreterErr> {
+        // a timeout only stops its own run, it doesn't cancel the token
+        let timed_out = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        match timed_out || self.token.is_cancelled() {
+            true => Err(InterpreterErr::Cancelled),
+            false => Ok(()),
+        }
+    }
+}
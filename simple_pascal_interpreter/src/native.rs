@@ -0,0 +1,396 @@
+//! Host functions callable from Pascal code.
+//!
+//! `Interpreter::register_fn` accepts any closure of up to four arguments
+//! whose argument types implement [`FromValue`] and whose result implements
+//! [`IntoReturn`], and derives the function's [`Signature`] from them:
+//!
+//! - `f64` and `f32` are REALs
+//! - `i64`, `i32`, `u32` and `usize` are INTEGERs, an argument must be whole
+//!   and fit the Rust type
+//! - `()` makes the function a procedure, which is only callable as a
+//!   statement
+//! - `Result<T, E>` with `E: Display` stops the program with a `HostError`
+//!   when the closure returns `Err`
+//!
+//! Calls are checked against the signatures before a program runs, see
+//! `Interpreter::check`. `Interpreter::set_global` and `get_global` convert
+//! values the same way.
+
+use std::{collections::HashMap, fmt};
+
+use simple_pascal_ast::{
+    node::*,
+    token::op::OpKind,
+    visit::{self, Visitor},
+};
+
+use crate::{interpreter::InterpreterErr, standard::Standard, types};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Integer,
+    Real,
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValueType::Integer => "INTEGER",
+            ValueType::Real => "REAL",
+        })
+    }
+}
+
+/// Parameter and result types of a registered function, `ret` is `None`
+/// for procedures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub params: Vec<ValueType>,
+    pub ret: Option<ValueType>,
+}
+
+/// A failed call of a host function, or a call the checks rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeErr {
+    pub function: String,
+    pub err: InterpreterErr,
+    pub message: String,
+    /// Source line of the statement with the call, if the interpreter has
+    /// the program's lines, see `Interpreter::set_statement_lines`.
+    pub line: Option<u32>,
+}
+
+impl fmt::Display for NativeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.err, self.message)
+    }
+}
+
+/// Why a [`NativeFn`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallErr {
+    /// The argument at this index doesn't convert to the parameter type.
+    Arg(usize, InterpreterErr),
+    /// The function itself returned an error.
+    Host(String),
+}
+
+pub trait FromValue: Sized {
+    const TYPE: ValueType;
+
+    fn from_value(value: f64) -> Result<Self, InterpreterErr>;
+}
+
+pub trait IntoValue {
+    fn into_value(self) -> f64;
+}
+
+pub trait IntoReturn {
+    const RET: Option<ValueType>;
+
+    fn into_return(self) -> Result<Option<f64>, String>;
+}
+
+/// Closures `register_fn` accepts, `Args` tells the arities apart.
+pub trait NativeFn<Args>: Send + 'static {
+    fn signature() -> Signature;
+
+    fn call(&self, args: &[f64]) -> Result<Option<f64>, CallErr>;
+}
+
+impl FromValue for f64 {
+    const TYPE: ValueType = ValueType::Real;
+
+    fn from_value(value: f64) -> Result<Self, InterpreterErr> {
+        Ok(value)
+    }
+}
+
+impl FromValue for f32 {
+    const TYPE: ValueType = ValueType::Real;
+
+    fn from_value(value: f64) -> Result<Self, InterpreterErr> {
+        Ok(value as f32)
+    }
+}
+
+macro_rules! integer_value {
+    ($($ty:ty),*) => {$(
+        impl FromValue for $ty {
+            const TYPE: ValueType = ValueType::Integer;
+
+            fn from_value(value: f64) -> Result<Self, InterpreterErr> {
+                if value.fract() != 0.0 || !value.is_finite() {
+                    return Err(InterpreterErr::TypeMismatch);
+                }
+                // `as` saturates, so anything that doesn't round trip is out of range
+                let int = value as $ty;
+                if int as f64 != value {
+                    return Err(InterpreterErr::IntegerOverflow);
+                }
+                Ok(int)
+            }
+        }
+
+        impl IntoValue for $ty {
+            fn into_value(self) -> f64 {
+                self as f64
+            }
+        }
+
+        impl IntoReturn for $ty {
+            const RET: Option<ValueType> = Some(ValueType::Integer);
+
+            fn into_return(self) -> Result<Option<f64>, String> {
+                Ok(Some(self as f64))
+            }
+        }
+    )*};
+}
+
+integer_value!(i64, i32, u32, usize);
+
+impl IntoValue for f64 {
+    fn into_value(self) -> f64 {
+        self
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> f64 {
+        self as f64
+    }
+}
+
+impl IntoReturn for f64 {
+    const RET: Option<ValueType> = Some(ValueType::Real);
+
+    fn into_return(self) -> Result<Option<f64>, String> {
+        Ok(Some(self))
+    }
+}
+
+impl IntoReturn for f32 {
+    const RET: Option<ValueType> = Some(ValueType::Real);
+
+    fn into_return(self) -> Result<Option<f64>, String> {
+        Ok(Some(self as f64))
+    }
+}
+
+impl IntoReturn for () {
+    const RET: Option<ValueType> = None;
+
+    fn into_return(self) -> Result<Option<f64>, String> {
+        Ok(None)
+    }
+}
+
+macro_rules! result_return {
+    ($($ty:ty),*) => {$(
+        impl<E: fmt::Display> IntoReturn for Result<$ty, E> {
+            const RET: Option<ValueType> = <$ty as IntoReturn>::RET;
+
+            fn into_return(self) -> Result<Option<f64>, String> {
+                self.map_err(|err| err.to_string())?.into_return()
+            }
+        }
+    )*};
+}
+
+result_return!((), f64, f32, i64, i32, u32, usize);
+
+macro_rules! native_fn {
+    ($($arg:ident),*) => {
+        impl<Func, Ret, $($arg),*> NativeFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Ret + Send + 'static,
+            Ret: IntoReturn,
+            $($arg: FromValue,)*
+        {
+            fn signature() -> Signature {
+                Signature {
+                    params: vec![$($arg::TYPE),*],
+                    ret: Ret::RET,
+                }
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, args: &[f64]) -> Result<Option<f64>, CallErr> {
+                let mut args = args.iter().enumerate();
+                $(
+                    let (idx, value) = args.next().expect("arity is checked by the caller");
+                    let $arg = $arg::from_value(*value).map_err(|err| CallErr::Arg(idx, err))?;
+                )*
+                self($($arg),*).into_return().map_err(CallErr::Host)
+            }
+        }
+    };
+}
+
+native_fn!();
+native_fn!(A);
+native_fn!(A, B);
+native_fn!(A, B, C);
+native_fn!(A, B, C, D);
+
+type Body = dyn Fn(&[f64]) -> Result<Option<f64>, CallErr> + Send;
+
+pub(crate) struct Native {
+    pub signature: Signature,
+    pub func: Box<Body>,
+}
+
+impl Native {
+    pub fn new<Args, F: NativeFn<Args>>(func: F) -> Self {
+        Self {
+            signature: F::signature(),
+            func: Box::new(move |args| func.call(args)),
+        }
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native").field("signature", &self.signature).finish_non_exhaustive()
+    }
+}
+
+/// Semantic checks of calls: every function exists, gets as many
+/// arguments as it takes and no argument is statically of the wrong type.
+/// Procedures may only be called as statements, and `ReadLn` only with
+/// variables.
+pub(crate) struct Checker<'a> {
+    natives: &'a HashMap<String, Native>,
+    // `Parser::statement_lines`, or empty
+    lines: &'a [u32],
+    statements: usize,
+    // line of the statement being checked
+    line: Option<u32>,
+    pub err: Option<NativeErr>,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(natives: &'a HashMap<String, Native>, lines: &'a [u32]) -> Self {
+        Self {
+            natives,
+            lines,
+            statements: 0,
+            line: None,
+            err: None,
+        }
+    }
+
+    // statements are checked in the same pre-order the parser numbers them
+    pub fn statement(&mut self, node: &Node) {
+        self.line = self.lines.get(self.statements).copied();
+        self.statements += 1;
+
+        match node {
+            Node::Call(call) => self.call(call, false),
+            node => self.visit_node(node),
+        }
+    }
+
+    fn call(&mut self, call: &Call, is_expr: bool) {
+        if self.err.is_none() {
+            self.err = self.check_call(call, is_expr);
+        }
+        visit::walk_call(self, call);
+    }
+
+    fn check_call(&self, call: &Call, is_expr: bool) -> Option<NativeErr> {
+        let fail = |err, message: String| {
+            Some(NativeErr {
+                function: call.name.clone(),
+                err,
+                message,
+                line: self.line,
+            })
+        };
+        match Standard::get(&call.name) {
+            Some(_) if is_expr => {
+                return fail(
+                    InterpreterErr::TypeMismatch,
+                    format!("procedure `{}` has no value", call.name),
+                )
+            },
+            Some(Standard::ReadLn) if !call.args.iter().all(|arg| matches!(arg, Node::Ident(_))) => {
+                return fail(
+                    InterpreterErr::InvalidAssignment,
+                    format!("`{}` can only read into variables", call.name),
+                )
+            },
+            Some(_) => return None,
+            None => (),
+        }
+
+        let signature = match self.natives.get(&call.name) {
+            Some(native) => &native.signature,
+            None => {
+                return fail(
+                    InterpreterErr::UndefinedFunction,
+                    format!("`{}` isn't defined", call.name),
+                )
+            }
+        };
+
+        if call.args.len() != signature.params.len() {
+            return fail(
+                InterpreterErr::ArityMismatch,
+                format!(
+                    "`{}` takes {} argument(s), got {}",
+                    call.name,
+                    signature.params.len(),
+                    call.args.len()
+                ),
+            );
+        }
+        if is_expr && signature.ret.is_none() {
+            return fail(
+                InterpreterErr::TypeMismatch,
+                format!("procedure `{}` has no value", call.name),
+            );
+        }
+        for (idx, (arg, param)) in call.args.iter().zip(&signature.params).enumerate() {
+            match self.static_type(arg) {
+                Some(ValueType::Real) if *param == ValueType::Integer => {
+                    return fail(
+                        InterpreterErr::TypeMismatch,
+                        format!("argument {} of `{}` must be an INTEGER, got a REAL", idx + 1, call.name),
+                    )
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    // the type an expression has whatever the variables hold, if known
+    fn static_type(&self, node: &Node) -> Option<ValueType> {
+        match node {
+            Node::Literal(lit) => types::literal(lit),
+            Node::Call(call) => self.natives.get(&call.name)?.signature.ret,
+            Node::UnaryOp(unary_op) => self.static_type(&unary_op.node),
+            Node::BinOp(bin_op) if bin_op.op == OpKind::AssignEq => self.static_type(&bin_op.rhs),
+            Node::BinOp(bin_op) => types::bin_op(
+                &bin_op.op,
+                self.static_type(&bin_op.lhs),
+                self.static_type(&bin_op.rhs),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Checker<'_> {
+    fn visit_compound(&mut self, compound: &'ast CompoundNode) {
+        for child in &compound.children {
+            self.statement(child);
+        }
+    }
+
+    fn visit_call(&mut self, call: &'ast Call) {
+        self.call(call, true);
+    }
+}
@@ -0,0 +1,379 @@
+//! `.pcode` files: compiled [`Program`]s saved to disk.
+//!
+//! All integers are little-endian. A file is the magic `PCODE`, a `u16`
+//! format version and then
+//!
+//! ```text
+//! dialect: u8 (0 ISO 7185, 1 Turbo Pascal, 2 Free Pascal)
+//! slots: u32
+//! scopes: u32, per scope: vars: u32, per var: name (u32 length, UTF-8), slot: u32
+//! lines: u32, per entry: offset: u32, line: u32
+//! strings: u32, per string: u32 length, UTF-8
+//! code: u32, per op: tag: u8, operand (f64 for Const, u32 for slots,
+//!       scopes and strings, u8 error code for Trap)
+//! ```
+//!
+//! Loading checks every slot, scope and string index, so a loaded program can't
+//! make the VM index out of bounds. Every slot is named by a scope entry or
+//! an op, so loading also rejects more slots than the program has of those
+//! together, or than it uses, and a small file can't make the VM allocate
+//! gigabytes. Nothing may follow the code.
+
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    bytecode::{Op, Program},
+    interpreter::InterpreterErr,
+    snapshot::DIALECTS,
+};
+
+pub const MAGIC: &[u8; 5] = b"PCODE";
+pub const VERSION: u16 = 4;
+
+#[derive(Debug)]
+pub enum PcodeErr {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    Corrupt(String),
+}
+
+impl fmt::Display for PcodeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcodeErr::Io(err) => write!(f, "{}", err),
+            PcodeErr::BadMagic => f.write_str("not a .pcode file"),
+            PcodeErr::UnsupportedVersion(version) => write!(
+                f,
+                ".pcode version {} isn't supported, expected {}",
+                version, VERSION
+            ),
+            PcodeErr::Corrupt(reason) => write!(f, "corrupt .pcode file: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for PcodeErr {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => PcodeErr::Corrupt(String::from("unexpected end of file")),
+            _ => PcodeErr::Io(err),
+        }
+    }
+}
+
+impl Program {
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+
+        let dialect = DIALECTS.iter().position(|dialect| *dialect == self.dialect).unwrap();
+        out.write_all(&[dialect as u8])?;
+        write_u32(out, self.slots)?;
+
+        write_len(out, self.scopes.len())?;
+        for scope in &self.scopes {
+            write_len(out, scope.len())?;
+            for (name, slot) in scope {
+                write_len(out, name.len())?;
+                out.write_all(name.as_bytes())?;
+                write_u32(out, *slot)?;
+            }
+        }
+
+        write_len(out, self.lines.len())?;
+        for (offset, line) in &self.lines {
+            write_u32(out, *offset)?;
+            write_u32(out, *line)?;
+        }
+
+        write_len(out, self.strings.len())?;
+        for string in &self.strings {
+            write_len(out, string.len())?;
+            out.write_all(string.as_bytes())?;
+        }
+
+        write_len(out, self.code.len())?;
+        for op in &self.code {
+            write_op(out, op)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(input: &mut R) -> Result<Self, PcodeErr> {
+        let mut magic = [0; 5];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(PcodeErr::BadMagic);
+        }
+
+        let mut version = [0; 2];
+        input.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(PcodeErr::UnsupportedVersion(version));
+        }
+
+        let mut dialect = [0];
+        input.read_exact(&mut dialect)?;
+        let dialect = match DIALECTS.get(dialect[0] as usize) {
+            Some(dialect) => *dialect,
+            None => return Err(PcodeErr::Corrupt(String::from("unknown dialect"))),
+        };
+
+        let mut program = Program {
+            slots: read_u32(input)?,
+            dialect,
+            ..Default::default()
+        };
+
+        for _ in 0..read_u32(input)? {
+            let mut scope = Vec::new();
+            for _ in 0..read_u32(input)? {
+                let name = read_string(input, "variable name")?;
+                scope.push((name, program.check_slot(read_u32(input)?)?));
+            }
+            program.scopes.push(scope);
+        }
+
+        for _ in 0..read_u32(input)? {
+            program.lines.push((read_u32(input)?, read_u32(input)?));
+        }
+
+        for _ in 0..read_u32(input)? {
+            let string = read_string(input, "string")?;
+            program.strings.push(string);
+        }
+
+        for _ in 0..read_u32(input)? {
+            let op = read_op(input)?;
+            match op {
+                Op::Load(slot) | Op::Store(slot) => {
+                    program.check_slot(slot)?;
+                },
+                Op::ExitScope(scope) if scope as usize >= program.scopes.len() => {
+                    return Err(PcodeErr::Corrupt(format!("scope {} out of range", scope)));
+                },
+                Op::WriteStr(index) if index as usize >= program.strings.len() => {
+                    return Err(PcodeErr::Corrupt(format!("string {} out of range", index)));
+                },
+                _ => (),
+            }
+            program.code.push(op);
+        }
+
+        let named = program.scopes.iter().map(Vec::len).sum::<usize>() + program.code.len();
+        if program.slots as usize > named {
+            return Err(PcodeErr::Corrupt(format!(
+                "{} slots but only {} scope entries and ops",
+                program.slots, named
+            )));
+        }
+
+        // slots are numbered from 0, so the highest one used is the count
+        let scope_slots = program.scopes.iter().flatten().map(|(_, slot)| *slot);
+        let code_slots = program.code.iter().filter_map(|op| match op {
+            Op::Load(slot) | Op::Store(slot) => Some(*slot),
+            _ => None,
+        });
+        let used = scope_slots.chain(code_slots).max().map_or(0, |slot| slot + 1);
+        if program.slots > used {
+            return Err(PcodeErr::Corrupt(format!(
+                "{} slots but only {} used",
+                program.slots, used
+            )));
+        }
+
+        if input.read(&mut [0])? != 0 {
+            return Err(PcodeErr::Corrupt(String::from("trailing bytes after the code")));
+        }
+
+        Ok(program)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).expect("writing to a Vec can't fail");
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, PcodeErr> {
+        Self::read_from(&mut bytes)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PcodeErr> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    fn check_slot(&self, slot: u32) -> Result<u32, PcodeErr> {
+        if slot < self.slots {
+            Ok(slot)
+        } else {
+            Err(PcodeErr::Corrupt(format!("slot {} out of range", slot)))
+        }
+    }
+}
+
+// op tags, part of the file format: never reuse or renumber them
+const CONST: u8 = 0;
+const LOAD: u8 = 1;
+const STORE: u8 = 2;
+const DUP: u8 = 3;
+const POP: u8 = 4;
+const NEG: u8 = 5;
+const ADD: u8 = 6;
+const SUB: u8 = 7;
+const MUL: u8 = 8;
+const SLASH: u8 = 9;
+const POW: u8 = 10;
+const INT_DIV: u8 = 11;
+const MOD: u8 = 12;
+const EXIT_SCOPE: u8 = 13;
+const TRAP: u8 = 14;
+const INT_ADD: u8 = 15;
+const INT_SUB: u8 = 16;
+const INT_MUL: u8 = 17;
+const WRITE: u8 = 18;
+const WRITE_STR: u8 = 19;
+const WRITE_LN: u8 = 20;
+const READ_LINE: u8 = 21;
+const READ: u8 = 22;
+
+/// Error codes of `Trap` ops by position, also reported by
+/// [`wasm`](crate::wasm) modules. Append only.
+pub const TRAPS: &[InterpreterErr] = &[
+    InterpreterErr::InvalidLiteral,
+    InterpreterErr::InvalidUnaryOp,
+    InterpreterErr::InvalidBinOp,
+    InterpreterErr::UndefinedIdent,
+    InterpreterErr::InvalidAssignment,
+    InterpreterErr::DivisionByZero,
+    InterpreterErr::IntegerOverflow,
+    InterpreterErr::UndefinedErr,
+    InterpreterErr::UndefinedFunction,
+    InterpreterErr::ArityMismatch,
+    InterpreterErr::TypeMismatch,
+    InterpreterErr::HostError,
+    InterpreterErr::InvalidInput,
+];
+
+pub(crate) fn trap_code(err: InterpreterErr) -> u8 {
+    TRAPS.iter().position(|trap| *trap == err).unwrap() as u8
+}
+
+fn write_op<W: Write>(out: &mut W, op: &Op) -> io::Result<()> {
+    match *op {
+        Op::Const(value) => {
+            out.write_all(&[CONST])?;
+            out.write_all(&value.to_le_bytes())
+        },
+        Op::Load(slot) => write_tagged(out, LOAD, slot),
+        Op::Store(slot) => write_tagged(out, STORE, slot),
+        Op::Dup => out.write_all(&[DUP]),
+        Op::Pop => out.write_all(&[POP]),
+        Op::Neg => out.write_all(&[NEG]),
+        Op::Add => out.write_all(&[ADD]),
+        Op::Sub => out.write_all(&[SUB]),
+        Op::Mul => out.write_all(&[MUL]),
+        Op::IntAdd => out.write_all(&[INT_ADD]),
+        Op::IntSub => out.write_all(&[INT_SUB]),
+        Op::IntMul => out.write_all(&[INT_MUL]),
+        Op::Slash => out.write_all(&[SLASH]),
+        Op::Pow => out.write_all(&[POW]),
+        Op::IntDiv => out.write_all(&[INT_DIV]),
+        Op::Mod => out.write_all(&[MOD]),
+        Op::ExitScope(scope) => write_tagged(out, EXIT_SCOPE, scope),
+        Op::Write => out.write_all(&[WRITE]),
+        Op::WriteStr(index) => write_tagged(out, WRITE_STR, index),
+        Op::WriteLn => out.write_all(&[WRITE_LN]),
+        Op::ReadLine => out.write_all(&[READ_LINE]),
+        Op::Read => out.write_all(&[READ]),
+        Op::Trap(err) => {
+            out.write_all(&[TRAP, trap_code(err)])
+        },
+    }
+}
+
+fn read_op<R: Read>(input: &mut R) -> Result<Op, PcodeErr> {
+    let mut tag = [0];
+    input.read_exact(&mut tag)?;
+
+    Ok(match tag[0] {
+        CONST => {
+            let mut value = [0; 8];
+            input.read_exact(&mut value)?;
+            Op::Const(f64::from_le_bytes(value))
+        },
+        LOAD => Op::Load(read_u32(input)?),
+        STORE => Op::Store(read_u32(input)?),
+        DUP => Op::Dup,
+        POP => Op::Pop,
+        NEG => Op::Neg,
+        ADD => Op::Add,
+        SUB => Op::Sub,
+        MUL => Op::Mul,
+        INT_ADD => Op::IntAdd,
+        INT_SUB => Op::IntSub,
+        INT_MUL => Op::IntMul,
+        SLASH => Op::Slash,
+        POW => Op::Pow,
+        INT_DIV => Op::IntDiv,
+        MOD => Op::Mod,
+        EXIT_SCOPE => Op::ExitScope(read_u32(input)?),
+        WRITE => Op::Write,
+        WRITE_STR => Op::WriteStr(read_u32(input)?),
+        WRITE_LN => Op::WriteLn,
+        READ_LINE => Op::ReadLine,
+        READ => Op::Read,
+        TRAP => {
+            let mut code = [0];
+            input.read_exact(&mut code)?;
+            match TRAPS.get(code[0] as usize) {
+                Some(err) => Op::Trap(*err),
+                None => return Err(PcodeErr::Corrupt(format!("unknown trap {}", code[0]))),
+            }
+        },
+        tag => return Err(PcodeErr::Corrupt(format!("unknown op {}", tag))),
+    })
+}
+
+fn write_tagged<W: Write>(out: &mut W, tag: u8, operand: u32) -> io::Result<()> {
+    out.write_all(&[tag])?;
+    write_u32(out, operand)
+}
+
+fn write_u32<W: Write>(out: &mut W, value: u32) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_len<W: Write>(out: &mut W, len: usize) -> io::Result<()> {
+    match u32::try_from(len) {
+        Ok(len) => write_u32(out, len),
+        Err(_) => Err(io::Error::new(io::ErrorKind::InvalidInput, "program too large for .pcode")),
+    }
+}
+
+// a corrupt length must not allocate gigabytes up front
+fn read_string<R: Read>(input: &mut R, what: &str) -> Result<String, PcodeErr> {
+    let len = read_u32(input)? as u64;
+    let mut bytes = Vec::new();
+    input.by_ref().take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(PcodeErr::Corrupt(String::from("unexpected end of file")));
+    }
+    String::from_utf8(bytes).map_err(|_| PcodeErr::Corrupt(format!("{} isn't UTF-8", what)))
+}
+
+fn read_u32<R: Read>(input: &mut R) -> Result<u32, PcodeErr> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
@@ -0,0 +1,253 @@
+//! Snapshots of suspended runs, see `Interpreter::snapshot`.
+//!
+//! A run that stops with `BudgetExhausted` or `Cancelled` keeps its tasks
+//! and can be saved to bytes, restored in another process and continued
+//! with `Interpreter::resume`. All integers are little-endian. A snapshot
+//! is the magic `SPISNAP`, a `u16` format version and then
+//!
+//! ```text
+//! fingerprint: u64 of the program's tree
+//! dialect: u8 (0 ISO 7185, 1 Turbo Pascal, 2 Free Pascal)
+//! memory: u64 bytes of variables created so far
+//! scopes: u32, innermost first and the global frame last, per scope:
+//!         vars: u32, per var: name (u32 length, UTF-8), value: f64
+//! blocks: u32 finished blocks in exit order, each like a scope
+//! tasks: u32 bottom of the stack first, per task: tag: u8, node: u32,
+//!        and for blocks the next statement: u32
+//! values: u32, per value: f64
+//! ```
+//!
+//! Node ids number the tree breadth-first. Resuming checks the fingerprint
+//! against the program, and loading checks that every open block has a
+//! scope, so a restored run can't pop scopes or values that aren't there.
+
+use std::{
+    collections::{HashMap, LinkedList},
+    fmt,
+    io::{self, Read},
+};
+
+use simple_pascal_ast::dialect::Dialect;
+
+use crate::interpreter::{Machine, Task};
+
+pub const MAGIC: &[u8; 7] = b"SPISNAP";
+pub const VERSION: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotErr {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Corrupt(String),
+}
+
+impl fmt::Display for SnapshotErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotErr::BadMagic => f.write_str("not a snapshot"),
+            SnapshotErr::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} isn't supported, expected {}",
+                version, VERSION
+            ),
+            SnapshotErr::Corrupt(reason) => write!(f, "corrupt snapshot: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotErr {
+    // reading from a slice only fails at its end
+    fn from(_: io::Error) -> Self {
+        SnapshotErr::Corrupt(String::from("unexpected end of snapshot"))
+    }
+}
+
+// everything of an `Interpreter` a suspended run needs
+#[derive(Debug)]
+pub(crate) struct State {
+    pub dialect: Dialect,
+    pub memory: usize,
+    pub vars: LinkedList<HashMap<String, f64>>,
+    pub hist_vars: LinkedList<HashMap<String, f64>>,
+    pub machine: Machine,
+}
+
+// task tags, part of the format: never reuse or renumber them
+const EVAL: u8 = 0;
+const APPLY: u8 = 1;
+const BLOCK: u8 = 2;
+
+// dialects by their tag, also in `.pcode` files
+pub(crate) const DIALECTS: &[Dialect] = &[Dialect::Iso7185, Dialect::TurboPascal, Dialect::FreePascal];
+
+impl State {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        out.extend_from_slice(&self.machine.fingerprint.to_le_bytes());
+        let dialect = DIALECTS.iter().position(|dialect| *dialect == self.dialect).unwrap();
+        out.push(dialect as u8);
+        out.extend_from_slice(&(self.memory as u64).to_le_bytes());
+
+        write_scopes(&mut out, &self.vars);
+        write_scopes(&mut out, &self.hist_vars);
+
+        write_len(&mut out, self.machine.tasks.len());
+        for task in &self.machine.tasks {
+            match *task {
+                Task::Eval(node) => {
+                    out.push(EVAL);
+                    out.extend_from_slice(&node.to_le_bytes());
+                },
+                Task::Apply(node) => {
+                    out.push(APPLY);
+                    out.extend_from_slice(&node.to_le_bytes());
+                },
+                Task::Block { block, next } => {
+                    out.push(BLOCK);
+                    out.extend_from_slice(&block.to_le_bytes());
+                    out.extend_from_slice(&next.to_le_bytes());
+                },
+            }
+        }
+
+        write_len(&mut out, self.machine.values.len());
+        for value in &self.machine.values {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, SnapshotErr> {
+        let input = &mut bytes;
+
+        let mut magic = [0; 7];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotErr::BadMagic);
+        }
+
+        let mut version = [0; 2];
+        input.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(SnapshotErr::UnsupportedVersion(version));
+        }
+
+        let fingerprint = read_u64(input)?;
+        let dialect = match DIALECTS.get(read_u8(input)? as usize) {
+            Some(dialect) => *dialect,
+            None => return Err(SnapshotErr::Corrupt(String::from("unknown dialect"))),
+        };
+        let memory = usize::try_from(read_u64(input)?)
+            .map_err(|_| SnapshotErr::Corrupt(String::from("memory out of range")))?;
+
+        let vars = read_scopes(input)?;
+        let hist_vars = read_scopes(input)?;
+
+        let mut tasks = Vec::new();
+        for _ in 0..read_u32(input)? {
+            tasks.push(match read_u8(input)? {
+                EVAL => Task::Eval(read_u32(input)?),
+                APPLY => Task::Apply(read_u32(input)?),
+                BLOCK => Task::Block {
+                    block: read_u32(input)?,
+                    next: read_u32(input)?,
+                },
+                tag => return Err(SnapshotErr::Corrupt(format!("unknown task {}", tag))),
+            });
+        }
+
+        let mut values = Vec::new();
+        for _ in 0..read_u32(input)? {
+            values.push(f64::from_bits(read_u64(input)?));
+        }
+
+        if tasks.is_empty() {
+            return Err(SnapshotErr::Corrupt(String::from("no run to resume")));
+        }
+        let blocks = tasks.iter().filter(|task| matches!(task, Task::Block { .. })).count();
+        if vars.len() != blocks + 1 {
+            return Err(SnapshotErr::Corrupt(format!(
+                "{} open block(s) but {} scope(s)",
+                blocks,
+                vars.len()
+            )));
+        }
+
+        Ok(State {
+            dialect,
+            memory,
+            vars,
+            hist_vars,
+            machine: Machine {
+                fingerprint,
+                tasks,
+                values,
+            },
+        })
+    }
+}
+
+fn write_scopes(out: &mut Vec<u8>, scopes: &LinkedList<HashMap<String, f64>>) {
+    write_len(out, scopes.len());
+    for scope in scopes {
+        // sorted, so the same state always has the same bytes
+        let mut vars: Vec<_> = scope.iter().collect();
+        vars.sort_by_key(|(name, _)| *name);
+
+        write_len(out, vars.len());
+        for (name, value) in vars {
+            write_len(out, name.len());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+fn read_scopes(input: &mut &[u8]) -> Result<LinkedList<HashMap<String, f64>>, SnapshotErr> {
+    let mut scopes = LinkedList::new();
+    for _ in 0..read_u32(input)? {
+        let mut scope = HashMap::new();
+        for _ in 0..read_u32(input)? {
+            // a corrupt length must not allocate gigabytes up front
+            let len = read_u32(input)? as u64;
+            let mut name = Vec::new();
+            input.by_ref().take(len).read_to_end(&mut name)?;
+            if name.len() as u64 != len {
+                return Err(SnapshotErr::Corrupt(String::from("unexpected end of snapshot")));
+            }
+            let name = String::from_utf8(name)
+                .map_err(|_| SnapshotErr::Corrupt(String::from("variable name isn't UTF-8")))?;
+
+            scope.insert(name, f64::from_bits(read_u64(input)?));
+        }
+        scopes.push_back(scope);
+    }
+    Ok(scopes)
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("more than u32::MAX entries in a snapshot");
+    out.extend_from_slice(&len.to_le_bytes());
+}
+
+fn read_u8(input: &mut &[u8]) -> Result<u8, SnapshotErr> {
+    let mut bytes = [0; 1];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32, SnapshotErr> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut &[u8]) -> Result<u64, SnapshotErr> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,115 @@
+//! Standard procedures: `Write`, `WriteLn` and `ReadLn`.
+//!
+//! They're part of the language rather than host functions, so compiled
+//! programs have them too, and they're always statements:
+//!
+//! - `Write(args)` prints its arguments one after another: string literals
+//!   as they are, any other expression as its value, formatted by
+//!   [`format_number`]
+//! - `WriteLn(args)` does the same and ends the line
+//! - `ReadLn(vars)` reads the next line of input and assigns its numbers,
+//!   separated by whitespace, to the variables in order, ignoring any
+//!   left over. A missing line or number, or one that isn't written in
+//!   decimal, fails with `InvalidInput`
+//!
+//! Calls always take parentheses, `WriteLn()` just ends the line. A
+//! variable `ReadLn` assigns to has no static type, as the input may be
+//! any number.
+
+use std::fmt;
+
+use simple_pascal_ast::{node::Node, token::literal::Literal};
+
+use crate::interpreter::InterpreterErr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Standard {
+    Write,
+    WriteLn,
+    ReadLn,
+}
+
+impl Standard {
+    pub fn get(name: &str) -> Option<Self> {
+        match name {
+            "Write" => Some(Standard::Write),
+            "WriteLn" => Some(Standard::WriteLn),
+            "ReadLn" => Some(Standard::ReadLn),
+            _ => None,
+        }
+    }
+}
+
+/// How `Write` prints a number: like Rust's `{}` for `f64`, the fewest
+/// digits that read back exactly and never an exponent, e.g. `3`, `0.1`
+/// or `-2.5`.
+pub fn format_number(value: f64) -> String {
+    value.to_string()
+}
+
+/// A number `ReadLn` reads: optional sign, digits with an optional
+/// fraction and exponent, nothing else.
+pub fn parse_number(token: &str) -> Result<f64, InterpreterErr> {
+    let decimal = token.bytes().all(|byte| byte.is_ascii_digit() || b"+-.eE".contains(&byte));
+    match token.parse() {
+        Ok(value) if decimal => Ok(value),
+        _ => Err(InterpreterErr::InvalidInput),
+    }
+}
+
+// the text of an argument `Write` prints as it is
+pub(crate) fn string_arg(arg: &Node) -> Option<&str> {
+    match arg {
+        Node::Literal(Literal::String(text)) => Some(text),
+        _ => None,
+    }
+}
+
+type Output = dyn FnMut(&str) + Send;
+type Input = dyn FnMut() -> Option<String> + Send;
+
+/// Where the standard procedures write to and read from.
+#[derive(Default)]
+pub(crate) struct Io {
+    // everything written since the last `start`
+    pub output: String,
+    pub write: Option<Box<Output>>,
+    pub read: Option<Box<Input>>,
+    // numbers of the line being read, the next one last
+    tokens: Vec<String>,
+}
+
+impl Io {
+    pub fn start(&mut self) {
+        self.output.clear();
+        self.tokens.clear();
+    }
+
+    pub fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+        if let Some(write) = &mut self.write {
+            write(text);
+        }
+    }
+
+    pub fn write_number(&mut self, value: f64) {
+        self.write(&format_number(value));
+    }
+
+    // starts reading the next line, dropping what's left of the last one
+    pub fn read_line(&mut self) -> Result<(), InterpreterErr> {
+        let line = self.read.as_mut().and_then(|read| read()).ok_or(InterpreterErr::InvalidInput)?;
+        self.tokens = line.split_whitespace().rev().map(String::from).collect();
+        Ok(())
+    }
+
+    pub fn read(&mut self) -> Result<f64, InterpreterErr> {
+        parse_number(&self.tokens.pop().ok_or(InterpreterErr::InvalidInput)?)
+    }
+}
+
+impl fmt::Debug for Io {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Io").field("output", &self.output).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,231 @@
+//! Static INTEGER and REAL types of expressions.
+//!
+//! Values are all `f64`s at run time, and programs have no declarations
+//! yet, so types follow from the expressions alone, like in `ConstFolder`:
+//!
+//! - integer literals are INTEGERs, real literals REALs
+//! - `+`, `-` and `*` of two INTEGERs are INTEGERs, with a REAL operand
+//!   REALs
+//! - `/` and `^` are always REAL, `DIV`, `MOD` and `%` always INTEGER
+//! - calls have their function's result type
+//! - a variable is an INTEGER or a REAL when every assignment to it in the
+//!   program is; variables of one name in different blocks count as one,
+//!   and one that `ReadLn` reads into has no type
+//!
+//! Everything else has no static type, e.g. a global the host set. Only
+//! results that are statically INTEGERs are range checked, see
+//! `arith::bin_op`, so REAL arithmetic never fails with `IntegerOverflow`.
+//!
+//! Inference works on the program as an `arena::Ast`, keeping each pass's
+//! expression types in a `NodeMap`.
+
+use std::collections::{HashMap, HashSet};
+
+use simple_pascal_ast::{
+    arena::{Ast, AstNode, NodeId, NodeMap},
+    node::*,
+    token::{literal::Literal, op::OpKind},
+};
+
+use crate::{native::ValueType, standard::Standard};
+
+// the type of an expression while variables are inferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+    // reads a variable none of whose assignments is typed yet
+    Pending,
+    Known(Option<ValueType>),
+}
+
+// what the type of a node depends on besides those of its children
+enum Shape<'a> {
+    Literal(&'a Literal),
+    Ident(&'a str),
+    Assign,
+    BinOp(&'a OpKind),
+    UnaryOp,
+    Call(&'a str),
+    Other,
+}
+
+impl<'a> From<&'a Node> for Shape<'a> {
+    fn from(node: &'a Node) -> Self {
+        match node {
+            Node::Literal(lit) => Shape::Literal(lit),
+            Node::Ident(name) => Shape::Ident(name),
+            Node::BinOp(bin_op) if bin_op.op == OpKind::AssignEq => Shape::Assign,
+            Node::BinOp(bin_op) => Shape::BinOp(&bin_op.op),
+            Node::UnaryOp(_) => Shape::UnaryOp,
+            Node::Call(call) => Shape::Call(&call.name),
+            _ => Shape::Other,
+        }
+    }
+}
+
+impl<'a> From<&'a AstNode> for Shape<'a> {
+    fn from(node: &'a AstNode) -> Self {
+        match node {
+            AstNode::Literal(lit) => Shape::Literal(lit),
+            AstNode::Ident(name) => Shape::Ident(name),
+            AstNode::BinOp { op: OpKind::AssignEq, .. } => Shape::Assign,
+            AstNode::BinOp { op, .. } => Shape::BinOp(op),
+            AstNode::UnaryOp { .. } => Shape::UnaryOp,
+            AstNode::Call { name, .. } => Shape::Call(name),
+            _ => Shape::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Types {
+    vars: HashMap<String, Option<ValueType>>,
+    // result types of the functions the program may call
+    calls: HashMap<String, ValueType>,
+}
+
+impl Types {
+    /// Types of the variables of `ast`, which calls the functions in
+    /// `calls`.
+    pub fn infer(ast: &Node, calls: HashMap<String, ValueType>) -> Self {
+        let ast = Ast::from_node(ast);
+        let mut types = Types {
+            vars: HashMap::new(),
+            calls,
+        };
+        let mut assigned = HashSet::new();
+        for id in ast.ids() {
+            assigned.extend(assignment(&ast, id).into_iter().chain(read(&ast, id)).map(String::from));
+        }
+
+        // starting with every variable pending, each pass can only make
+        // types less specific, so they settle after a few passes
+        loop {
+            let vars = types.pass(&ast, &assigned);
+            if vars == types.vars {
+                break;
+            }
+            types.vars = vars;
+        }
+
+        // only assigned from themselves, e.g. `x := x + 1` on a host global
+        for name in assigned {
+            types.vars.entry(name).or_insert(None);
+        }
+        types
+    }
+
+    /// The static type of the variable `name`.
+    pub fn var(&self, name: &str) -> Option<ValueType> {
+        self.vars.get(name).copied().flatten()
+    }
+
+    /// The static type of `node`, given those of its children in order.
+    pub fn node(&self, node: &Node, children: &[Option<ValueType>]) -> Option<ValueType> {
+        let children: Vec<Ty> = children.iter().copied().map(Ty::Known).collect();
+        match self.ty(node.into(), &children, &HashSet::new()) {
+            Ty::Known(ty) => ty,
+            Ty::Pending => None,
+        }
+    }
+
+    // one pass over `ast`, returning the types of the variables its
+    // assignments give
+    fn pass(&self, ast: &Ast, assigned: &HashSet<String>) -> HashMap<String, Option<ValueType>> {
+        let mut vars: HashMap<String, Option<ValueType>> = HashMap::new();
+        let mut types = NodeMap::new();
+
+        // ids are in pre-order, so going backwards children come first
+        for id in ast.ids().rev() {
+            let node = &ast[id];
+            let children: Vec<Ty> = node
+                .children()
+                .into_iter()
+                .map(|child| types.get(child).copied().unwrap_or(Ty::Known(None)))
+                .collect();
+            let ty = self.ty(node.into(), &children, assigned);
+
+            if let (Some(name), Ty::Known(ty)) = (assignment(ast, id), ty) {
+                let var = vars.entry(name.to_string()).or_insert(ty);
+                if *var != ty {
+                    *var = None;
+                }
+            }
+            for name in read(ast, id) {
+                vars.insert(name.to_string(), None);
+            }
+            types.insert(id, ty);
+        }
+        vars
+    }
+
+    fn ty(&self, shape: Shape, children: &[Ty], assigned: &HashSet<String>) -> Ty {
+        match shape {
+            Shape::Literal(lit) => Ty::Known(literal(lit)),
+            Shape::Ident(name) => match self.vars.get(name) {
+                Some(ty) => Ty::Known(*ty),
+                None if assigned.contains(name) => Ty::Pending,
+                None => Ty::Known(None),
+            },
+            Shape::UnaryOp => children[0],
+            Shape::Assign => children[1],
+            Shape::BinOp(op) => combine(op, children[0], children[1]),
+            Shape::Call(name) => Ty::Known(self.calls.get(name).copied()),
+            Shape::Other => Ty::Known(None),
+        }
+    }
+}
+
+pub(crate) fn literal(lit: &Literal) -> Option<ValueType> {
+    match lit {
+        Literal::Integer(_) => Some(ValueType::Integer),
+        Literal::Float(_) => Some(ValueType::Real),
+        Literal::String(_) => None,
+    }
+}
+
+/// The type of `lhs op rhs`.
+pub(crate) fn bin_op(op: &OpKind, lhs: Option<ValueType>, rhs: Option<ValueType>) -> Option<ValueType> {
+    match combine(op, Ty::Known(lhs), Ty::Known(rhs)) {
+        Ty::Known(ty) => ty,
+        Ty::Pending => None,
+    }
+}
+
+fn combine(op: &OpKind, lhs: Ty, rhs: Ty) -> Ty {
+    use ValueType::*;
+
+    match op {
+        OpKind::Slash | OpKind::Caret => Ty::Known(Some(Real)),
+        OpKind::Div | OpKind::Mod | OpKind::Percent => Ty::Known(Some(Integer)),
+        _ => match (lhs, rhs) {
+            (Ty::Known(Some(Real)), _) | (_, Ty::Known(Some(Real))) => Ty::Known(Some(Real)),
+            (Ty::Pending, _) | (_, Ty::Pending) => Ty::Pending,
+            (Ty::Known(Some(Integer)), Ty::Known(Some(Integer))) => Ty::Known(Some(Integer)),
+            _ => Ty::Known(None),
+        },
+    }
+}
+
+// the variable `id` assigns to, if it's an assignment
+fn assignment(ast: &Ast, id: NodeId) -> Option<&str> {
+    match ast.get(id)? {
+        AstNode::BinOp { lhs, op: OpKind::AssignEq, .. } => ident(ast, *lhs),
+        _ => None,
+    }
+}
+
+// the variables `id` reads input into, if it's a `ReadLn`
+fn read(ast: &Ast, id: NodeId) -> impl Iterator<Item = &str> {
+    let args = match ast.get(id) {
+        Some(AstNode::Call { name, args }) if Standard::get(name) == Some(Standard::ReadLn) => &args[..],
+        _ => &[],
+    };
+    args.iter().filter_map(|arg| ident(ast, *arg))
+}
+
+fn ident(ast: &Ast, id: NodeId) -> Option<&str> {
+    match ast.get(id)? {
+        AstNode::Ident(name) => Some(name),
+        _ => None,
+    }
+}
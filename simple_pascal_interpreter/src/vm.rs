@@ -0,0 +1,142 @@
+//! Stack machine running [`Program`]s from [`bytecode::compile`].
+//!
+//! `Vm::run` returns the same variables and errors as
+//! `Interpreter::interpret` on the source tree, and reads and writes the
+//! same with `set_input` and `set_output`.
+//!
+//! [`bytecode::compile`]: crate::bytecode::compile
+
+use std::collections::{HashMap, LinkedList};
+
+use simple_pascal_ast::{dialect::Dialect, token::op::OpKind};
+
+use crate::{
+    arith,
+    bytecode::{Op, Program},
+    interpreter::InterpreterErr,
+    native::ValueType,
+    standard::Io,
+};
+
+#[derive(Debug, Default)]
+pub struct Vm {
+    stack: Vec<f64>,
+    slots: Vec<f64>,
+    dialect: Dialect,
+    io: Io,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_dialect(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Like `Interpreter::set_output`, `output` has it either way.
+    pub fn set_output<F: FnMut(&str) + Send + 'static>(&mut self, output: F) {
+        self.io.write = Some(Box::new(output));
+    }
+
+    /// Like `Interpreter::set_input`.
+    pub fn set_input<F: FnMut() -> Option<String> + Send + 'static>(&mut self, input: F) {
+        self.io.read = Some(Box::new(input));
+    }
+
+    /// What `Write` and `WriteLn` printed in the last run.
+    pub fn output(&self) -> &str {
+        &self.io.output
+    }
+
+    pub fn run(&mut self, program: &Program) -> Result<LinkedList<HashMap<String, f64>>, InterpreterErr> {
+        self.stack.clear();
+        self.io.start();
+        self.slots.clear();
+        self.slots.resize(program.slots as usize, 0.0);
+
+        let mut hist_vars = LinkedList::new();
+
+        for op in &program.code {
+            match *op {
+                Op::Const(value) => self.stack.push(value),
+                Op::Load(slot) => self.stack.push(self.slots[slot as usize]),
+                Op::Store(slot) => self.slots[slot as usize] = self.pop()?,
+                Op::Dup => {
+                    let value = self.pop()?;
+                    self.stack.extend([value, value]);
+                },
+                Op::Pop => {
+                    self.pop()?;
+                },
+                Op::Neg => {
+                    let value = self.pop()?;
+                    self.stack.push(-value);
+                },
+                Op::Add => self.bin_op(|lhs, rhs| Ok(lhs + rhs))?,
+                Op::Sub => self.bin_op(|lhs, rhs| Ok(lhs - rhs))?,
+                Op::Mul => self.bin_op(|lhs, rhs| Ok(lhs * rhs))?,
+                Op::IntAdd => self.int_op(OpKind::Plus)?,
+                Op::IntSub => self.int_op(OpKind::Minus)?,
+                Op::IntMul => self.int_op(OpKind::Star)?,
+                Op::Slash => self.bin_op(|lhs, rhs| Ok(lhs / rhs))?,
+                Op::Pow => self.bin_op(|lhs, rhs| Ok(lhs.powf(rhs)))?,
+                Op::IntDiv => self.int_op(OpKind::Div)?,
+                Op::Mod => self.int_op(OpKind::Mod)?,
+                Op::ExitScope(scope) => hist_vars.push_back(
+                    program
+                        .scope(scope)
+                        .iter()
+                        .map(|(name, slot)| (name.clone(), self.slots[*slot as usize]))
+                        .collect(),
+                ),
+                Op::Write => {
+                    let value = self.pop()?;
+                    self.io.write_number(value);
+                },
+                Op::WriteStr(index) => self.io.write(program.string(index)),
+                Op::WriteLn => self.io.write("\n"),
+                Op::ReadLine => self.io.read_line()?,
+                Op::Read => {
+                    let value = self.io.read()?;
+                    self.stack.push(value);
+                },
+                Op::Trap(err) => return Err(err),
+            }
+        }
+
+        Ok(hist_vars)
+    }
+
+    fn pop(&mut self) -> Result<f64, InterpreterErr> {
+        self.stack.pop().ok_or(InterpreterErr::UndefinedErr)
+    }
+
+    fn bin_op<F>(&mut self, op: F) -> Result<(), InterpreterErr>
+    where
+        F: FnOnce(f64, f64) -> Result<f64, InterpreterErr>,
+    {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        self.stack.push(op(lhs, rhs)?);
+        Ok(())
+    }
+
+    // an operator on INTEGERs
+    fn int_op(&mut self, op: OpKind) -> Result<(), InterpreterErr> {
+        let dialect = self.dialect;
+        self.bin_op(|lhs, rhs| arith::bin_op(dialect, &op, Some(ValueType::Integer), lhs, rhs))
+    }
+}
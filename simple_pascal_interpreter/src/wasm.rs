@@ -0,0 +1,254 @@
+//! WebAssembly back end: turns compiled programs into `.wat`/`.wasm`
+//! modules.
+//!
+//! Stack code maps almost one to one onto wasm instructions, and every
+//! variable slot becomes an `f64` local of the exported `main` function.
+//! `DIV`, `MOD` and `+`, `-` and `*` of INTEGERs with the dialect's
+//! integer checks are built into the module;
+//! everything else comes from the host, as imports of the `spi` module:
+//!
+//! ```text
+//! (func $var (param $name i32) (param $len i32) (param $value f64))
+//! (func $end_scope)
+//! (func $error (param $code i32))
+//! (func $pow (param f64 f64) (result f64))
+//! (func $write (param $value f64))
+//! (func $write_str (param $text i32) (param $len i32))
+//! (func $write_ln)
+//! (func $read_line (result i32))
+//! (func $read (result f64))
+//! ```
+//!
+//! When a block ends its variables are passed to `var` one by one, names as
+//! UTF-8 in the exported `memory`, followed by `end_scope`, in the order
+//! `Interpreter::interpret` returns the blocks. A run-time error calls
+//! `error` with the error's index in [`TRAPS`] and then traps.
+//!
+//! The rest are the standard procedures of [`crate::standard`]: `write`
+//! prints a number, formatted by [`format_number`], `write_str` a string
+//! in `memory` and `write_ln` ends the line. `read_line` starts reading
+//! the next line of input and returns 0 at the end of it, `read` returns
+//! the next number of the line, see [`parse_number`], or NaN when there's
+//! none, which no input spells. Both fail with `InvalidInput` like any
+//! other error.
+//!
+//! [`format_number`]: crate::standard::format_number
+//! [`parse_number`]: crate::standard::parse_number
+//!
+//! Binary modules need the `wasm` feature.
+
+use std::fmt::Write;
+
+use simple_pascal_ast::dialect::Dialect;
+
+use crate::{
+    bytecode::{Op, Program},
+    interpreter::InterpreterErr,
+    pcode::{trap_code, TRAPS},
+};
+
+/// Translates `program` to the WebAssembly text format.
+pub fn to_wat(program: &Program, dialect: Dialect) -> String {
+    // all variable names, one after another, from address 0, and then
+    // the strings `Write` prints
+    let mut names = String::new();
+    let mut name_ranges = Vec::new();
+    for scope in &program.scopes {
+        let mut ranges = Vec::new();
+        for (name, slot) in scope {
+            ranges.push((names.len(), name.len(), *slot));
+            names.push_str(name);
+        }
+        name_ranges.push(ranges);
+    }
+    let mut string_ranges = Vec::new();
+    for string in &program.strings {
+        string_ranges.push((names.len(), string.len()));
+        names.push_str(string);
+    }
+
+    let mut out = String::new();
+    writeln!(out, ";; Pascal program compiled to WebAssembly, {} rules", dialect).unwrap();
+    out.push_str("(module\n");
+    out.push_str("  (import \"spi\" \"var\" (func $var (param i32 i32 f64)))\n");
+    out.push_str("  (import \"spi\" \"end_scope\" (func $end_scope))\n");
+    out.push_str("  (import \"spi\" \"error\" (func $error (param i32)))\n");
+    out.push_str("  (import \"spi\" \"pow\" (func $pow (param f64 f64) (result f64)))\n");
+    out.push_str("  (import \"spi\" \"write\" (func $write (param f64)))\n");
+    out.push_str("  (import \"spi\" \"write_str\" (func $write_str (param i32 i32)))\n");
+    out.push_str("  (import \"spi\" \"write_ln\" (func $write_ln))\n");
+    out.push_str("  (import \"spi\" \"read_line\" (func $read_line (result i32)))\n");
+    out.push_str("  (import \"spi\" \"read\" (func $read (result f64)))\n");
+    // 64 KiB pages, enough for every name and string
+    let pages = names.len().div_ceil(65536).max(1);
+    writeln!(out, "  (memory (export \"memory\") {})", pages).unwrap();
+    if !names.is_empty() {
+        writeln!(out, "  (data (i32.const 0) \"{}\")", escape(&names)).unwrap();
+    }
+    out.push_str(&runtime(dialect));
+
+    out.push_str("  (func (export \"main\")\n");
+    for slot in 0..program.slots() {
+        writeln!(out, "    (local $v{} f64)", slot).unwrap();
+    }
+    // `Dup` keeps the value here while it's pushed twice
+    out.push_str("    (local $tmp f64)\n");
+
+    for op in program.code() {
+        match *op {
+            Op::Const(value) => writeln!(out, "    f64.const {}", wat_f64(value)),
+            Op::Load(slot) => writeln!(out, "    local.get $v{}", slot),
+            Op::Store(slot) => writeln!(out, "    local.set $v{}", slot),
+            Op::Dup => writeln!(out, "    local.tee $tmp\n    local.get $tmp"),
+            Op::Pop => writeln!(out, "    drop"),
+            Op::Neg => writeln!(out, "    f64.neg"),
+            Op::Add => writeln!(out, "    f64.add"),
+            Op::Sub => writeln!(out, "    f64.sub"),
+            Op::Mul => writeln!(out, "    f64.mul"),
+            Op::IntAdd => writeln!(out, "    call $add"),
+            Op::IntSub => writeln!(out, "    call $sub"),
+            Op::IntMul => writeln!(out, "    call $mul"),
+            Op::Slash => writeln!(out, "    f64.div"),
+            Op::Pow => writeln!(out, "    call $pow"),
+            Op::IntDiv => writeln!(out, "    call $div"),
+            Op::Mod => writeln!(out, "    call $mod"),
+            Op::ExitScope(scope) => {
+                for (start, len, slot) in &name_ranges[scope as usize] {
+                    writeln!(
+                        out,
+                        "    i32.const {}\n    i32.const {}\n    local.get $v{}\n    call $var",
+                        start, len, slot
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "    call $end_scope")
+            },
+            Op::Write => writeln!(out, "    call $write"),
+            Op::WriteStr(index) => {
+                let (start, len) = string_ranges[index as usize];
+                writeln!(out, "    i32.const {}\n    i32.const {}\n    call $write_str", start, len)
+            },
+            Op::WriteLn => writeln!(out, "    call $write_ln"),
+            Op::ReadLine => writeln!(out, "    call $next_line"),
+            Op::Read => writeln!(out, "    call $next_number"),
+            Op::Trap(err) => writeln!(out, "{}", raise(err, "    ")),
+        }
+        .unwrap();
+    }
+    out.push_str("  )\n)\n");
+    out
+}
+
+/// Translates `program` to a binary `.wasm` module.
+#[cfg(feature = "wasm")]
+pub fn to_wasm(program: &Program, dialect: Dialect) -> Vec<u8> {
+    wat::parse_str(to_wat(program, dialect)).expect("generated modules are valid")
+}
+
+// `+`, `-` and `*` of INTEGERs, `DIV` and `MOD`, see `arith::bin_op`,
+// and reading input
+fn runtime(dialect: Dialect) -> String {
+    let max_int = wat_f64(dialect.max_int() as f64);
+    let invalid_input = raise(InterpreterErr::InvalidInput, "      ");
+    let invalid_bin_op = raise(InterpreterErr::InvalidBinOp, "      ");
+    let overflow = raise(InterpreterErr::IntegerOverflow, "      ");
+    let division_by_zero = raise(InterpreterErr::DivisionByZero, "      ");
+
+    // ISO 7185 requires a positive divisor and a non-negative result
+    let (iso_divisor, iso_result) = if dialect == Dialect::Iso7185 {
+        (
+            format!(
+                "    (if (f64.lt (local.get $rhs) (f64.const 0))\n      (then\n{}))\n",
+                invalid_bin_op
+            ),
+            String::from(
+                "    (if (f64.lt (local.get $res) (f64.const 0))\n      \
+                 (then (local.set $res (f64.add (local.get $res) (f64.abs (local.get $rhs))))))\n",
+            ),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+
+    format!(
+        r#"  (func $check_int (param $value f64)
+    (if (f64.ne (f64.sub (local.get $value) (f64.trunc (local.get $value))) (f64.const 0))
+      (then
+{invalid_bin_op}))
+    (if (f64.gt (f64.abs (local.get $value)) (f64.const {max_int}))
+      (then
+{overflow})))
+  (func $int_result (param $res f64) (result f64)
+    (if (f64.gt (f64.abs (local.get $res)) (f64.const {max_int}))
+      (then
+{overflow}))
+    (local.get $res))
+  (func $add (param $lhs f64) (param $rhs f64) (result f64)
+    (call $int_result (f64.add (local.get $lhs) (local.get $rhs))))
+  (func $sub (param $lhs f64) (param $rhs f64) (result f64)
+    (call $int_result (f64.sub (local.get $lhs) (local.get $rhs))))
+  (func $mul (param $lhs f64) (param $rhs f64) (result f64)
+    (call $int_result (f64.mul (local.get $lhs) (local.get $rhs))))
+  (func $int_operands (param $lhs f64) (param $rhs f64)
+    (call $check_int (local.get $lhs))
+    (call $check_int (local.get $rhs))
+    (if (f64.eq (local.get $rhs) (f64.const 0))
+      (then
+{division_by_zero})))
+  (func $div (param $lhs f64) (param $rhs f64) (result f64)
+    (call $int_operands (local.get $lhs) (local.get $rhs))
+    (f64.trunc (f64.div (local.get $lhs) (local.get $rhs))))
+  (func $mod (param $lhs f64) (param $rhs f64) (result f64)
+    (local $res f64)
+    (call $int_operands (local.get $lhs) (local.get $rhs))
+{iso_divisor}    ;; magnitudes up to 2^63 fit a u64 exactly, the sign is the dividend's
+    (local.set $res
+      (f64.copysign
+        (f64.convert_i64_u
+          (i64.rem_u
+            (i64.trunc_f64_u (f64.abs (local.get $lhs)))
+            (i64.trunc_f64_u (f64.abs (local.get $rhs)))))
+        (local.get $lhs)))
+{iso_result}    (local.get $res))
+  (func $next_line
+    (if (i32.eqz (call $read_line))
+      (then
+{invalid_input})))
+  (func $next_number (result f64)
+    (local $value f64)
+    (local.set $value (call $read))
+    (if (f64.ne (local.get $value) (local.get $value))
+      (then
+{invalid_input}))
+    (local.get $value))
+"#
+    )
+}
+
+fn raise(err: InterpreterErr, indent: &str) -> String {
+    debug_assert!(TRAPS.contains(&err));
+    format!("{indent}i32.const {}\n{indent}call $error\n{indent}unreachable", trap_code(err))
+}
+
+fn wat_f64(value: f64) -> String {
+    if value.is_nan() {
+        String::from("nan")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "inf" } else { "-inf" })
+    } else {
+        // `Debug` keeps a `.0` or an exponent, both valid wat floats
+        format!("{:?}", value)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'"' | b'\\' => write!(escaped, "\\{:02x}", byte).unwrap(),
+            0x20..=0x7e => escaped.push(byte as char),
+            _ => write!(escaped, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    escaped
+}
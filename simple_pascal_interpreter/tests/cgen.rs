@@ -0,0 +1,214 @@
+use std::{
+    collections::{HashMap, LinkedList},
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use simple_pascal_ast::{dialect::Dialect, parser::Parser};
+use simple_pascal_interpreter::{cgen::transpile, interpreter::InterpreterErr, Interpreter};
+
+const PROGRAMS: &[&str] = &[
+    "BEGIN END.",
+    "BEGIN ;-2; END.",
+    r"BEGIN
+        x:= 2 + 3 * (2 + 3);
+        y:= 2 / 2 - 2 + 3 * ((1 + 1) + (1 + 1));
+    END.",
+    r"BEGIN
+        y := 2;
+        BEGIN
+            a := 3;
+            a := a;
+            b := 10 + a + 10 * y / 4;
+            c := a - b
+        END;
+        x := 11;
+    END.",
+    "BEGIN BEGIN x := 1 END; x := 2; BEGIN x := x + 1 END END.",
+    "BEGIN a := 7 DIV 2; b := -7 DIV 2; c := -7 MOD 3; d := 7 % -3 END.",
+    "BEGIN x := 2 ^ 3 ^ 2; y := - -2 ^ 2; z := 0.1 + 0.2 END.",
+    "BEGIN x := 1; x := x + 1; y := x * x END.",
+    "BEGIN z := 1; w := q END.",
+    "BEGIN x := 1; y := x MOD 0 END.",
+    "BEGIN x := 1.5 DIV 1 END.",
+    "BEGIN x := 2147483648 MOD 2 END.",
+    "BEGIN x := 2147483647 + 1; y := 2147483647.5 + 1 END.",
+    "BEGIN x := 65536 * 65536 - 1 END.",
+    "BEGIN x := 1 / 0; y := -x END.",
+    // REAL operands are never range checked, whatever their values
+    "BEGIN x := 65536.0 * 65536.0 END.",
+    "BEGIN x := 4 / 2 * 2147483647 END.",
+    // strings are only printed, in C too
+    "BEGIN x := 1; s := 'hi' END.",
+];
+
+// programs using the standard procedures, with their input
+const IO_PROGRAMS: &[(&str, &str)] = &[
+    ("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x) END.", "3.5 4 extra\n"),
+    ("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x) END.", "1\n"),
+    (
+        "BEGIN ReadLn(); ReadLn(x); WriteLn(x / 3, ' ', -x, ' ', x * 1000000000000000000000.0, ' ', x / 1000000000) END.",
+        "skipped\n2\n",
+    ),
+    ("BEGIN WriteLn('it''s \"quoted\" \\ ??= caf\u{e9}') END.", ""),
+    ("BEGIN ReadLn(x); WriteLn(x) END.", "0x10\n"),
+    ("BEGIN ReadLn(x); x := x + 1; WriteLn(x, ' ', -0.0, ' ', 0.1 + 0.2) END.", "2147483647\n"),
+    ("BEGIN ReadLn(x, y); WriteLn(x, ' ', y) END.", "-.5 1.e3"),
+];
+
+type Output = Result<LinkedList<HashMap<String, f64>>, InterpreterErr>;
+
+fn cc_available() -> bool {
+    Command::new("cc").arg("--version").output().is_ok()
+}
+
+fn build_c(name: &str, source: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("spi-cgen-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let c_file = dir.join(format!("{}.c", name));
+    let exe = dir.join(name);
+    fs::write(&c_file, source).unwrap();
+
+    let status = Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Werror", "-o"])
+        .arg(&exe)
+        .arg(&c_file)
+        .arg("-lm")
+        .status()
+        .unwrap();
+    assert!(status.success(), "cc failed on:\n{}", source);
+    exe
+}
+
+fn error(output: &std::process::Output) -> InterpreterErr {
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+    match stderr.trim().strip_prefix("runtime error: ") {
+        Some("InvalidLiteral") => InterpreterErr::InvalidLiteral,
+        Some("InvalidUnaryOp") => InterpreterErr::InvalidUnaryOp,
+        Some("InvalidBinOp") => InterpreterErr::InvalidBinOp,
+        Some("UndefinedIdent") => InterpreterErr::UndefinedIdent,
+        Some("InvalidAssignment") => InterpreterErr::InvalidAssignment,
+        Some("DivisionByZero") => InterpreterErr::DivisionByZero,
+        Some("IntegerOverflow") => InterpreterErr::IntegerOverflow,
+        Some("UndefinedErr") => InterpreterErr::UndefinedErr,
+        Some("InvalidInput") => InterpreterErr::InvalidInput,
+        _ => panic!("unexpected stderr: {}", stderr),
+    }
+}
+
+// builds and runs the C program, reading its output back
+fn run_c(name: &str, source: &str) -> Output {
+    let output = Command::new(build_c(name, source)).output().unwrap();
+    if !output.status.success() {
+        return Err(error(&output));
+    }
+
+    Ok(String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|pair| {
+                    let (name, value) = pair.split_once('=').unwrap();
+                    (name.to_string(), value.parse().unwrap())
+                })
+                .collect()
+        })
+        .collect())
+}
+
+#[test]
+fn matches_interpreter() {
+    if !cc_available() {
+        eprintln!("cc not found, skipping");
+        return;
+    }
+
+    let mut parser = Parser::calculator();
+
+    for dialect in [Dialect::Iso7185, Dialect::TurboPascal, Dialect::FreePascal] {
+        parser.set_dialect(dialect);
+        let mut interpreter = Interpreter::with_dialect(dialect);
+
+        for (idx, source) in PROGRAMS.iter().enumerate() {
+            let ast = match parser.parse(source) {
+                Ok(ast) => ast,
+                Err(_) => continue,
+            };
+
+            assert_eq!(
+                run_c(&format!("{:?}_{}", dialect, idx), &transpile(&ast, dialect).unwrap()),
+                interpreter.interpret(&ast),
+                "running `{}` in {}",
+                source,
+                dialect
+            );
+        }
+    }
+}
+
+// builds and runs the C program with `input` on stdin, returns whether it
+// failed and all it printed
+fn run_c_with_input(name: &str, source: &str, input: &str) -> (Result<(), InterpreterErr>, String) {
+    let mut child = Command::new(build_c(name, source))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let status = if output.status.success() { Ok(()) } else { Err(error(&output)) };
+    (status, String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn standard_procedures() {
+    if !cc_available() {
+        eprintln!("cc not found, skipping");
+        return;
+    }
+
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+
+    for (idx, (source, input)) in IO_PROGRAMS.iter().enumerate() {
+        let ast = parser.parse(source).unwrap();
+        let mut lines = input.lines().map(String::from).collect::<Vec<_>>().into_iter();
+        interpreter.set_input(move || lines.next());
+        let res = interpreter.execute(&ast);
+
+        // the program block's variables come last, on a line of their own
+        let (status, stdout) =
+            run_c_with_input(&format!("io_{}", idx), &transpile(&ast, Dialect::Iso7185).unwrap(), input);
+        let output = match (status, stdout.strip_suffix('\n')) {
+            (Ok(()), Some(rest)) => &rest[..rest.rfind('\n').map_or(0, |at| at + 1)],
+            _ => &stdout,
+        };
+        assert_eq!((status, output), (res.status, res.output.as_str()), "running `{}`", source);
+    }
+}
+
+#[test]
+fn generated_code() {
+    let ast = Parser::new().parse("BEGIN x := 1; y := -x DIV 2 END.").unwrap();
+    let code = transpile(&ast, Dialect::TurboPascal).unwrap();
+
+    assert!(code.contains("#define SPI_MAX_INT 2147483647.0"));
+    assert!(code.contains("#define SPI_ISO_MOD 0"));
+    assert!(code.contains("    double v0_x = 0.0;\n    double v1_y = 0.0;\n"));
+    assert!(code.contains("    v0_x = 1.0;\n    const double t0 = spi_div((-v0_x), 2.0);\n    v1_y = t0;\n"));
+    assert!(code.contains("    printf(\"x=%.17g y=%.17g\\n\", v0_x, v1_y);\n"));
+
+    // strings are C literals, with no trigraphs
+    let ast = Parser::new().parse("BEGIN ReadLn(x); WriteLn('\"??/\u{e9}', x) END.").unwrap();
+    let code = transpile(&ast, Dialect::Iso7185).unwrap();
+    assert!(code.contains(
+        "    spi_read_line();\n    const double t0 = spi_read();\n    v0_x = t0;\n    \
+         spi_write_str(\"\\\"\\?\\?/\\303\\251\");\n    spi_write(v0_x);\n    spi_write_ln();\n"
+    ));
+}
@@ -86,7 +86,7 @@ fn exprs_and_vars() -> (Vec<&'static str>, Vec<LinkedList<HashMap<String, f64>>>
 
 #[test]
 fn interpret() {
-    let mut parser = Parser::new();
+    let mut parser = Parser::calculator();
     let mut interpreter = Interpreter::new();
 
     let (exprs, vars) = exprs_and_vars();
@@ -94,9 +94,775 @@ fn interpret() {
     for (i, expr) in exprs.iter().enumerate() {
         assert_eq!(
             interpreter.interpret(
-                parser.parse(expr).unwrap()
+                &parser.parse(expr).unwrap()
             ).unwrap(),
             vars[i]
         )
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn right_associative_power() {
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+        interpreter.interpret(
+            &parser.parse("BEGIN x := 2 ^ 3 ^ 2; y := -2 ^ 2 END.").unwrap()
+        ).unwrap(),
+        LinkedList::from([
+            HashMap::from([
+                (String::from("x"), 512.0),
+                (String::from("y"), -4.0)
+            ])
+        ])
+    );
+}
+
+#[test]
+fn dialect_div_mod() {
+    use simple_pascal_ast::dialect::Dialect;
+
+    let source = "BEGIN a := 7 DIV 2; b := -7 DIV 2; c := -7 MOD 3 END.";
+
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::with_dialect(Dialect::Iso7185);
+    assert_eq!(
+        interpreter.interpret(&parser.parse(source).unwrap()).unwrap(),
+        LinkedList::from([
+            HashMap::from([
+                (String::from("a"), 3.0),
+                (String::from("b"), -3.0),
+                (String::from("c"), 2.0)
+            ])
+        ])
+    );
+
+    interpreter.set_dialect(Dialect::TurboPascal);
+    assert_eq!(
+        interpreter.interpret(&parser.parse(source).unwrap()).unwrap(),
+        LinkedList::from([
+            HashMap::from([
+                (String::from("a"), 3.0),
+                (String::from("b"), -3.0),
+                (String::from("c"), -1.0)
+            ])
+        ])
+    );
+
+    for source in ["BEGIN x := 1 MOD 0 END.", "BEGIN x := 1.5 DIV 1 END."] {
+        assert!(interpreter.interpret(&parser.parse(source).unwrap()).is_err());
+    }
+}
+
+#[test]
+fn integer_overflow() {
+    use simple_pascal_ast::dialect::Dialect;
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    let source = "BEGIN x := 2147483647 + 1 END.";
+
+    for dialect in [Dialect::Iso7185, Dialect::TurboPascal] {
+        let ast = Parser::with_dialect(dialect).parse(source).unwrap();
+        assert_eq!(
+            Interpreter::with_dialect(dialect).interpret(&ast),
+            Err(InterpreterErr::IntegerOverflow),
+            "in {}",
+            dialect
+        );
+    }
+
+    let ast = Parser::with_dialect(Dialect::FreePascal).parse(source).unwrap();
+    assert_eq!(
+        Interpreter::with_dialect(Dialect::FreePascal).interpret(&ast).unwrap(),
+        LinkedList::from([HashMap::from([(String::from("x"), 2147483648.0)])])
+    );
+    let ast = Parser::with_dialect(Dialect::FreePascal)
+        .parse("BEGIN x := 9223372036854775807 * 2 END.")
+        .unwrap();
+    assert_eq!(
+        Interpreter::with_dialect(Dialect::FreePascal).interpret(&ast),
+        Err(InterpreterErr::IntegerOverflow)
+    );
+
+    // reals aren't limited, even whole ones
+    for source in [
+        "BEGIN x := 2147483647 + 0.5; y := x * x END.",
+        "BEGIN x := 65536.0 * 65536.0 END.",
+        "BEGIN x := 4 / 2 * 2147483647 END.",
+        "BEGIN x := 65536.0; y := x * x END.",
+    ] {
+        let ast = Parser::new().parse(source).unwrap();
+        assert!(Interpreter::new().interpret(&ast).is_ok(), "running `{}`", source);
+    }
+
+    // variables have the type of their assignments
+    let ast = Parser::new().parse("BEGIN x := 2147483647; y := x + 1 END.").unwrap();
+    assert_eq!(Interpreter::new().interpret(&ast), Err(InterpreterErr::IntegerOverflow));
+    let ast = Parser::new().parse("BEGIN x := 2147483647; y := x + 1; x := 0.5 END.").unwrap();
+    assert!(Interpreter::new().interpret(&ast).is_ok());
+}
+
+#[test]
+fn reuse_ast() {
+    let ast = Parser::new().parse(
+        r"BEGIN
+            y := 2;
+            BEGIN
+                a := 3;
+                b := 10 + a + 10 * y / 4;
+            END;
+            x := y * 2;
+        END."
+    ).unwrap();
+    let expected = LinkedList::from([
+        HashMap::from([
+            (String::from("a"), 3.0),
+            (String::from("b"), 18.0)
+        ]),
+        HashMap::from([
+            (String::from("x"), 4.0),
+            (String::from("y"), 2.0)
+        ])
+    ]);
+
+    let mut interpreter = Interpreter::new();
+    for _ in 0..3 {
+        assert_eq!(interpreter.interpret(&ast).unwrap(), expected);
+    }
+
+    // a failed run doesn't leak its variables into the next one
+    let failing = Parser::new().parse("BEGIN z := 1; w := q END.").unwrap();
+    assert!(interpreter.interpret(&failing).is_err());
+    assert_eq!(interpreter.interpret(&ast).unwrap(), expected);
+
+    // several interpreters can share one tree
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| Interpreter::new().interpret(&ast).unwrap()))
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    });
+}
+
+#[test]
+fn unparsed_nodes() {
+    use simple_pascal_ast::{
+        node::{BinOp, CompoundNode, Node},
+        token::{delim::DelimKind, keyword::Keyword, op::OpKind},
+    };
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    // trees built by hand may hold nodes the parser never produces
+    let mut interpreter = Interpreter::new();
+    for node in [Node::Keyword(Keyword::Begin), Node::Delim(DelimKind::Comma)] {
+        let assign = BinOp::new(Node::Ident(String::from("x")), OpKind::AssignEq, node);
+        let ast = CompoundNode::from_list([assign].into());
+        assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::UndefinedErr));
+    }
+}
+
+#[test]
+fn const_fold_keeps_results() {
+    use simple_pascal_ast::{const_fold::const_fold, dialect::Dialect};
+
+    let mut parser = Parser::calculator();
+    let (exprs, vars) = exprs_and_vars();
+
+    for dialect in [Dialect::Iso7185, Dialect::FreePascal] {
+        let mut interpreter = Interpreter::with_dialect(dialect);
+
+        for (i, expr) in exprs.iter().enumerate() {
+            let ast = const_fold(parser.parse(expr).unwrap(), dialect);
+            assert_eq!(interpreter.interpret(&ast).unwrap(), vars[i]);
+        }
+
+        // operations that fail at run time still fail after folding
+        for source in ["BEGIN x := 1 MOD (2 - 2) END.", "BEGIN x := -7 MOD -2 + 1.5 DIV 1 END."] {
+            let ast = const_fold(parser.parse(source).unwrap(), dialect);
+            assert!(interpreter.interpret(&ast).is_err());
+        }
+    }
+}
+
+#[test]
+fn const_fold_cases_run_the_same() {
+    use simple_pascal_ast::{const_fold::const_fold, dialect::Dialect};
+
+    // the cases of the folder's own tests, which only check the trees
+    let exprs = [
+        // fold_constants
+        "10 * 2 / 4",
+        "2 + 3 * 4",
+        "1.5 + 2",
+        "7 DIV 2 + 7 MOD 2",
+        "2 ^ 3 ^ 2",
+        "-(2 + 3)",
+        "-2.5 * 2",
+        "+4",
+        "1 / 3",
+        "y + 2 * 3",
+        "y + 2 + 3",
+        // keep_trapping_operations
+        "1 / 0",
+        "1 / (2 - 2)",
+        "1 DIV 0",
+        "1 MOD 0",
+        "7.0 DIV 2",
+        "-7 MOD -2",
+        "-7 MOD 2",
+        "2147483647 + 1",
+        "-2147483647 - 1",
+    ];
+
+    for dialect in [Dialect::Iso7185, Dialect::TurboPascal, Dialect::FreePascal] {
+        let mut interpreter = Interpreter::with_dialect(dialect);
+
+        for expr in exprs {
+            let source = format!("BEGIN y := 5; x := {} END.", expr);
+            let ast = Parser::calculator().parse(&source).unwrap();
+            let expected = interpreter.interpret(&ast);
+            let folded = interpreter.interpret(&const_fold(ast, dialect));
+            assert_eq!(folded, expected, "`{}` in {}", expr, dialect);
+        }
+    }
+}
+
+#[test]
+fn eval_keeps_globals() {
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    let mut parser = Parser::calculator();
+    let mut interpreter = Interpreter::new();
+    let mut eval = |text: &'static str| -> Result<Vec<f64>, InterpreterErr> {
+        let statements = parser.parse_statements(text).unwrap();
+        statements.iter().map(|node| interpreter.eval(node)).collect()
+    };
+
+    assert_eq!(eval("x := 2; y := x * 3"), Ok(vec![2.0, 6.0]));
+    assert_eq!(eval("x + y"), Ok(vec![8.0]));
+    // variables of blocks are still local to them
+    assert_eq!(eval("BEGIN z := 1; x := z + 1 END"), Ok(vec![2.0]));
+    assert_eq!(eval("z"), Err(InterpreterErr::UndefinedIdent));
+    // a failed statement keeps what was assigned before it
+    assert_eq!(eval("BEGIN x := 10; w := 1 DIV 0 END"), Err(InterpreterErr::DivisionByZero));
+
+    let mut globals: Vec<_> = interpreter.globals().collect();
+    globals.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+    assert_eq!(globals, [("x", 10.0), ("y", 6.0)]);
+
+    interpreter.reset();
+    assert_eq!(interpreter.globals().count(), 0);
+}
+
+#[test]
+fn execution_result() {
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+    let ast = parser.parse("BEGIN y := 2; BEGIN a := 3 END; x := y + 1 END.").unwrap();
+
+    let res = interpreter.execute(&ast);
+    assert!(res.is_ok());
+    assert_eq!(res.program_vars, HashMap::from([(String::from("x"), 3.0), (String::from("y"), 2.0)]));
+    assert_eq!(res.trace, None);
+
+    interpreter.set_trace(true);
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.trace, Some(interpreter.interpret(&ast).unwrap()));
+
+    // a failed program keeps what its block assigned so far
+    let ast = parser.parse("BEGIN y := 2; BEGIN a := 3; b := a DIV 0 END; x := 1 END.").unwrap();
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.status, Err(InterpreterErr::DivisionByZero));
+    assert_eq!(res.program_vars, HashMap::from([(String::from("y"), 2.0)]));
+    assert_eq!(res.trace, Some(LinkedList::new()));
+}
+
+#[test]
+fn failed_statement_line() {
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    let mut parser = Parser::new();
+    let ast = parser.parse("BEGIN\n  y := 0;\n  BEGIN\n    x := 1 DIV y\n  END\nEND.").unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::DivisionByZero));
+    // without the program's lines there's nothing to report
+    assert_eq!(interpreter.line(), None);
+
+    interpreter.set_statement_lines(parser.statement_lines());
+    assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::DivisionByZero));
+    assert_eq!(interpreter.line(), Some(4));
+}
+
+#[test]
+fn execution_result_types() {
+    use simple_pascal_interpreter::native::ValueType;
+
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_global("rate", 0.5);
+    let ast = parser
+        .parse("BEGIN i := 7 DIV 2; r := i / 2; m := 1; m := 1.5; g := rate; BEGIN a := 1 END END.")
+        .unwrap();
+
+    let res = interpreter.execute(&ast);
+    assert!(res.is_ok());
+    assert_eq!(
+        res.types,
+        HashMap::from([
+            (String::from("i"), Some(ValueType::Integer)),
+            (String::from("r"), Some(ValueType::Real)),
+            (String::from("m"), None),
+            (String::from("g"), None),
+        ])
+    );
+}
+
+#[test]
+fn host_functions() {
+    use std::sync::{Arc, Mutex};
+
+    use simple_pascal_interpreter::{
+        interpreter::InterpreterErr,
+        native::{Signature, ValueType},
+    };
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut interpreter = Interpreter::new();
+    interpreter.register_fn("GetSensor", |id: i32| match id {
+        0..=3 => Ok(id as f64 * 1.5),
+        _ => Err(format!("no sensor {}", id)),
+    });
+    interpreter.register_fn("Max", |lhs: f64, rhs: f64| lhs.max(rhs));
+    interpreter.register_fn("Log", {
+        let log = log.clone();
+        move |value: f64| log.lock().unwrap().push(value)
+    });
+
+    assert_eq!(
+        interpreter.signature("GetSensor"),
+        Some(&Signature { params: vec![ValueType::Integer], ret: Some(ValueType::Real) })
+    );
+    assert_eq!(interpreter.signature("Log").unwrap().ret, None);
+
+    let mut parser = Parser::new();
+    let mut run = |text: &'static str| {
+        let res = interpreter.execute(&parser.parse(text).unwrap());
+        (res.status, res.program_vars, interpreter.native_error().map(|err| err.message.clone()))
+    };
+
+    let (status, globals, _) = run("BEGIN x := Max(GetSensor(3), 2) + 1; Log(x); Log(-x) END.");
+    assert_eq!(status, Ok(()));
+    assert_eq!(globals, HashMap::from([(String::from("x"), 5.5)]));
+    assert_eq!(*log.lock().unwrap(), [5.5, -5.5]);
+
+    // host errors stop the program
+    let (status, globals, message) = run("BEGIN x := 1; y := GetSensor(x + 4) END.");
+    assert_eq!(status, Err(InterpreterErr::HostError));
+    assert_eq!(globals, HashMap::from([(String::from("x"), 1.0)]));
+    assert_eq!(message.as_deref(), Some("no sensor 5"));
+
+    // arguments are converted at run time
+    let (status, _, message) = run("BEGIN x := 1 / 2; y := GetSensor(x) END.");
+    assert_eq!(status, Err(InterpreterErr::TypeMismatch));
+    assert_eq!(message.as_deref(), Some("argument 1 of `GetSensor` can't be 0.5"));
+
+    // calls are checked before anything runs
+    log.lock().unwrap().clear();
+    for (text, err, expected) in [
+        ("BEGIN Log(1); Beep() END.", InterpreterErr::UndefinedFunction, "`Beep` isn't defined"),
+        ("BEGIN Log(1); x := Max(1) END.", InterpreterErr::ArityMismatch, "`Max` takes 2 argument(s), got 1"),
+        (
+            "BEGIN Log(1); x := GetSensor(2 / 1) END.",
+            InterpreterErr::TypeMismatch,
+            "argument 1 of `GetSensor` must be an INTEGER, got a REAL",
+        ),
+        ("BEGIN Log(1); x := Log(2) END.", InterpreterErr::TypeMismatch, "procedure `Log` has no value"),
+    ] {
+        let (status, globals, message) = run(text);
+        assert_eq!(status, Err(err), "running `{}`", text);
+        assert!(globals.is_empty());
+        assert_eq!(message.as_deref(), Some(expected));
+    }
+    assert!(log.lock().unwrap().is_empty());
+
+    // with the program's lines, errors tell where the call is
+    let text = "BEGIN\n    x := 1;\n    BEGIN\n        y := GetSensor(x + 4)\n    END\nEND.";
+    let ast = parser.parse(text).unwrap();
+    interpreter.set_statement_lines(parser.statement_lines());
+    assert_eq!(interpreter.execute(&ast).status, Err(InterpreterErr::HostError));
+    assert_eq!(interpreter.native_error().unwrap().line, Some(4));
+
+    let ast = parser.parse("BEGIN\n    Log(1);\n    Beep()\nEND.").unwrap();
+    interpreter.set_statement_lines(parser.statement_lines());
+    assert_eq!(interpreter.check(&ast), Err(InterpreterErr::UndefinedFunction));
+    assert_eq!(interpreter.native_error().unwrap().line, Some(3));
+}
+
+#[test]
+fn standard_procedures() {
+    use std::sync::{Arc, Mutex};
+
+    use simple_pascal_interpreter::{interpreter::InterpreterErr, standard::format_number};
+
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+    let ast = parser
+        .parse("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x); WriteLn() END.")
+        .unwrap();
+
+    // without input there's nothing to read
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.status, Err(InterpreterErr::InvalidInput));
+    assert_eq!(res.output, "sum? ");
+
+    let mut lines = vec![String::from("3.5 4 extra"), String::from("  -1 \t 1e3 ")].into_iter();
+    interpreter.set_input(move || lines.next());
+    let written = Arc::new(Mutex::new(String::new()));
+    interpreter.set_output({
+        let written = written.clone();
+        move |text| written.lock().unwrap().push_str(text)
+    });
+
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.status, Ok(()));
+    assert_eq!(res.output, "sum? 3.5 + 4 = 7.5\n\n");
+    assert_eq!(*written.lock().unwrap(), res.output);
+    assert_eq!(interpreter.execute(&ast).output, "sum? -1 + 1000 = 999\n\n");
+    // the input is used up
+    assert_eq!(interpreter.execute(&ast).status, Err(InterpreterErr::InvalidInput));
+
+    // numbers must be decimal, and there must be enough of them
+    for line in ["0x10 1", "inf 1", "1,5 2", "1"] {
+        let mut lines = vec![String::from(line)].into_iter();
+        interpreter.set_input(move || lines.next());
+        assert_eq!(interpreter.execute(&ast).status, Err(InterpreterErr::InvalidInput), "reading `{}`", line);
+    }
+
+    // values read aren't INTEGERs, so they're never range checked
+    let mut lines = vec![String::from("2147483647")].into_iter();
+    interpreter.set_input(move || lines.next());
+    let ast = parser.parse("BEGIN ReadLn(x); x := x + 1; WriteLn(x) END.").unwrap();
+    assert_eq!(interpreter.execute(&ast).output, "2147483648\n");
+
+    // they're statements, and `ReadLn` only reads into variables
+    for (text, err) in [
+        ("BEGIN WriteLn(1); x := WriteLn(2) END.", InterpreterErr::TypeMismatch),
+        ("BEGIN WriteLn(1); ReadLn(x + 1) END.", InterpreterErr::InvalidAssignment),
+    ] {
+        let res = interpreter.execute(&parser.parse(text).unwrap());
+        assert_eq!(res.status, Err(err), "running `{}`", text);
+        assert_eq!(res.output, "");
+    }
+    // strings are still no values
+    let res = interpreter.execute(&parser.parse("BEGIN WriteLn('a'); x := 'b' END.").unwrap());
+    assert_eq!((res.status, res.output.as_str()), (Err(InterpreterErr::InvalidLiteral), "a\n"));
+
+    for (value, text) in [(3.0, "3"), (-0.0, "-0"), (0.1, "0.1"), (1e21, "1000000000000000000000"), (1.5e-7, "0.00000015")] {
+        assert_eq!(format_number(value), text);
+    }
+}
+
+#[test]
+fn host_globals() {
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    let mut parser = Parser::new();
+    let ast = parser
+        .parse("BEGIN total := amount * (1 + rate); BEGIN tmp := 1 END; runs := runs + 1 END.")
+        .unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_global("rate", 0.5);
+    interpreter.set_global("amount", 100);
+    interpreter.set_global("runs", 0_u32);
+
+    // globals aren't part of any block
+    let blocks = interpreter.interpret(&ast).unwrap();
+    assert_eq!(
+        blocks.back(),
+        Some(&HashMap::from([(String::from("total"), 150.0)]))
+    );
+    assert_eq!(interpreter.get_global::<f64>("total"), Ok(150.0));
+    assert_eq!(interpreter.get_global::<i32>("total"), Ok(150));
+    assert_eq!(interpreter.get_global::<u32>("runs"), Ok(1));
+    assert_eq!(interpreter.get_global::<f64>("tmp"), Err(InterpreterErr::UndefinedIdent));
+    assert_eq!(interpreter.get_global::<i64>("rate"), Err(InterpreterErr::TypeMismatch));
+
+    // assignments to globals outlive the run
+    interpreter.set_global("rate", 0.25);
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.program_vars, HashMap::from([(String::from("total"), 125.0)]));
+    assert_eq!(interpreter.get_global::<f64>("runs"), Ok(2.0));
+
+    let mut globals: Vec<_> = interpreter.globals().collect();
+    globals.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+    assert_eq!(globals, [("amount", 100.0), ("rate", 0.25), ("runs", 2.0)]);
+
+    interpreter.reset();
+    assert_eq!(interpreter.get_global::<f64>("total"), Err(InterpreterErr::UndefinedIdent));
+    assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::UndefinedIdent));
+}
+
+#[test]
+fn fuel() {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    // 1 block + 2 for `x := 1` + 4 for `y := x + 2`
+    let ast = Parser::new().parse("BEGIN x := 1; y := x + 2 END.").unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.fuel(), None);
+
+    interpreter.set_fuel(Some(7));
+    assert!(interpreter.interpret(&ast).is_ok());
+    assert_eq!(interpreter.fuel(), Some(0));
+
+    // a run stops where the budget ends
+    interpreter.add_fuel(6);
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.status, Err(InterpreterErr::BudgetExhausted));
+    assert_eq!(res.program_vars, HashMap::from([(String::from("x"), 1.0)]));
+
+    // or continues with what `refuel` hands out
+    let refuels = Arc::new(AtomicU64::new(0));
+    interpreter.set_refuel({
+        let refuels = refuels.clone();
+        move || if refuels.fetch_add(1, Ordering::SeqCst) == 0 { 3 } else { 0 }
+    });
+    interpreter.set_fuel(Some(6));
+    assert!(interpreter.interpret(&ast).is_ok());
+    assert_eq!(interpreter.fuel(), Some(2));
+    assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::BudgetExhausted));
+    assert_eq!(refuels.load(Ordering::SeqCst), 2);
+
+    interpreter.set_fuel(None);
+    assert!(interpreter.interpret(&ast).is_ok());
+}
+
+#[test]
+fn cancellation() {
+    use std::{thread, time::Duration};
+
+    use simple_pascal_interpreter::interpreter::InterpreterErr;
+
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+    let token = interpreter.cancel_token();
+    let stop = token.clone();
+    interpreter.register_fn("Stop", move || stop.cancel());
+    interpreter.register_fn("Sleep", |ms: u32| thread::sleep(Duration::from_millis(ms as u64)));
+
+    // the statement after the cancelling one doesn't run
+    let res = interpreter.execute(&parser.parse("BEGIN x := 1; Stop(); y := 2 END.").unwrap());
+    assert_eq!(res.status, Err(InterpreterErr::Cancelled));
+    assert_eq!(res.program_vars, HashMap::from([(String::from("x"), 1.0)]));
+
+    // a cancel before the run isn't lost, the token stays cancelled until
+    // it's reset
+    let ast = parser.parse("BEGIN x := 1; Sleep(30); Sleep(30); y := 2 END.").unwrap();
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.status, Err(InterpreterErr::Cancelled));
+    assert!(res.program_vars.is_empty());
+    token.reset();
+    assert!(interpreter.interpret(&ast).is_ok());
+
+    token.cancel();
+    assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::Cancelled));
+    token.reset();
+
+    // a timeout stops only its own run
+    interpreter.set_timeout(Some(Duration::from_millis(10)));
+    let res = interpreter.execute(&ast);
+    assert_eq!(res.status, Err(InterpreterErr::Cancelled));
+    assert!(!token.is_cancelled());
+    interpreter.set_timeout(None);
+
+    // from another thread
+    let token = interpreter.cancel_token();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        token.cancel();
+    });
+    assert_eq!(interpreter.interpret(&ast), Err(InterpreterErr::Cancelled));
+    canceller.join().unwrap();
+}
+
+#[test]
+fn limits() {
+    use simple_pascal_ast::parser::DEFAULT_MAX_DEPTH;
+    use simple_pascal_interpreter::{interpreter::InterpreterErr, limits::Limits};
+
+    // a parser with a higher limit accepts more than a run may nest
+    let minuses = format!("BEGIN x := {}1 END.", "-".repeat(300));
+    let mut parser = Parser::new();
+    parser.set_max_depth(1000);
+    let deep = parser.parse(&minuses).unwrap();
+    let shallow = Parser::new().parse("BEGIN x := 1 + 1 + 1; BEGIN y := 2 END END.").unwrap();
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.limits().max_depth, Some(DEFAULT_MAX_DEPTH));
+    assert_eq!(interpreter.interpret(&deep), Err(InterpreterErr::DepthExceeded));
+
+    // chains of operators don't nest, unlike their right operands and
+    // inner blocks
+    let sum = format!("BEGIN x := 1{} END.", " + 1".repeat(999));
+    assert!(interpreter.interpret(&Parser::new().parse(&sum).unwrap()).is_ok());
+    interpreter.set_limits(Limits { max_depth: Some(2), max_memory: None });
+    assert!(interpreter.interpret(&shallow).is_ok());
+    interpreter.set_limits(Limits { max_depth: Some(1), max_memory: None });
+    assert_eq!(interpreter.interpret(&shallow), Err(InterpreterErr::DepthExceeded));
+
+    // `x` and `y` take a name byte and an f64 each
+    interpreter.set_limits(Limits { max_depth: None, max_memory: Some(18) });
+    assert!(interpreter.interpret(&shallow).is_ok());
+    interpreter.set_limits(Limits { max_depth: None, max_memory: Some(17) });
+    let res = interpreter.execute(&shallow);
+    assert_eq!(res.status, Err(InterpreterErr::MemoryExceeded));
+    assert_eq!(res.program_vars, HashMap::from([(String::from("x"), 3.0)]));
+}
+
+#[test]
+fn snapshots() {
+    use simple_pascal_interpreter::{interpreter::InterpreterErr, snapshot::SnapshotErr};
+
+    let source = "BEGIN x := 2; BEGIN y := x * Twice(x + 1); z := -y END; w := x + 1 END.";
+    let ast = Parser::new().parse(source).unwrap();
+    let new_interpreter = || {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("Twice", |x: f64| 2.0 * x);
+        interpreter.set_trace(true);
+        interpreter
+    };
+    let expected = new_interpreter().execute(&ast);
+    assert!(expected.is_ok());
+
+    // stop after every possible step, move the run to a new interpreter
+    // and finish it there
+    for steps in 0.. {
+        let mut interpreter = new_interpreter();
+        interpreter.set_fuel(Some(steps));
+        let res = interpreter.execute(&ast);
+        if res.is_ok() {
+            assert!(steps > 0);
+            assert!(!interpreter.is_suspended());
+            assert_eq!(interpreter.snapshot(), None);
+            break;
+        }
+        assert_eq!(res.status, Err(InterpreterErr::BudgetExhausted));
+
+        let snapshot = interpreter.snapshot().unwrap();
+        let mut restored = new_interpreter();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.snapshot(), Some(snapshot));
+
+        let res = restored.resume(&ast);
+        assert_eq!(res.status, Ok(()));
+        assert_eq!(res.program_vars, expected.program_vars);
+        assert_eq!(res.trace, expected.trace);
+        assert!(!restored.is_suspended());
+        assert_eq!(restored.resume(&ast).status, Err(InterpreterErr::InvalidResume));
+    }
+
+    // a cancelled run resumes in place, but only with its own program
+    let mut interpreter = new_interpreter();
+    let token = interpreter.cancel_token();
+    interpreter.register_fn("Stop", move || token.cancel());
+    let ast = Parser::new().parse("BEGIN x := 1; Stop(); y := x + 1 END.").unwrap();
+    assert_eq!(interpreter.execute(&ast).status, Err(InterpreterErr::Cancelled));
+
+    let other = Parser::new().parse("BEGIN x := 1; Stop(); y := x + 2 END.").unwrap();
+    assert_eq!(interpreter.resume(&other).status, Err(InterpreterErr::InvalidResume));
+    assert!(interpreter.is_suspended());
+    // still cancelled, so nothing runs
+    assert_eq!(interpreter.resume(&ast).status, Err(InterpreterErr::Cancelled));
+    interpreter.cancel_token().reset();
+    let res = interpreter.resume(&ast);
+    assert!(res.is_ok());
+    assert_eq!(
+        res.program_vars,
+        HashMap::from([(String::from("x"), 1.0), (String::from("y"), 2.0)])
+    );
+
+    assert_eq!(interpreter.restore(b"PCODE\x01\x00\x00"), Err(SnapshotErr::BadMagic));
+    assert_eq!(
+        interpreter.restore(b"SPISNAP\x01\x00"),
+        Err(SnapshotErr::Corrupt(String::from("unexpected end of snapshot")))
+    );
+}
+
+#[test]
+fn debugger() {
+    use simple_pascal_interpreter::{
+        debug::{Step, Stop},
+        interpreter::InterpreterErr,
+    };
+
+    let mut parser = Parser::new();
+    let ast = parser.parse(r"
+BEGIN
+    y := 2;
+    BEGIN
+        a := 3; b := a
+    END;
+    ;
+    x := y * 2
+END.").unwrap();
+    let lines = parser.statement_lines().to_vec();
+    let mut interpreter = Interpreter::new();
+
+    // into nested blocks, over them and out of them
+    assert_eq!(interpreter.debug(&ast, &lines), Ok(Stop::Step(2)));
+    assert_eq!(interpreter.step(&ast, Step::Into), Ok(Stop::Step(3)));
+    assert_eq!(interpreter.step(&ast, Step::Over), Ok(Stop::Step(4)));
+    assert_eq!(interpreter.step(&ast, Step::Into), Ok(Stop::Step(5)));
+    assert_eq!(interpreter.step(&ast, Step::Into), Ok(Stop::Step(5)));
+    assert_eq!(interpreter.stop(), Some(Stop::Step(5)));
+
+    let scopes: Vec<_> = interpreter.scopes().cloned().collect();
+    assert_eq!(
+        scopes,
+        [
+            HashMap::from([(String::from("a"), 3.0)]),
+            HashMap::from([(String::from("y"), 2.0)]),
+            HashMap::new(),
+        ]
+    );
+    let watch = Parser::calculator().parse_statements("a * y").unwrap();
+    assert_eq!(interpreter.watch(&watch[0]), Ok(6.0));
+
+    // the empty statement on line 7 doesn't stop
+    assert_eq!(interpreter.step(&ast, Step::Out), Ok(Stop::Step(8)));
+    assert_eq!(interpreter.step(&ast, Step::Over), Ok(Stop::Finished));
+    assert_eq!(interpreter.stop(), None);
+    assert_eq!(interpreter.step(&ast, Step::Into), Err(InterpreterErr::InvalidResume));
+
+    // breakpoints, with conditions on the variables at that point; one
+    // that fails, as `a` does before its assignment, stops too
+    let condition = |text| Parser::calculator().parse_statements(text).unwrap().pop();
+    interpreter.set_breakpoint(5, condition("a"));
+    interpreter.set_breakpoint(8, None);
+    assert_eq!(interpreter.debug(&ast, &lines), Ok(Stop::Step(2)));
+    assert_eq!(interpreter.step(&ast, Step::Continue), Ok(Stop::Breakpoint(5)));
+    assert_eq!(interpreter.step(&ast, Step::Continue), Ok(Stop::Breakpoint(5)));
+    assert_eq!(interpreter.step(&ast, Step::Continue), Ok(Stop::Breakpoint(8)));
+
+    interpreter.set_breakpoint(5, condition("y - 2"));
+    assert_eq!(interpreter.breakpoints().len(), 2);
+    assert_eq!(interpreter.debug(&ast, &lines), Ok(Stop::Step(2)));
+    assert_eq!(interpreter.step(&ast, Step::Continue), Ok(Stop::Breakpoint(8)));
+    assert!(interpreter.clear_breakpoint(5));
+    assert!(!interpreter.clear_breakpoint(5));
+    let res = interpreter.resume(&ast);
+    assert!(res.is_ok());
+    assert_eq!(res.program_vars["x"], 4.0);
+}
@@ -0,0 +1,172 @@
+use simple_pascal_ast::{dialect::Dialect, parser::Parser};
+use simple_pascal_interpreter::{
+    bytecode::{compile, compile_with_lines, Op, Program},
+    disasm::disassemble,
+    pcode::{PcodeErr, MAGIC, VERSION},
+    Vm,
+};
+
+const SOURCE: &str = r"BEGIN
+    y := 2;
+    BEGIN
+        a := -3.5; b := a + y * 2
+    END;
+    ;
+    x := y MOD 0
+END.";
+
+fn compiled(source: &str) -> Program {
+    let mut parser = Parser::new();
+    let ast = parser.parse(source).unwrap();
+    compile_with_lines(&ast, parser.statement_lines()).unwrap()
+}
+
+#[test]
+fn round_trip() {
+    let program = compiled(SOURCE);
+    let bytes = program.to_bytes();
+
+    assert_eq!(&bytes[..5], MAGIC);
+    assert_eq!(bytes[5..7], VERSION.to_le_bytes());
+
+    let loaded = Program::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, program);
+    assert_eq!(Vm::new().run(&loaded), Vm::new().run(&program));
+
+    let path = std::env::temp_dir().join(format!("spi-round-trip-{}.pcode", std::process::id()));
+    program.save(&path).unwrap();
+    let loaded = Program::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), program);
+
+    // strings are saved with the code that prints them
+    let program = compiled("BEGIN Write('x = ', 1); WriteLn('\u{e9}') END.");
+    let loaded = Program::from_bytes(&program.to_bytes()).unwrap();
+    assert_eq!(loaded.string(1), "\u{e9}");
+    assert_eq!(loaded, program);
+
+    // the dialect follows the header
+    let mut turbo = program.clone();
+    turbo.set_dialect(Dialect::TurboPascal);
+    let bytes = turbo.to_bytes();
+    assert_eq!(bytes[7], 1);
+    assert_eq!(Program::from_bytes(&bytes).unwrap().dialect(), Dialect::TurboPascal);
+}
+
+#[test]
+fn reject_bad_files() {
+    let bytes = compile(&Parser::new().parse("BEGIN x := 1; y := x END.").unwrap()).unwrap().to_bytes();
+
+    assert!(matches!(Program::from_bytes(b"MZ\x90\x00\x03\x00\x00"), Err(PcodeErr::BadMagic)));
+
+    let mut newer = bytes.clone();
+    newer[5..7].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        Program::from_bytes(&newer),
+        Err(PcodeErr::UnsupportedVersion(version)) if version == VERSION + 1
+    ));
+
+    for len in 0..bytes.len() {
+        assert!(matches!(Program::from_bytes(&bytes[..len]), Err(PcodeErr::Corrupt(_) | PcodeErr::BadMagic)));
+    }
+
+    // the dialect and `slots` come right after the header
+    let mut bad_dialect = bytes.clone();
+    bad_dialect[7] = 3;
+    assert!(matches!(Program::from_bytes(&bad_dialect), Err(PcodeErr::Corrupt(_))));
+
+    let mut fewer_slots = bytes.clone();
+    fewer_slots[8..12].copy_from_slice(&1u32.to_le_bytes());
+    assert!(matches!(Program::from_bytes(&fewer_slots), Err(PcodeErr::Corrupt(_))));
+
+    // a huge slot count would make the VM allocate gigabytes
+    let mut more_slots = bytes.clone();
+    more_slots[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(Program::from_bytes(&more_slots), Err(PcodeErr::Corrupt(_))));
+
+    // also when an op uses the highest slot but one
+    let mut hostile = Vec::new();
+    hostile.extend_from_slice(MAGIC);
+    hostile.extend_from_slice(&VERSION.to_le_bytes());
+    hostile.push(0);
+    hostile.extend_from_slice(&u32::MAX.to_le_bytes());
+    hostile.extend_from_slice(&[0; 12]);
+    hostile.extend_from_slice(&1u32.to_le_bytes());
+    hostile.push(1);
+    hostile.extend_from_slice(&(u32::MAX - 1).to_le_bytes());
+    assert!(matches!(Program::from_bytes(&hostile), Err(PcodeErr::Corrupt(_))));
+
+    // while the same file with the slots it names loads
+    hostile[8..12].copy_from_slice(&1u32.to_le_bytes());
+    let len = hostile.len();
+    hostile[len - 4..].copy_from_slice(&0u32.to_le_bytes());
+    assert_eq!(Program::from_bytes(&hostile).unwrap().code(), &[Op::Load(0)]);
+
+    // and there's no string for `WriteStr` to print
+    let len = hostile.len();
+    hostile[len - 5..].copy_from_slice(&[19, 0, 0, 0, 0]);
+    hostile[8..12].copy_from_slice(&0u32.to_le_bytes());
+    assert!(matches!(Program::from_bytes(&hostile), Err(PcodeErr::Corrupt(_))));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(Program::from_bytes(&trailing), Err(PcodeErr::Corrupt(_))));
+}
+
+#[test]
+fn disassemble_with_source() {
+    let program = compiled(SOURCE);
+
+    assert_eq!(
+        disassemble(&program, Some(SOURCE)),
+        r"; 4 slots, 2 scopes
+      ; 2 | y := 2;
+0000  CONST      2
+0001  STORE      0  ; y
+      ; 4 | a := -3.5; b := a + y * 2
+0002  CONST      3.5
+0003  NEG
+0004  STORE      1  ; a
+0005  LOAD       1  ; a
+0006  LOAD       0  ; y
+0007  CONST      2
+0008  IMUL
+0009  ADD
+0010  STORE      2  ; b
+0011  EXIT_SCOPE 1  ; a, b
+      ; 7 | x := y MOD 0
+0012  LOAD       0  ; y
+0013  CONST      0
+0014  MOD
+0015  STORE      3  ; x
+0016  EXIT_SCOPE 0  ; y, x
+"
+    );
+
+    let program = compile(&Parser::new().parse("BEGIN x := q END.").unwrap()).unwrap();
+    assert_eq!(
+        disassemble(&program, None),
+        r"; 1 slot, 1 scope
+0000  TRAP       UndefinedIdent
+0001  STORE      0  ; x
+0002  EXIT_SCOPE 0  ; x
+"
+    );
+    assert_eq!(compiled("BEGIN\n x := 1\nEND.").line_at(0), Some(2));
+
+    let program = compiled("BEGIN ReadLn(x); Write('it''s ', x); WriteLn() END.");
+    assert_eq!(
+        disassemble(&program, None),
+        r"; 1 slot, 1 scope
+      ; line 1
+0000  READ_LINE
+0001  READ
+0002  STORE      0  ; x
+0003  WRITE_STR  0  ; 'it''s '
+0004  LOAD       0  ; x
+0005  WRITE
+0006  WRITE_LN
+0007  EXIT_SCOPE 0  ; x
+"
+    );
+}
@@ -0,0 +1,180 @@
+use simple_pascal_ast::{dialect::Dialect, parser::Parser};
+use simple_pascal_interpreter::{
+    bytecode::{compile, compile_with_lines, CompileErr, Op},
+    interpreter::InterpreterErr,
+    Interpreter, Vm,
+};
+
+const PROGRAMS: &[&str] = &[
+    "BEGIN END.",
+    "BEGIN ;-2; END.",
+    r"BEGIN
+        x:= 2 + 3 * (2 + 3);
+        y:= 2 / 2 - 2 + 3 * ((1 + 1) + (1 + 1));
+    END.",
+    r"BEGIN
+        y := 2;
+        BEGIN
+            a := 3;
+            a := a;
+            b := 10 + a + 10 * y / 4;
+            c := a - b
+        END;
+        x := 11;
+    END.",
+    r"BEGIN
+        z := 3;
+        ;y :=5;
+        ;;;
+        BEGIN
+            c := 2;
+            BEGIN
+                z := c;
+            END;
+            z := y;
+        END;
+        BEGIN
+            a := 4;
+            y := a + z;
+        END;
+        ;4 - y;
+    END.",
+    // an inner block defines `x` first, the outer one gets its own later
+    "BEGIN BEGIN x := 1 END; x := 2; BEGIN x := x + 1 END END.",
+    "BEGIN a := 7 DIV 2; b := -7 DIV 2; c := -7 MOD 3; d := 7 % -3 END.",
+    "BEGIN x := 2 ^ 3 ^ 2; y := -2 ^ 2 END.",
+    "BEGIN z := 1; w := q END.",
+    "BEGIN x := 1; y := x MOD 0 END.",
+    "BEGIN x := 1.5 DIV 1 END.",
+    "BEGIN x := 2147483648 MOD 2 END.",
+    "BEGIN x := 2147483647 + 1; y := 2147483647.5 + 1 END.",
+    "BEGIN x := 65536 * 65536 - 1 END.",
+    "BEGIN x := 1 / 0; y := -x END.",
+    // REAL operands are never range checked, whatever their values
+    "BEGIN x := 65536.0 * 65536.0 END.",
+    "BEGIN x := 4 / 2 * 2147483647 END.",
+];
+
+// programs using the standard procedures, with their input
+const IO_PROGRAMS: &[(&str, &str)] = &[
+    ("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x) END.", "3.5 4 extra\n"),
+    ("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x) END.", "1\n"),
+    ("BEGIN ReadLn(); ReadLn(x); WriteLn(x / 3, ' ', -x, ' ', x * 1000000000000000000000.0, ' ', x / 1000000000) END.", "skipped\n2\n"),
+    ("BEGIN WriteLn('it''s \"quoted\" \\ ??= caf\u{e9}') END.", ""),
+    ("BEGIN ReadLn(x); WriteLn(x) END.", "0x10\n"),
+    ("BEGIN BEGIN ReadLn(x) END; WriteLn(x) END.", "1\n"),
+    ("BEGIN ReadLn(x); x := x + 1; WriteLn(x) END.", "2147483647\n"),
+];
+
+// `input` one line at a time, for `set_input`
+fn lines(input: &str) -> impl FnMut() -> Option<String> {
+    let mut lines = input.lines().map(String::from).collect::<Vec<_>>().into_iter();
+    move || lines.next()
+}
+
+#[test]
+fn matches_interpreter() {
+    let mut parser = Parser::calculator();
+
+    for dialect in [Dialect::Iso7185, Dialect::TurboPascal, Dialect::FreePascal] {
+        parser.set_dialect(dialect);
+        let mut interpreter = Interpreter::with_dialect(dialect);
+        let mut vm = Vm::with_dialect(dialect);
+
+        for source in PROGRAMS {
+            let ast = match parser.parse(source) {
+                Ok(ast) => ast,
+                // e.g. integer literals out of range
+                Err(_) => continue,
+            };
+            let program = compile(&ast).unwrap();
+
+            assert_eq!(
+                vm.run(&program),
+                interpreter.interpret(&ast),
+                "running `{}` in {}",
+                source,
+                dialect
+            );
+        }
+    }
+}
+
+#[test]
+fn standard_procedures() {
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+    let mut vm = Vm::new();
+
+    for (source, input) in IO_PROGRAMS {
+        let ast = parser.parse(source).unwrap();
+        interpreter.set_input(lines(input));
+        let res = interpreter.execute(&ast);
+        vm.set_input(lines(input));
+        let status = vm.run(&compile(&ast).unwrap()).map(|_| ());
+
+        assert_eq!((status, vm.output()), (res.status, res.output.as_str()), "running `{}`", source);
+    }
+}
+
+#[test]
+fn resolved_slots() {
+    let ast = Parser::new()
+        .parse("BEGIN x := 1; BEGIN y := x + 2; x := y END END.")
+        .unwrap();
+    let program = compile(&ast).unwrap();
+
+    assert_eq!(
+        program.code(),
+        [
+            Op::Const(1.0),
+            Op::Store(0),
+            Op::Load(0),
+            Op::Const(2.0),
+            Op::IntAdd,
+            Op::Store(1),
+            Op::Load(1),
+            Op::Store(0),
+            Op::ExitScope(1),
+            Op::ExitScope(0),
+        ]
+    );
+    assert_eq!(program.slots(), 2);
+    assert_eq!(program.scope(0), [(String::from("x"), 0)]);
+    assert_eq!(program.scope(1), [(String::from("y"), 1)]);
+}
+
+#[test]
+fn traps() {
+    let ast = Parser::new().parse("BEGIN x := 1; y := z + x END.").unwrap();
+    let program = compile(&ast).unwrap();
+
+    assert_eq!(
+        program.code(),
+        [
+            Op::Const(1.0),
+            Op::Store(0),
+            Op::Trap(InterpreterErr::UndefinedIdent),
+            Op::Load(0),
+            Op::Add,
+            Op::Store(1),
+            Op::ExitScope(0),
+        ]
+    );
+    assert_eq!(Vm::new().run(&program), Err(InterpreterErr::UndefinedIdent));
+
+    // compiled programs have no host functions to call
+    let mut parser = Parser::new();
+    let ast = parser.parse("BEGIN\n    x := 1;\n    y := Max(x, 2)\nEND.").unwrap();
+    assert_eq!(
+        compile_with_lines(&ast, parser.statement_lines()),
+        Err(CompileErr::Call { function: String::from("Max"), line: Some(3) })
+    );
+
+    // a program can run any number of times
+    let program = compile(&Parser::new().parse("BEGIN x := 1; x := x + 1 END.").unwrap()).unwrap();
+    let mut vm = Vm::new();
+    for _ in 0..3 {
+        assert_eq!(vm.run(&program).unwrap().front().unwrap()["x"], 2.0);
+    }
+}
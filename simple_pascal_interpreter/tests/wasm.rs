@@ -0,0 +1,228 @@
+#![cfg(feature = "wasm")]
+
+use std::collections::{HashMap, LinkedList};
+
+use simple_pascal_ast::{dialect::Dialect, parser::Parser};
+use simple_pascal_interpreter::{
+    bytecode::compile,
+    interpreter::InterpreterErr,
+    pcode::TRAPS,
+    standard::{format_number, parse_number},
+    wasm::{to_wasm, to_wat},
+    Interpreter,
+};
+use wasmi::{core::F64, Caller, Engine, Extern, Linker, Module, Store};
+
+const PROGRAMS: &[&str] = &[
+    "BEGIN END.",
+    "BEGIN ;-2; END.",
+    r"BEGIN
+        x:= 2 + 3 * (2 + 3);
+        y:= 2 / 2 - 2 + 3 * ((1 + 1) + (1 + 1));
+    END.",
+    r"BEGIN
+        y := 2;
+        BEGIN
+            a := 3;
+            a := a;
+            b := 10 + a + 10 * y / 4;
+            c := a - b
+        END;
+        x := 11;
+    END.",
+    "BEGIN BEGIN x := 1 END; x := 2; BEGIN x := x + 1 END END.",
+    "BEGIN a := 7 DIV 2; b := -7 DIV 2; c := -7 MOD 3; d := 7 % -3; e := -8 MOD 4 END.",
+    "BEGIN x := 2 ^ 3 ^ 2; y := - -2 ^ 2; z := 0.1 + 0.2 END.",
+    "BEGIN x := 9223372036854775807 MOD 3; y := -9007199254740993 MOD 10 END.",
+    "BEGIN z := 1; w := q END.",
+    "BEGIN x := 1; y := x MOD 0 END.",
+    "BEGIN x := 1.5 DIV 1 END.",
+    "BEGIN x := 2147483648 MOD 2 END.",
+    "BEGIN x := 2147483647 + 1; y := 2147483647.5 + 1 END.",
+    "BEGIN x := 65536 * 65536 - 1 END.",
+    "BEGIN x := 1 / 0; y := -x END.",
+    // REAL operands are never range checked, whatever their values
+    "BEGIN x := 65536.0 * 65536.0 END.",
+    "BEGIN x := 4 / 2 * 2147483647 END.",
+];
+
+// programs using the standard procedures, with their input
+const IO_PROGRAMS: &[(&str, &str)] = &[
+    ("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x) END.", "3.5 4 extra\n"),
+    ("BEGIN Write('sum? '); ReadLn(a, b); x := a + b; WriteLn(a, ' + ', b, ' = ', x) END.", "1\n"),
+    ("BEGIN ReadLn(); ReadLn(x); WriteLn(x / 3, ' ', -x) END.", "skipped\n2\n"),
+    ("BEGIN WriteLn('it''s caf\u{e9}') END.", ""),
+    ("BEGIN ReadLn(x); WriteLn(x) END.", "nan\n"),
+    ("BEGIN BEGIN ReadLn(x) END; WriteLn(x) END.", "1\n"),
+];
+
+#[derive(Default)]
+struct Host {
+    vars: HashMap<String, f64>,
+    blocks: LinkedList<HashMap<String, f64>>,
+    error: Option<InterpreterErr>,
+    output: String,
+    // lines left to read and the numbers of the one read, both last first
+    input: Vec<String>,
+    numbers: Vec<String>,
+}
+
+type Output = Result<LinkedList<HashMap<String, f64>>, InterpreterErr>;
+
+fn run_wasm(bytes: &[u8]) -> Output {
+    run_wasm_with_input(bytes, "").0
+}
+
+// also returns what the module wrote
+fn run_wasm_with_input(bytes: &[u8], input: &str) -> (Output, String) {
+    let engine = Engine::default();
+    let module = Module::new(&engine, bytes).unwrap();
+    let host = Host {
+        input: input.lines().rev().map(String::from).collect(),
+        ..Default::default()
+    };
+    let mut store = Store::new(&engine, host);
+    let mut linker = Linker::<Host>::new(&engine);
+
+    linker
+        .func_wrap("spi", "var", |mut caller: Caller<'_, Host>, name: i32, len: i32, value: F64| {
+            let memory = caller.get_export("memory").and_then(Extern::into_memory).unwrap();
+            let name = &memory.data(&caller)[name as usize..(name + len) as usize];
+            let name = String::from_utf8(name.to_vec()).unwrap();
+            caller.data_mut().vars.insert(name, value.to_float());
+        })
+        .unwrap();
+    linker
+        .func_wrap("spi", "end_scope", |mut caller: Caller<'_, Host>| {
+            let host = caller.data_mut();
+            let vars = std::mem::take(&mut host.vars);
+            host.blocks.push_back(vars);
+        })
+        .unwrap();
+    linker
+        .func_wrap("spi", "error", |mut caller: Caller<'_, Host>, code: i32| {
+            caller.data_mut().error = Some(TRAPS[code as usize]);
+        })
+        .unwrap();
+    linker
+        .func_wrap("spi", "pow", |lhs: F64, rhs: F64| F64::from(lhs.to_float().powf(rhs.to_float())))
+        .unwrap();
+
+    linker
+        .func_wrap("spi", "write", |mut caller: Caller<'_, Host>, value: F64| {
+            caller.data_mut().output.push_str(&format_number(value.to_float()));
+        })
+        .unwrap();
+    linker
+        .func_wrap("spi", "write_str", |mut caller: Caller<'_, Host>, text: i32, len: i32| {
+            let memory = caller.get_export("memory").and_then(Extern::into_memory).unwrap();
+            let text = &memory.data(&caller)[text as usize..(text + len) as usize];
+            let text = String::from_utf8(text.to_vec()).unwrap();
+            caller.data_mut().output.push_str(&text);
+        })
+        .unwrap();
+    linker
+        .func_wrap("spi", "write_ln", |mut caller: Caller<'_, Host>| caller.data_mut().output.push('\n'))
+        .unwrap();
+    linker
+        .func_wrap("spi", "read_line", |mut caller: Caller<'_, Host>| {
+            let host = caller.data_mut();
+            match host.input.pop() {
+                Some(line) => {
+                    host.numbers = line.split_whitespace().rev().map(String::from).collect();
+                    1
+                },
+                None => 0,
+            }
+        })
+        .unwrap();
+    linker
+        .func_wrap("spi", "read", |mut caller: Caller<'_, Host>| {
+            let number = caller.data_mut().numbers.pop();
+            F64::from(number.map_or(f64::NAN, |number| parse_number(&number).unwrap_or(f64::NAN)))
+        })
+        .unwrap();
+
+    let instance = linker.instantiate(&mut store, &module).unwrap().start(&mut store).unwrap();
+    let main = instance.get_typed_func::<(), ()>(&store, "main").unwrap();
+
+    let res = match main.call(&mut store, ()) {
+        Ok(()) => Ok(std::mem::take(&mut store.data_mut().blocks)),
+        // errors trap right after reporting themselves
+        Err(_) => Err(store.data().error.expect("trap without an error")),
+    };
+    (res, std::mem::take(&mut store.data_mut().output))
+}
+
+#[test]
+fn matches_interpreter() {
+    let mut parser = Parser::calculator();
+
+    for dialect in [Dialect::Iso7185, Dialect::TurboPascal, Dialect::FreePascal] {
+        parser.set_dialect(dialect);
+        let mut interpreter = Interpreter::with_dialect(dialect);
+
+        for source in PROGRAMS {
+            let ast = match parser.parse(source) {
+                Ok(ast) => ast,
+                Err(_) => continue,
+            };
+
+            assert_eq!(
+                run_wasm(&to_wasm(&compile(&ast).unwrap(), dialect)),
+                interpreter.interpret(&ast),
+                "running `{}` in {}",
+                source,
+                dialect
+            );
+        }
+    }
+}
+
+#[test]
+fn standard_procedures() {
+    let mut parser = Parser::new();
+    let mut interpreter = Interpreter::new();
+
+    for (source, input) in IO_PROGRAMS {
+        let ast = parser.parse(source).unwrap();
+        let mut lines = input.lines().map(String::from).collect::<Vec<_>>().into_iter();
+        interpreter.set_input(move || lines.next());
+        let res = interpreter.execute(&ast);
+
+        let (status, output) = run_wasm_with_input(&to_wasm(&compile(&ast).unwrap(), Dialect::Iso7185), input);
+        assert_eq!((status.map(|_| ()), output), (res.status, res.output), "running `{}`", source);
+    }
+}
+
+#[test]
+fn long_names() {
+    // the names take more than one 64 KiB page of memory
+    let names: Vec<String> = (0..3).map(|idx| format!("v{}{}", idx, "x".repeat(40_000))).collect();
+    let source = format!("BEGIN {} := 1; {} := 2; {} := 3 END.", names[0], names[1], names[2]);
+    let ast = Parser::new().parse(&source).unwrap();
+
+    assert!(to_wat(&compile(&ast).unwrap(), Dialect::Iso7185).contains("(memory (export \"memory\") 2)"));
+    assert_eq!(
+        run_wasm(&to_wasm(&compile(&ast).unwrap(), Dialect::Iso7185)),
+        Interpreter::new().interpret(&ast)
+    );
+}
+
+#[test]
+fn text_format() {
+    let ast = Parser::new().parse("BEGIN x := 1; y := -x DIV 2 END.").unwrap();
+    let wat = to_wat(&compile(&ast).unwrap(), Dialect::TurboPascal);
+
+    assert!(wat.contains("  (data (i32.const 0) \"xy\")\n"));
+    assert!(wat.contains("(f64.const 2147483647.0)"));
+    assert!(wat.contains(
+        "    f64.const 1.0\n    local.set $v0\n    local.get $v0\n    f64.neg\n    f64.const 2.0\n    call $div\n    local.set $v1\n"
+    ));
+    assert!(wat.contains(
+        "    i32.const 0\n    i32.const 1\n    local.get $v0\n    call $var\n    \
+         i32.const 1\n    i32.const 1\n    local.get $v1\n    call $var\n    call $end_scope\n"
+    ));
+    // only ISO 7185 checks the sign of the divisor
+    assert!(!wat.contains("(f64.lt (local.get $rhs)"));
+}
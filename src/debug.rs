@@ -0,0 +1,276 @@
+//! `spi debug`: runs a program under `Interpreter::debug`, stopping before
+//! its first statement and taking commands, see `HELP`, whenever it stops.
+
+use std::process;
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+use simple_pascal_ast::{dialect::Dialect, node::Node, parser::Parser};
+use simple_pascal_interpreter::{
+    debug::{Step, Stop},
+    interpreter::InterpreterErr,
+    Interpreter,
+};
+
+use crate::{format_vars, read_stdin, write_stdout};
+
+const HELP: &str = "break LINE [if EXPR]  stop before the statements on LINE, or only when EXPR isn't 0 (b)
+delete LINE           remove the breakpoint on LINE
+breakpoints           list the breakpoints
+step                  run to the next statement (s)
+next                  run to the next statement, stepping over nested blocks (n)
+finish                run until the current block ends
+continue              run to the next breakpoint (c)
+print EXPR            print the value of EXPR (p)
+watch EXPR            print the value of EXPR at every stop
+unwatch N             remove watch expression N
+vars                  print the variables of every open block, innermost first
+list                  print the source around the current line (l)
+run                   start the program over
+help                  print this message
+quit                  leave the debugger (q)";
+
+pub fn run(name: &str, text: &str, dialect: Dialect, ast: Node, lines: Vec<u32>) -> i32 {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+    let mut debugger = Debugger {
+        name,
+        source: text.lines().collect(),
+        dialect,
+        ast,
+        lines,
+        interpreter: Interpreter::with_dialect(dialect),
+        watches: Vec::new(),
+    };
+    debugger.interpreter.set_output(write_stdout);
+    debugger.interpreter.set_input(read_stdin);
+    debugger.start();
+
+    loop {
+        let line = match editor.readline("(spi) ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return 0,
+            Err(err) => {
+                eprintln!("{}", err);
+                return 2;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(line.trim());
+        debugger.command(line.trim());
+    }
+}
+
+struct Debugger<'a> {
+    name: &'a str,
+    source: Vec<&'a str>,
+    dialect: Dialect,
+    ast: Node,
+    // `Parser::statement_lines` of `ast`
+    lines: Vec<u32>,
+    interpreter: Interpreter,
+    watches: Vec<(String, Node)>,
+}
+
+impl Debugger<'_> {
+    fn start(&mut self) {
+        let res = self.interpreter.debug(&self.ast, &self.lines);
+        self.report(res);
+    }
+
+    fn command(&mut self, line: &str) {
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+
+        match (command, arg) {
+            ("break" | "b", arg) if !arg.is_empty() => self.set_breakpoint(arg),
+            ("delete", arg) => match arg.parse() {
+                Ok(line) if self.interpreter.clear_breakpoint(line) => (),
+                Ok(line) => eprintln!("no breakpoint on line {}", line),
+                Err(_) => eprintln!("usage: delete LINE"),
+            },
+            ("breakpoints", "") => {
+                for breakpoint in self.interpreter.breakpoints() {
+                    match &breakpoint.condition {
+                        Some(condition) => println!("line {} if {}", breakpoint.line, condition.to_sexpr()),
+                        None => println!("line {}", breakpoint.line),
+                    }
+                }
+            }
+            ("step" | "s", "") => self.step(Step::Into),
+            ("next" | "n", "") => self.step(Step::Over),
+            ("finish", "") => self.step(Step::Out),
+            ("continue" | "c", "") => self.step(Step::Continue),
+            ("print" | "p", expr) if !expr.is_empty() => {
+                if let Some(expr) = self.parse_expr(expr) {
+                    match self.interpreter.watch(&expr) {
+                        Ok(value) => println!("{}", value),
+                        Err(err) => eprintln!("runtime error: {}", self.runtime_error(err)),
+                    }
+                }
+            }
+            ("watch", expr) if !expr.is_empty() => {
+                if let Some(node) = self.parse_expr(expr) {
+                    self.watches.push((expr.to_string(), node));
+                    self.print_watches();
+                }
+            }
+            ("unwatch", arg) => match arg.parse::<usize>() {
+                Ok(idx) if (1..=self.watches.len()).contains(&idx) => {
+                    self.watches.remove(idx - 1);
+                }
+                _ => eprintln!("usage: unwatch N, with N from 1 to {}", self.watches.len()),
+            },
+            ("vars", "") => self.print_scopes(),
+            ("list" | "l", "") => self.list(),
+            ("run", "") => self.start(),
+            ("help", "") => println!("{}", HELP),
+            ("quit" | "q", "") => process::exit(0),
+            _ => eprintln!("unknown command `{}`, try help", line),
+        }
+    }
+
+    fn step(&mut self, step: Step) {
+        if !self.interpreter.is_suspended() {
+            eprintln!("the program isn't running, try run");
+            return;
+        }
+        let res = self.interpreter.step(&self.ast, step);
+        self.report(res);
+    }
+
+    // `LINE` or `LINE if EXPR`
+    fn set_breakpoint(&mut self, arg: &str) {
+        let (line, condition) = match arg.split_once(" if ") {
+            Some((line, condition)) => (line.trim(), Some(condition)),
+            None => (arg, None),
+        };
+        let line = match line.parse() {
+            Ok(line) if self.lines.contains(&line) => line,
+            Ok(line) => {
+                eprintln!("no statement on line {}", line);
+                return;
+            }
+            Err(_) => {
+                eprintln!("usage: break LINE [if EXPR]");
+                return;
+            }
+        };
+
+        match condition.map(|condition| self.parse_expr(condition)) {
+            Some(None) => (),
+            Some(condition) => self.interpreter.set_breakpoint(line, condition),
+            None => self.interpreter.set_breakpoint(line, None),
+        }
+    }
+
+    fn report(&mut self, res: Result<Stop, InterpreterErr>) {
+        match res {
+            Ok(Stop::Breakpoint(line)) => {
+                println!("breakpoint on line {}", line);
+                self.print_line(line);
+                self.print_watches();
+            }
+            Ok(Stop::Step(line)) => {
+                self.print_line(line);
+                self.print_watches();
+            }
+            Ok(Stop::Finished) => println!("program finished"),
+            Err(err) => {
+                let message = self.runtime_error(err);
+                let line = self.interpreter.native_error().and_then(|err| err.line);
+                match line.or(self.interpreter.line()) {
+                    Some(line) => eprintln!("{}:{}: runtime error: {}", self.name, line, message),
+                    None => eprintln!("{}: runtime error: {}", self.name, message),
+                }
+            }
+        }
+    }
+
+    fn print_line(&self, line: u32) {
+        let text = (line as usize)
+            .checked_sub(1)
+            .and_then(|idx| self.source.get(idx))
+            .copied()
+            .unwrap_or_default();
+        println!("{}: {}", line, text.trim());
+    }
+
+    fn print_watches(&mut self) {
+        for idx in 0..self.watches.len() {
+            let res = self.interpreter.watch(&self.watches[idx].1);
+            let (text, _) = &self.watches[idx];
+            match res {
+                Ok(value) => println!("watch {}: {} = {}", idx + 1, text, value),
+                Err(err) => println!("watch {}: {} = <{}>", idx + 1, text, err),
+            }
+        }
+    }
+
+    // the global frame is last, blocks are numbered from the outermost
+    fn print_scopes(&self) {
+        let scopes: Vec<_> = self.interpreter.scopes().collect();
+        for (depth, vars) in scopes.iter().enumerate() {
+            let vars = match vars.is_empty() {
+                true => String::from("-"),
+                false => format_vars(vars),
+            };
+            match scopes.len() - 1 - depth {
+                0 => println!("global: {}", vars),
+                block => println!("block {}: {}", block, vars),
+            }
+        }
+    }
+
+    // five lines either side of the current one, marked with `>`
+    fn list(&self) {
+        let Some(current) = self.interpreter.stop().and_then(|stop| stop.line()) else {
+            eprintln!("the program isn't running, try run");
+            return;
+        };
+
+        let first = current.saturating_sub(5).max(1);
+        for line in first..=current + 5 {
+            let Some(text) = self.source.get(line as usize - 1) else {
+                break;
+            };
+            let marker = if line == current { '>' } else { ' ' };
+            println!("{}{:>4} {}", marker, line, text);
+        }
+    }
+
+    fn parse_expr(&self, text: &str) -> Option<Node> {
+        let mut parser = Parser::calculator();
+        parser.set_dialect(self.dialect);
+
+        match parser.parse_statements(text) {
+            Ok(mut statements) if statements.len() == 1 => statements.pop(),
+            Ok(_) => {
+                eprintln!("expected a single expression");
+                None
+            }
+            Err(err) => {
+                eprintln!("syntax error: {}", err);
+                None
+            }
+        }
+    }
+
+    // calls carry the function and what went wrong
+    fn runtime_error(&self, err: InterpreterErr) -> String {
+        match self.interpreter.native_error() {
+            Some(err) => err.to_string(),
+            None => err.to_string(),
+        }
+    }
+}
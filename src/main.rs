@@ -1,23 +1,879 @@
-use simple_pascal_ast::parser::Parser;
-use simple_pascal_interpreter::Interpreter;
+mod debug;
+mod repl;
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Read, Write},
+    path::Path,
+    process,
+    time::Duration,
+};
+
+use simple_pascal_ast::{
+    const_fold::const_fold,
+    dialect::Dialect,
+    cst,
+    format::{format_tree, FormatOptions},
+    node::Node,
+    parser::Parser,
+};
+use simple_pascal_interpreter::{
+    bytecode::{compile_with_lines, Program},
+    cgen::to_c,
+    wasm::{to_wasm, to_wat},
+    disasm::disassemble,
+    limits::Limits,
+    Interpreter, Vm,
+};
+
+const USAGE: &str = "usage: spi <command> [ARGS]
+
+commands:
+    run        run a program
+    repl       run statements interactively
+    debug      run a program step by step
+    format     format programs
+    dump-ast   print the syntax tree of a program
+    compile    compile a program to a .pcode file
+    exec       run a .pcode file
+    disasm     print the bytecode of a program
+    to-c       translate a program to C
+    to-wasm    compile a program to a WebAssembly module
+
+exit codes: 0 success, 1 syntax error, 2 usage or I/O error, 3 run-time error";
+const RUN_USAGE: &str =
+    "usage: run [--dialect iso|turbo|fpc] [--calculator] [--dump-tokens] [--dump-ast] [--dump-vars] [--trace] [--time] [--fuel STEPS] [--timeout MS] [--max-depth N] [--max-operators N] [FILE|-]";
+const REPL_USAGE: &str = "usage: repl [--dialect iso|turbo|fpc]";
+const DEBUG_USAGE: &str = "usage: debug [--dialect iso|turbo|fpc] FILE";
+const FORMAT_USAGE: &str =
+    "usage: format [--check | --write] [--dialect iso|turbo|fpc] [FILE]...";
+const DUMP_AST_USAGE: &str =
+    "usage: dump-ast [--format debug|json|sexpr|dot] [--dialect iso|turbo|fpc] [--calculator] [--fold] [FILE]";
+const COMPILE_USAGE: &str =
+    "usage: compile [--dialect iso|turbo|fpc] [--calculator] [--fold] [-o OUT] FILE";
+const TO_C_USAGE: &str =
+    "usage: to-c [--dialect iso|turbo|fpc] [--calculator] [--fold] [-o OUT] FILE";
+const TO_WASM_USAGE: &str =
+    "usage: to-wasm [--dialect iso|turbo|fpc] [--calculator] [--fold] [--text] [-o OUT] FILE";
+const EXEC_USAGE: &str = "usage: exec FILE.pcode";
+const DISASM_USAGE: &str = "usage: disasm [--source FILE] FILE.pcode|FILE";
 
 fn main() {
-    // example
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("run") => process::exit(run_cmd(&args[1..])),
+        Some("repl") => process::exit(repl_cmd(&args[1..])),
+        Some("debug") => process::exit(debug_cmd(&args[1..])),
+        Some("format") => process::exit(format_cmd(&args[1..])),
+        Some("dump-ast") => process::exit(dump_ast_cmd(&args[1..])),
+        Some("compile") => process::exit(compile_cmd(&args[1..])),
+        Some("to-c") => process::exit(to_c_cmd(&args[1..])),
+        Some("to-wasm") => process::exit(to_wasm_cmd(&args[1..])),
+        Some("exec") => process::exit(exec_cmd(&args[1..])),
+        Some("disasm") => process::exit(disasm_cmd(&args[1..])),
+        Some("help" | "--help" | "-h") => println!("{}", USAGE),
+        Some(command) => {
+            eprintln!("unknown command `{}`\n{}", command, USAGE);
+            process::exit(2);
+        }
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+// Runs a program from a file or stdin (no FILE or `-`). The dump flags
+// print the tokens, the tree and the program's variables when it stops,
+// `--trace` the variables of every finished block before them and
+// `--time` how long the run took. `--fuel` stops the program after that
+// many statements and expressions, `--timeout` after that many
+// milliseconds. Exits with 1 on a syntax error and with
+// 3 on a run-time error.
+fn run_cmd(args: &[String]) -> i32 {
+    let mut parser = Parser::new();
+    let mut dump_tokens = false;
+    let mut dump_ast = false;
+    let mut dump_vars = false;
+    let mut trace = false;
+    let mut time = false;
+    let mut fuel = None;
+    let mut timeout = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => match dialect_flag(&mut args, RUN_USAGE) {
+                Ok(dialect) => parser.set_dialect(dialect),
+                Err(code) => return code,
+            },
+            "--fuel" => match args.next().map(|steps| steps.parse()) {
+                Some(Ok(steps)) => fuel = Some(steps),
+                Some(Err(_)) | None => {
+                    eprintln!("{}", RUN_USAGE);
+                    return 2;
+                }
+            },
+            "--timeout" => match args.next().map(|ms| ms.parse()) {
+                Some(Ok(ms)) => timeout = Some(Duration::from_millis(ms)),
+                Some(Err(_)) | None => {
+                    eprintln!("{}", RUN_USAGE);
+                    return 2;
+                }
+            },
+            "--max-depth" => match args.next().map(|depth| depth.parse()) {
+                Some(Ok(depth)) => parser.set_max_depth(depth),
+                Some(Err(_)) | None => {
+                    eprintln!("{}", RUN_USAGE);
+                    return 2;
+                }
+            },
+            "--max-operators" => match args.next().map(|operators| operators.parse()) {
+                Some(Ok(operators)) => parser.set_max_operators(operators),
+                Some(Err(_)) | None => {
+                    eprintln!("{}", RUN_USAGE);
+                    return 2;
+                }
+            },
+            "--calculator" => parser.set_calculator(true),
+            "--dump-tokens" => dump_tokens = true,
+            "--dump-ast" => dump_ast = true,
+            "--dump-vars" => dump_vars = true,
+            "--trace" => trace = true,
+            "--time" => time = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag `{}`\n{}", flag, RUN_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", RUN_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let (name, text) = match read_source(file.filter(|name| *name != "-")) {
+        Ok(source) => source,
+        Err((name, err)) => {
+            eprintln!("{}: {}", name, err);
+            return 2;
+        }
+    };
+
+    if dump_tokens {
+        match parser.tokenize(&text) {
+            Ok(tokens) => {
+                for (line, token) in tokens {
+                    println!("{}: {:?}", line, token);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}:{}: syntax error: {}", name, parser.line(), err);
+                return 1;
+            }
+        }
+    }
+
+    let ast = match parser.parse(&text) {
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}:{}: syntax error: {}", name, parser.line(), err);
+            return 1;
+        }
+    };
+    if dump_ast {
+        println!("{:#?}", ast);
+    }
+
+    let mut interpreter = Interpreter::with_dialect(parser.dialect());
+    interpreter.set_trace(trace);
+    interpreter.set_fuel(fuel);
+    interpreter.set_timeout(timeout);
+    interpreter.set_statement_lines(parser.statement_lines());
+    interpreter.set_output(write_stdout);
+    interpreter.set_input(read_stdin);
+    // whatever nesting the parser allowed, the run allows too
+    interpreter.set_limits(Limits {
+        max_depth: Some(parser.max_depth()),
+        ..interpreter.limits()
+    });
+    let res = interpreter.execute(&ast);
+
+    for vars in res.trace.iter().flatten() {
+        println!("{}", format_vars(vars));
+    }
+    if dump_vars {
+        println!("{}", format_vars(&res.program_vars));
+    }
+    if time {
+        eprintln!("{}: ran in {:?}", name, res.elapsed);
+    }
+
+    match res.status {
+        Ok(()) => 0,
+        Err(err) => {
+            // calls carry the function, what went wrong and where, also
+            // when `check` rejects them before the run
+            let (line, message) = match interpreter.native_error() {
+                Some(err) => (err.line.or(interpreter.line()), err.to_string()),
+                None => (interpreter.line(), err.to_string()),
+            };
+            match line {
+                Some(line) => eprintln!("{}:{}: runtime error: {}", name, line, message),
+                None => eprintln!("{}: runtime error: {}", name, message),
+            }
+            3
+        }
+    }
+}
+
+// the value of a `--dialect` flag, or the exit code once a missing or
+// unknown dialect is reported
+fn dialect_flag<'a>(args: &mut impl Iterator<Item = &'a String>, usage: &str) -> Result<Dialect, i32> {
+    match args.next().map(|name| name.parse()) {
+        Some(Ok(dialect)) => Ok(dialect),
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            Err(2)
+        }
+        None => {
+            eprintln!("{}", usage);
+            Err(2)
+        }
+    }
+}
+
+// where `Write` prints to while a program runs
+fn write_stdout(text: &str) {
+    print!("{}", text);
+}
+
+// where `ReadLn` reads from, showing what was written so far first, e.g.
+// a prompt
+fn read_stdin() -> Option<String> {
+    let _ = io::stdout().flush();
+    io::stdin().lines().next()?.ok()
+}
+
+// `a = 1, b = 2`, sorted by name
+fn format_vars(vars: &HashMap<String, f64>) -> String {
+    let mut vars: Vec<_> = vars.iter().collect();
+    vars.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+
+    let vars: Vec<String> = vars.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+    vars.join(", ")
+}
+
+fn repl_cmd(args: &[String]) -> i32 {
+    let mut dialect = Default::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => match dialect_flag(&mut args, REPL_USAGE) {
+                Ok(name) => dialect = name,
+                Err(code) => return code,
+            },
+            _ => {
+                eprintln!("{}", REPL_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    repl::run(dialect)
+}
+
+// Debugs a program from a file, reading commands from stdin. Exits with 1
+// on a syntax error.
+fn debug_cmd(args: &[String]) -> i32 {
+    let mut parser = Parser::new();
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => match dialect_flag(&mut args, DEBUG_USAGE) {
+                Ok(dialect) => parser.set_dialect(dialect),
+                Err(code) => return code,
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag `{}`\n{}", flag, DEBUG_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", DEBUG_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    // stdin is for commands
+    let Some(file) = file else {
+        eprintln!("{}", DEBUG_USAGE);
+        return 2;
+    };
+    let (name, text) = match read_source(Some(file)) {
+        Ok(source) => source,
+        Err((name, err)) => {
+            eprintln!("{}: {}", name, err);
+            return 2;
+        }
+    };
+
+    let ast = match parser.parse(&text) {
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}:{}: syntax error: {}", name, parser.line(), err);
+            return 1;
+        }
+    };
+    let lines = parser.statement_lines().to_vec();
+
+    debug::run(name, &text, parser.dialect(), ast, lines)
+}
+
+// Formats the given files (or stdin) and prints the result. With `--check`
+// only the names of inputs that aren't formatted are printed and the exit
+// code is 1 if there are any, `--write` rewrites the files in place.
+fn format_cmd(args: &[String]) -> i32 {
+    let mut check = false;
+    let mut write = false;
+    let mut options = FormatOptions::default();
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--write" => write = true,
+            "--dialect" => match dialect_flag(&mut args, FORMAT_USAGE) {
+                Ok(dialect) => options.dialect = dialect,
+                Err(code) => return code,
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag `{}`\n{}", flag, FORMAT_USAGE);
+                return 2;
+            }
+            file => files.push(file),
+        }
+    }
+
+    if write && (check || files.is_empty()) {
+        eprintln!("{}", FORMAT_USAGE);
+        return 2;
+    }
+
+    let inputs = if files.is_empty() {
+        let mut text = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut text) {
+            eprintln!("<stdin>: {}", err);
+            return 2;
+        }
+        vec![("<stdin>", text)]
+    } else {
+        let mut inputs = Vec::new();
+        for file in files {
+            match fs::read_to_string(file) {
+                Ok(text) => inputs.push((file, text)),
+                Err(err) => {
+                    eprintln!("{}: {}", file, err);
+                    return 2;
+                }
+            }
+        }
+        inputs
+    };
+
+    let mut status = 0;
+    for (name, text) in inputs {
+        let formatted = match cst::parse_with_line(&text, options.dialect) {
+            Ok(tree) => format_tree(&tree, &options),
+            Err((line, err)) => {
+                eprintln!("{}:{}: syntax error: {}", name, line, err);
+                return 2;
+            }
+        };
+
+        if check {
+            if formatted != text {
+                println!("{}", name);
+                status = 1;
+            }
+        } else if write {
+            if let Err(err) = fs::write(name, formatted) {
+                eprintln!("{}: {}", name, err);
+                return 2;
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+    status
+}
+
+// Parses a file (or stdin) and prints the AST in the `Debug` notation, as
+// JSON for other tools, as an S-expression or as a Graphviz graph. With
+// `--fold` the tree is printed before and after constant folding. Exits
+// with 1 if the program doesn't parse.
+fn dump_ast_cmd(args: &[String]) -> i32 {
+    let mut output = "debug";
+    let mut fold = false;
+    let mut parser = Parser::new();
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some(format @ ("debug" | "json" | "sexpr" | "dot")) => output = format,
+                _ => {
+                    eprintln!("{}", DUMP_AST_USAGE);
+                    return 2;
+                }
+            },
+            "--dialect" => match dialect_flag(&mut args, DUMP_AST_USAGE) {
+                Ok(dialect) => parser.set_dialect(dialect),
+                Err(code) => return code,
+            },
+            "--calculator" => parser.set_calculator(true),
+            "--fold" => fold = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag `{}`\n{}", flag, DUMP_AST_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", DUMP_AST_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let (name, text) = match read_source(file) {
+        Ok(source) => source,
+        Err((name, err)) => {
+            eprintln!("{}: {}", name, err);
+            return 2;
+        }
+    };
+
+    let ast = match parser.parse(&text) {
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}:{}: syntax error: {}", name, parser.line(), err);
+            return 1;
+        }
+    };
+
+    let trees = if fold {
+        let folded = const_fold(ast.clone(), parser.dialect());
+        vec![(Some("before:"), ast), (Some("after:"), folded)]
+    } else {
+        vec![(None, ast)]
+    };
+
+    for (title, ast) in trees {
+        if let Some(title) = title {
+            println!("{}", title);
+        }
+        if let Err(err) = dump_ast(&ast, output) {
+            eprintln!("{}: {}", name, err);
+            return 2;
+        }
+    }
+    0
+}
+
+fn dump_ast(ast: &Node, output: &str) -> serde_json::Result<()> {
+    match output {
+        "json" => println!("{}", serde_json::to_string_pretty(ast)?),
+        "sexpr" => println!("{}", ast.to_sexpr()),
+        "dot" => print!("{}", ast.to_dot()),
+        _ => println!("{:#?}", ast),
+    }
+    Ok(())
+}
+
+// Compiles a program to bytecode and saves it next to the source as
+// `FILE.pcode`, or to `-o OUT`. Exits with 1 if the program doesn't parse
+// or calls a function.
+fn compile_cmd(args: &[String]) -> i32 {
+    let mut parser = Parser::new();
+    let mut fold = false;
+    let mut out = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => match dialect_flag(&mut args, COMPILE_USAGE) {
+                Ok(dialect) => parser.set_dialect(dialect),
+                Err(code) => return code,
+            },
+            "--calculator" => parser.set_calculator(true),
+            "--fold" => fold = true,
+            "-o" => match args.next() {
+                Some(name) => out = Some(name.clone()),
+                None => {
+                    eprintln!("{}", COMPILE_USAGE);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown flag `{}`\n{}", flag, COMPILE_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", COMPILE_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let Some(file) = file else {
+        eprintln!("{}", COMPILE_USAGE);
+        return 2;
+    };
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    let ast = match parser.parse(&text) {
+        Ok(ast) if fold => const_fold(ast, parser.dialect()),
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}:{}: syntax error: {}", file, parser.line(), err);
+            return 1;
+        }
+    };
+
+    let out = out.unwrap_or_else(|| {
+        Path::new(file).with_extension("pcode").to_string_lossy().into_owned()
+    });
+    // folding keeps statements where they are, so the lines still match
+    let mut program = match compile_or_report(file, &ast, parser.statement_lines()) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+    program.set_dialect(parser.dialect());
+    if let Err(err) = program.save(&out) {
+        eprintln!("{}: {}", out, err);
+        return 2;
+    }
+    0
+}
+
+// Translates a program to C99 and prints it, or saves it to `-o OUT`.
+// Exits with 1 if the program doesn't parse or calls a function.
+fn to_c_cmd(args: &[String]) -> i32 {
+    let mut parser = Parser::new();
+    let mut fold = false;
+    let mut out = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => match dialect_flag(&mut args, TO_C_USAGE) {
+                Ok(dialect) => parser.set_dialect(dialect),
+                Err(code) => return code,
+            },
+            "--calculator" => parser.set_calculator(true),
+            "--fold" => fold = true,
+            "-o" => match args.next() {
+                Some(name) => out = Some(name),
+                None => {
+                    eprintln!("{}", TO_C_USAGE);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown flag `{}`\n{}", flag, TO_C_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", TO_C_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let Some(file) = file else {
+        eprintln!("{}", TO_C_USAGE);
+        return 2;
+    };
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    let ast = match parser.parse(&text) {
+        Ok(ast) if fold => const_fold(ast, parser.dialect()),
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}:{}: syntax error: {}", file, parser.line(), err);
+            return 1;
+        }
+    };
+
+    let code = match compile_or_report(file, &ast, parser.statement_lines()) {
+        Ok(program) => to_c(&program, parser.dialect()),
+        Err(code) => return code,
+    };
+    match out {
+        Some(out) => {
+            if let Err(err) = fs::write(out, code) {
+                eprintln!("{}: {}", out, err);
+                return 2;
+            }
+        }
+        None => print!("{}", code),
+    }
+    0
+}
+
+// Compiles a program to a WebAssembly module and saves it next to the
+// source as `FILE.wasm`, `FILE.wat` with `--text`, or to `-o OUT`. Exits
+// with 1 if the program doesn't parse or calls a function.
+fn to_wasm_cmd(args: &[String]) -> i32 {
     let mut parser = Parser::new();
-    
-    let ast = parser.parse(
-        r"BEGIN
-            y := 2;
-            BEGIN
-                a := 3;
-                a := a;
-                b := 10 + a + 10 * y / 4;
-                c := a - b
-            END;
-            x := 11;
-        END."
-    ).unwrap();
-    
-    let mut interpreter = Interpreter::new();
-    println!("Vars: {:#?}", interpreter.interpret(ast));
+    let mut fold = false;
+    let mut text_format = false;
+    let mut out = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dialect" => match dialect_flag(&mut args, TO_WASM_USAGE) {
+                Ok(dialect) => parser.set_dialect(dialect),
+                Err(code) => return code,
+            },
+            "--calculator" => parser.set_calculator(true),
+            "--fold" => fold = true,
+            "--text" => text_format = true,
+            "-o" => match args.next() {
+                Some(name) => out = Some(name.clone()),
+                None => {
+                    eprintln!("{}", TO_WASM_USAGE);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown flag `{}`\n{}", flag, TO_WASM_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", TO_WASM_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let Some(file) = file else {
+        eprintln!("{}", TO_WASM_USAGE);
+        return 2;
+    };
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    let ast = match parser.parse(&text) {
+        Ok(ast) if fold => const_fold(ast, parser.dialect()),
+        Ok(ast) => ast,
+        Err(err) => {
+            eprintln!("{}:{}: syntax error: {}", file, parser.line(), err);
+            return 1;
+        }
+    };
+
+    let program = match compile_or_report(file, &ast, parser.statement_lines()) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+    let (extension, module) = if text_format {
+        ("wat", to_wat(&program, parser.dialect()).into_bytes())
+    } else {
+        ("wasm", to_wasm(&program, parser.dialect()))
+    };
+    let out = out.unwrap_or_else(|| {
+        Path::new(file).with_extension(extension).to_string_lossy().into_owned()
+    });
+    if let Err(err) = fs::write(&out, module) {
+        eprintln!("{}: {}", out, err);
+        return 2;
+    }
+    0
+}
+
+// Runs a `.pcode` file without its source. Exits with 3 on a run-time error.
+fn exec_cmd(args: &[String]) -> i32 {
+    let mut file = None;
+
+    for arg in args {
+        match arg.as_str() {
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag `{}`\n{}", flag, EXEC_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", EXEC_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let Some(file) = file else {
+        eprintln!("{}", EXEC_USAGE);
+        return 2;
+    };
+    let program = match Program::load(file) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    // the dialect is the one the program was compiled for
+    let mut vm = Vm::with_dialect(program.dialect());
+    vm.set_output(write_stdout);
+    vm.set_input(read_stdin);
+    match vm.run(&program) {
+        Ok(blocks) => {
+            // every finished block's variables, like `run --trace`
+            for vars in &blocks {
+                println!("{}", format_vars(vars));
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("{}: runtime error: {}", file, err);
+            3
+        }
+    }
+}
+
+// Prints the bytecode of a `.pcode` file, or of a source file compiled on
+// the fly, annotated with the source lines when the source is available.
+fn disasm_cmd(args: &[String]) -> i32 {
+    let mut source_file = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--source" => match args.next() {
+                Some(name) => source_file = Some(name.as_str()),
+                None => {
+                    eprintln!("{}", DISASM_USAGE);
+                    return 2;
+                }
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag `{}`\n{}", flag, DISASM_USAGE);
+                return 2;
+            }
+            name if file.is_none() => file = Some(name),
+            _ => {
+                eprintln!("{}", DISASM_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let Some(file) = file else {
+        eprintln!("{}", DISASM_USAGE);
+        return 2;
+    };
+
+    let (program, source) = if file.ends_with(".pcode") {
+        let program = match Program::load(file) {
+            Ok(program) => program,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                return 2;
+            }
+        };
+        let source = match source_file.map(fs::read_to_string) {
+            Some(Ok(source)) => Some(source),
+            Some(Err(err)) => {
+                eprintln!("{}: {}", source_file.unwrap(), err);
+                return 2;
+            }
+            None => None,
+        };
+        (program, source)
+    } else {
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                return 2;
+            }
+        };
+        let mut parser = Parser::new();
+        let program = match parser.parse(&source) {
+            Ok(ast) => match compile_or_report(file, &ast, parser.statement_lines()) {
+                Ok(program) => program,
+                Err(code) => return code,
+            },
+            Err(err) => {
+                eprintln!("{}:{}: syntax error: {}", file, parser.line(), err);
+                return 1;
+            }
+        };
+        (program, Some(source))
+    };
+
+    print!("{}", disassemble(&program, source.as_deref()));
+    0
+}
+
+// the bytecode of `ast`, or the exit code once a call it makes is reported
+fn compile_or_report(file: &str, ast: &Node, lines: &[u32]) -> Result<Program, i32> {
+    compile_with_lines(ast, lines).map_err(|err| {
+        match err.line() {
+            Some(line) => eprintln!("{}:{}: compile error: {}", file, line, err),
+            None => eprintln!("{}: compile error: {}", file, err),
+        }
+        1
+    })
+}
+
+// Reads the named file, or stdin when there is none.
+fn read_source(file: Option<&str>) -> Result<(&str, String), (&str, io::Error)> {
+    match file {
+        Some(name) => fs::read_to_string(name)
+            .map(|text| (name, text))
+            .map_err(|err| (name, err)),
+        None => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map(|_| ("<stdin>", text))
+                .map_err(|err| ("<stdin>", err))
+        }
+    }
 }
@@ -0,0 +1,231 @@
+//! `spi repl`: runs statements and expressions one at a time against one
+//! `Interpreter`, echoing the values of expressions.
+//!
+//! Input continues over several lines until its `BEGIN`s and `END`s
+//! balance. Lines starting with `:` are commands, see `HELP`.
+
+use std::{fs, process};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+use simple_pascal_ast::{
+    dialect::Dialect,
+    node::Node,
+    parser::Parser,
+    token::{keyword::Keyword, op::OpKind, Token},
+};
+use simple_pascal_interpreter::{interpreter::InterpreterErr, Interpreter};
+
+use crate::{read_stdin, write_stdout};
+
+const HELP: &str = ":vars         print the global variables
+:ast          print the tree of the last input
+:reset        forget all variables
+:load FILE    run the statements of a program, keeping its variables
+:help         print this message
+:quit         leave the REPL";
+
+pub fn run(dialect: Dialect) -> i32 {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+    let mut repl = Repl {
+        dialect,
+        interpreter: Interpreter::with_dialect(dialect),
+        last_input: Vec::new(),
+    };
+    repl.interpreter.set_output(write_stdout);
+    repl.interpreter.set_input(read_stdin);
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() { "spi> " } else { "...> " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C drops unfinished input, Ctrl-D leaves
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => return 0,
+            Err(err) => {
+                eprintln!("{}", err);
+                return 2;
+            }
+        };
+
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            let _ = editor.add_history_entry(line.trim());
+            repl.command(line.trim());
+            continue;
+        }
+        if input.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+
+        input.push_str(&line);
+        input.push('\n');
+        if !repl.is_complete(&input) {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(input.trim_end());
+        repl.eval(&input);
+        input.clear();
+    }
+}
+
+struct Repl {
+    dialect: Dialect,
+    interpreter: Interpreter,
+    // statements of the last input, for `:ast`
+    last_input: Vec<Node>,
+}
+
+impl Repl {
+    fn parser<'a>(&self) -> Parser<'a> {
+        let mut parser = Parser::calculator();
+        parser.set_dialect(self.dialect);
+        parser
+    }
+
+    // input that doesn't tokenize is complete: parsing reports the error
+    fn is_complete(&self, input: &str) -> bool {
+        let tokens = match self.parser().tokenize(input) {
+            Ok(tokens) => tokens,
+            Err(_) => return true,
+        };
+        let depth = tokens.iter().fold(0, |depth, (_, token)| match token {
+            Token::Keyword(Keyword::Begin) => depth + 1,
+            Token::Keyword(Keyword::End) => depth - 1,
+            _ => depth,
+        });
+        depth <= 0
+    }
+
+    fn eval(&mut self, input: &str) {
+        let mut parser = self.parser();
+        let statements = match parser.parse_statements(input) {
+            Ok(statements) => statements,
+            Err(err) => {
+                eprintln!("syntax error: {}", err);
+                return;
+            }
+        };
+
+        let mut lines = parser.statement_lines();
+        for statement in &statements {
+            let (statement_lines, rest) = lines.split_at(statement_count(statement).min(lines.len()));
+            lines = rest;
+            self.interpreter.set_statement_lines(statement_lines);
+
+            match self.interpreter.eval(statement) {
+                Ok(value) if is_expr(statement) => println!("{}", value),
+                Ok(_) => (),
+                Err(err) => {
+                    self.runtime_error(None, err);
+                    break;
+                }
+            }
+        }
+        self.last_input = statements;
+    }
+
+    fn command(&mut self, line: &str) {
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+
+        match (command, arg) {
+            (":vars", "") => {
+                let mut vars: Vec<_> = self.interpreter.globals().collect();
+                vars.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+                for (name, value) in vars {
+                    println!("{} = {}", name, value);
+                }
+            }
+            (":ast", "") => {
+                for statement in &self.last_input {
+                    println!("{:#?}", statement);
+                }
+            }
+            (":reset", "") => self.interpreter.reset(),
+            (":load", file) if !file.is_empty() => self.load(file),
+            (":help", "") => println!("{}", HELP),
+            (":quit" | ":q", "") => process::exit(0),
+            _ => eprintln!("unknown command `{}`, try :help", line),
+        }
+    }
+
+    // the program block's statements run in the global frame, so its
+    // variables stay around
+    fn load(&mut self, file: &str) {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                return;
+            }
+        };
+
+        let mut parser = self.parser();
+        let statements = match parser.parse(&text) {
+            Ok(Node::Compound(compound)) => compound.children,
+            Ok(node) => [node].into(),
+            Err(err) => {
+                eprintln!("{}:{}: syntax error: {}", file, parser.line(), err);
+                return;
+            }
+        };
+
+        // the program block comes first
+        let mut lines = parser.statement_lines().get(1..).unwrap_or_default();
+        for statement in &statements {
+            let (statement_lines, rest) = lines.split_at(statement_count(statement).min(lines.len()));
+            lines = rest;
+            self.interpreter.set_statement_lines(statement_lines);
+
+            if let Err(err) = self.interpreter.eval(statement) {
+                self.runtime_error(Some(file), err);
+                return;
+            }
+        }
+    }
+
+    // calls carry the function, what went wrong and where
+    fn runtime_error(&self, file: Option<&str>, err: InterpreterErr) {
+        let (line, message) = match self.interpreter.native_error() {
+            Some(err) => (err.line, err.to_string()),
+            None => (None, err.to_string()),
+        };
+
+        match (file, line) {
+            (Some(file), Some(line)) => eprintln!("{}:{}: runtime error: {}", file, line, message),
+            (Some(file), None) => eprintln!("{}: runtime error: {}", file, message),
+            (None, Some(line)) => eprintln!("line {}: runtime error: {}", line, message),
+            (None, None) => eprintln!("runtime error: {}", message),
+        }
+    }
+}
+
+// entries of `Parser::statement_lines` a statement and the ones nested in
+// it take up
+fn statement_count(node: &Node) -> usize {
+    match node {
+        Node::Compound(compound) => 1 + compound.children.iter().map(statement_count).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+// values of expressions are echoed, those of other statements aren't
+fn is_expr(node: &Node) -> bool {
+    match node {
+        Node::BinOp(bin_op) => bin_op.op != OpKind::AssignEq,
+        Node::UnaryOp(_) | Node::Literal(_) | Node::Ident(_) | Node::Call(_) => true,
+        _ => false,
+    }
+}
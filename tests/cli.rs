@@ -0,0 +1,268 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn spi(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_spi"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn run_dump_vars_and_trace() {
+    let output = spi(
+        &["run", "--dump-vars"],
+        "BEGIN y := 2; BEGIN b := 7 DIV y; a := 3 END; x := y / 4 END.",
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "x = 0.5, y = 2\n");
+
+    let output = spi(
+        &["run", "--trace", "--dump-vars"],
+        "BEGIN y := 2; BEGIN b := 7 DIV y; a := 3 END; x := y / 4 END.",
+    );
+    assert_eq!(stdout(&output), "a = 3, b = 3\nx = 0.5, y = 2\nx = 0.5, y = 2\n");
+
+    // the variables are printed even if the program fails
+    let output = spi(&["run", "--dump-vars"], "BEGIN y := 2; x := y DIV 0 END.");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "y = 2\n");
+
+    // nothing is printed without dump flags
+    let output = spi(&["run", "-"], "BEGIN x := 1 END.");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn run_dump_tokens_and_ast() {
+    let output = spi(&["run", "--dump-tokens", "--dump-ast"], "BEGIN\n  x := 1\nEND.");
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with(
+        "1: Keyword(Begin)\n2: Ident(\"x\")\n2: Op(AssignEq)\n2: Literal(Integer(\"1\"))\n3: Keyword(End)\n3: Delim(Dot)\nCompound(\n"
+    ));
+}
+
+#[test]
+fn run_exit_codes() {
+    let output = spi(&["run"], "BEGIN\n  x := (1 + 2;\nEND.");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "<stdin>:2: syntax error: Expected close delimiter for Paren\n");
+
+    // run-time errors come with the line of the failed statement
+    let output = spi(&["run"], "BEGIN\n  y := 0;\n  x := 1 DIV y\nEND.");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stderr(&output), "<stdin>:3: runtime error: division by zero\n");
+
+    // `spi` registers no host functions
+    let output = spi(&["run"], "BEGIN\n  x := 1;\n  Beep(x)\nEND.");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        stderr(&output),
+        "<stdin>:3: runtime error: call of an undefined function: `Beep` isn't defined\n"
+    );
+
+    // and compiled programs can't call any
+    let path = std::env::temp_dir().join(format!("spi-call-{}.pas", std::process::id()));
+    std::fs::write(&path, "BEGIN\n  x := 1;\n  Beep(x)\nEND.").unwrap();
+    let output = spi(&["to-c", path.to_str().unwrap()], "");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{}:3: compile error: `Beep` can't be called, compiled programs have no host functions\n",
+            path.display()
+        )
+    );
+
+    let output = spi(&["run", "--fuel", "3", "--dump-vars"], "BEGIN x := 1; y := 2 END.");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "x = 1\n");
+    assert_eq!(stderr(&output), "<stdin>:1: runtime error: step budget exhausted\n");
+
+    // nesting past the default limit needs `--max-depth`, which the run
+    // follows too
+    let minuses = format!("BEGIN x := {}1 END.", "-".repeat(300));
+    let output = spi(&["run"], &minuses);
+    assert_eq!(output.status.code(), Some(1));
+    let output = spi(&["run", "--max-depth", "400", "--dump-vars"], &minuses);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "x = 1\n");
+
+    let sum = format!("BEGIN x := 0{} END.", " + 1".repeat(2000));
+    let output = spi(&["run"], &sum);
+    assert_eq!(output.status.code(), Some(1));
+    let output = spi(&["run", "--max-operators", "2000", "--dump-vars"], &sum);
+    assert_eq!(stdout(&output), "x = 2000\n");
+
+    let output = spi(&["run", "missing.pas"], "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = spi(&["run", "--bogus"], "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = spi(&[], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("usage: spi <command>"));
+}
+
+#[test]
+fn dump_ast() {
+    let output = spi(&["dump-ast", "--format", "sexpr"], "BEGIN x := 1 END.");
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = spi(&["dump-ast"], "BEGIN\n  x := (1\nEND.");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "<stdin>:3: syntax error: Expected close delimiter for Paren\n");
+}
+
+#[test]
+fn format() {
+    let output = spi(&["format"], "BEGIN x:=1;y:=x*2 END.");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("x := 1;"));
+
+    let output = spi(&["format"], "BEGIN\n  x := (1\nEND.");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "<stdin>:3: syntax error: Expected close delimiter for Paren\n");
+}
+
+#[test]
+fn compile_and_exec() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("spi-exec-{}.pas", std::process::id()));
+    let pcode = dir.join(format!("spi-exec-{}.pcode", std::process::id()));
+    let (source, pcode) = (source.to_str().unwrap(), pcode.to_str().unwrap());
+
+    // `exec` prints what `run --trace` does
+    let program = "BEGIN\n  BEGIN y := 2.5 END;\n  x := 32767\nEND.";
+    std::fs::write(source, program).unwrap();
+    let output = spi(&["compile", "-o", pcode, source], "");
+    assert_eq!(output.status.code(), Some(0));
+    let output = spi(&["exec", pcode], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "y = 2.5\nx = 32767\n");
+    assert_eq!(stdout(&output), stdout(&spi(&["run", "--trace", source], "")));
+
+    // and runs with the dialect the program was compiled for
+    std::fs::write(source, "BEGIN x := 2147483647; x := x + 1 END.").unwrap();
+    let output = spi(&["compile", "-o", pcode, source], "");
+    assert_eq!(output.status.code(), Some(0));
+    let output = spi(&["exec", pcode], "");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stderr(&output), format!("{}: runtime error: integer overflow\n", pcode));
+    let output = spi(&["compile", "--dialect", "fpc", "-o", pcode, source], "");
+    assert_eq!(output.status.code(), Some(0));
+    let output = spi(&["exec", pcode], "");
+    assert_eq!(stdout(&output), "x = 2147483648\n");
+
+    std::fs::write(source, "BEGIN\n  x := (1\nEND.").unwrap();
+    let output = spi(&["compile", "-o", pcode, source], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), format!("{}:3: syntax error: Expected close delimiter for Paren\n", source));
+    let output = spi(&["disasm", source], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), format!("{}:3: syntax error: Expected close delimiter for Paren\n", source));
+
+    std::fs::remove_file(source).unwrap();
+    std::fs::remove_file(pcode).unwrap();
+}
+
+#[test]
+fn standard_procedures() {
+    let source = std::env::temp_dir().join(format!("spi-io-{}.pas", std::process::id()));
+    let source = source.to_str().unwrap();
+
+    // the program comes from a file, so `ReadLn` reads stdin
+    std::fs::write(source, "BEGIN\n  Write('a, b? ');\n  ReadLn(a, b);\n  WriteLn('sum: ', a + b)\nEND.").unwrap();
+    let output = spi(&["run", source], "2 3.5\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "a, b? sum: 5.5\n");
+
+    let output = spi(&["run", source], "2\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "a, b? ");
+    assert_eq!(stderr(&output), format!("{}:3: runtime error: invalid or missing input\n", source));
+
+    std::fs::write(source, "BEGIN\n  x := (1\nEND.").unwrap();
+    let output = spi(&["to-c", source], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), format!("{}:3: syntax error: Expected close delimiter for Paren\n", source));
+    let output = spi(&["to-wasm", "--text", source], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), format!("{}:3: syntax error: Expected close delimiter for Paren\n", source));
+
+    std::fs::remove_file(source).unwrap();
+}
+
+#[test]
+fn repl() {
+    let dir = std::env::temp_dir().join(format!("spi-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("rate.pas");
+    std::fs::write(&file, "BEGIN rate := 5; BEGIN tmp := 1 END END.").unwrap();
+
+    let input = format!(
+        "x := 2; y := x * 3\nx + y\nBEGIN\n  z := 1;\n  x := z + x\nEND\n:load {}\n:vars\nz\n:reset\n:vars\nx\n",
+        file.display()
+    );
+    let output = spi(&["repl"], &input);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "8\nrate = 5\nx = 3\ny = 6\n");
+    assert_eq!(
+        stderr(&output),
+        "runtime error: use of an undefined variable\nruntime error: use of an undefined variable\n"
+    );
+}
+
+#[test]
+fn debug() {
+    let dir = std::env::temp_dir().join(format!("spi-cli-debug-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("blocks.pas");
+    std::fs::write(&file, "BEGIN\n  y := 2;\n  BEGIN\n    a := y + 1\n  END;\n  x := a\nEND.\n").unwrap();
+    let file = file.to_str().unwrap();
+
+    let input = "break 4\nwatch y * 10\ncontinue\nvars\nprint a\nnext\nnext\nstep\n";
+    let output = spi(&["debug", file], input);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "1: BEGIN\nwatch 1: y * 10 = <use of an undefined variable>\n\
+         breakpoint on line 4\n4: a := y + 1\nwatch 1: y * 10 = 20\n\
+         block 2: -\nblock 1: y = 2\nglobal: -\n\
+         6: x := a\nwatch 1: y * 10 = 20\n"
+    );
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "runtime error: use of an undefined variable\n\
+             {}:6: runtime error: use of an undefined variable\n\
+             the program isn't running, try run\n",
+            file
+        )
+    );
+
+    let output = spi(&["debug"], "");
+    assert_eq!(output.status.code(), Some(2));
+}